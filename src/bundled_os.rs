@@ -0,0 +1,25 @@
+//! bundled_os
+//! The eight Jack OS classes written in Jack. They are shipped in the `os` directory of the
+//! crate, compiled by this compiler like any other class and can be linked into a program to
+//! make its .vm and .asm output self-contained.
+
+/// Class names and Jack source code of the OS classes, in the order in which `Sys.init`
+/// initializes them
+pub const CLASSES: [(&str, &str); 8] = [
+    ("Memory", include_str!("../os/Memory.jack")),
+    ("Math", include_str!("../os/Math.jack")),
+    ("Output", include_str!("../os/Output.jack")),
+    ("Screen", include_str!("../os/Screen.jack")),
+    ("Keyboard", include_str!("../os/Keyboard.jack")),
+    ("Array", include_str!("../os/Array.jack")),
+    ("String", include_str!("../os/String.jack")),
+    ("Sys", include_str!("../os/Sys.jack")),
+];
+
+/// The Jack source code of an OS class
+pub fn source(class_name: &str) -> Option<&'static str> {
+    CLASSES
+        .iter()
+        .find(|(name, _)| *name == class_name)
+        .map(|(_, source)| *source)
+}
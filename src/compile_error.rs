@@ -0,0 +1,515 @@
+//! compile_error
+
+use diagnostics::Diagnostic;
+use jack_ast::FunctionKind;
+use jack_tokenizer::{Span, Token};
+
+use std::error::Error;
+use std::fmt;
+
+/// Number of errors after which the parser and the compiler give up, unless configured otherwise
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+/// Everything that can go wrong while compiling a Jack class.
+/// Every variant carries the span of the offending source text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    /// A token that does not fit the grammar at this position
+    UnexpectedToken {
+        /// The token that was read
+        found: Token,
+        /// Descriptions of the tokens that would have been valid
+        expected: Vec<String>,
+        /// Location of `found`
+        span: Span,
+    },
+    /// The input ended while the grammar still expected more tokens
+    UnexpectedEof {
+        /// Descriptions of the tokens that would have been valid
+        expected: Vec<String>,
+        /// Location directly behind the last token
+        span: Span,
+    },
+    /// A string literal without the closing `"` on its line
+    UnterminatedString {
+        /// Location of the string literal up to the end of the line
+        span: Span,
+    },
+    /// A `/*` comment without the closing `*/`
+    UnterminatedComment {
+        /// Location of the `/*`
+        span: Span,
+    },
+    /// A character that cannot appear in Jack code outside string literals, which includes all
+    /// non-ASCII characters
+    IllegalCharacter {
+        /// The character
+        character: char,
+        /// Location of the character
+        span: Span,
+    },
+    /// A word that starts with a digit but is not an integer constant
+    InvalidIdentifier {
+        /// The word as it is written
+        name: String,
+        /// Location of the word
+        span: Span,
+    },
+    /// An integer constant above 32767, which does not fit into a Hack word
+    IntegerOutOfRange {
+        /// The constant as it is written
+        value: String,
+        /// Location of the constant
+        span: Span,
+    },
+    /// A variable that is used but not declared in the class or the subroutine
+    UndefinedVariable {
+        /// Name of the variable
+        name: String,
+        /// Location of the usage
+        span: Span,
+    },
+    /// A variable name that is declared twice in the same scope
+    DuplicateDeclaration {
+        /// The name that is already in use
+        name: String,
+        /// Location of the second declaration
+        span: Span,
+    },
+    /// A constructor whose declared return type is not the class it belongs to
+    BadConstructorReturnType {
+        /// Name of the class the constructor belongs to
+        class_name: String,
+        /// The declared return type
+        found: String,
+        /// Location of the declared return type
+        span: Span,
+    },
+    /// A value whose type does not fit where it is used, found by the optional type checker
+    TypeMismatch {
+        /// The type that is required, `void` if no value is allowed
+        expected: String,
+        /// The type of the value, `void` if a value is missing
+        found: String,
+        /// Location of the value
+        span: Span,
+    },
+    /// An operator applied to a value of a type it does not work with, found by the optional
+    /// type checker
+    InvalidOperand {
+        /// The operator, `[]` for an array access
+        operator: String,
+        /// The type of the operand
+        found: String,
+        /// Location of the operand
+        span: Span,
+    },
+    /// A call of a subroutine of a class that neither the program nor the OS defines
+    UndefinedClass {
+        /// Name of the class
+        name: String,
+        /// Location of the class name, or of the variable the subroutine is called on
+        span: Span,
+    },
+    /// A call of a subroutine that the class does not define
+    UndefinedSubroutine {
+        /// Name of the class
+        class_name: String,
+        /// Name of the subroutine
+        name: String,
+        /// Location of the subroutine name
+        span: Span,
+    },
+    /// A method called on a class name, or a function or constructor called on an object
+    WrongCallStyle {
+        /// Full name of the subroutine, `Class.subroutine`
+        name: String,
+        /// What the subroutine is
+        kind: FunctionKind,
+        /// Location of the call
+        span: Span,
+    },
+    /// A call with more or fewer arguments than the subroutine has parameters
+    WrongNumberOfArguments {
+        /// Full name of the subroutine, `Class.subroutine`
+        name: String,
+        /// Number of parameters
+        expected: usize,
+        /// Number of arguments
+        found: usize,
+        /// Location of the call
+        span: Span,
+    },
+    /// A method of the class called without an object inside a function, which has no `this`
+    MethodCallInFunction {
+        /// Name of the method
+        name: String,
+        /// Name of the function containing the call
+        function: String,
+        /// Location of the call
+        span: Span,
+    },
+    /// A field used inside a function, which has no object to read the field from
+    FieldInFunction {
+        /// Name of the field
+        name: String,
+        /// Name of the function using the field
+        function: String,
+        /// Location of the field name
+        span: Span,
+    },
+    /// `this` used inside a function
+    ThisInFunction {
+        /// Name of the function using `this`
+        function: String,
+        /// Location of `this`
+        span: Span,
+    },
+    /// A subroutine with a return type whose end can be reached without a `return`
+    MissingReturn {
+        /// Name of the subroutine
+        function: String,
+        /// The declared return type
+        return_type: String,
+        /// Location of the `}` that closes the subroutine
+        span: Span,
+    },
+    /// A constructor whose end can be reached without a `return`, or with a `return;` that has no
+    /// value
+    MissingReturnThis {
+        /// Name of the constructor
+        constructor: String,
+        /// Location of the `}` that closes the constructor, or of the `return;`
+        span: Span,
+    },
+    /// A constructor returning something other than `this`, reported as a warning
+    ConstructorReturnsOther {
+        /// Name of the constructor
+        constructor: String,
+        /// Location of the returned value, or of a `return;` without one
+        span: Span,
+    },
+    /// A function or method named `new`, which callers take for a constructor, reported as a
+    /// warning
+    NewIsNotAConstructor {
+        /// Name of the class
+        class_name: String,
+        /// Function or method
+        kind: FunctionKind,
+        /// Location of the name
+        span: Span,
+    },
+    /// `return;` without a value in a subroutine with a return type
+    MissingReturnValue {
+        /// Name of the subroutine
+        function: String,
+        /// The declared return type
+        return_type: String,
+        /// Location of the `return` statement
+        span: Span,
+    },
+    /// `return` with a value in a `void` subroutine
+    ReturnValueInVoid {
+        /// Name of the subroutine
+        function: String,
+        /// Location of the returned value
+        span: Span,
+    },
+    /// A subroutine called on a variable that is not an object
+    NotAnObject {
+        /// Name of the variable
+        name: String,
+        /// The type of the variable
+        found: String,
+        /// Location of the variable
+        span: Span,
+    },
+}
+
+impl CompileError {
+    /// Creates an `UnexpectedToken` error from a list of expected alternatives
+    pub fn unexpected_token(found: &Token, expected: &[&str], span: Span) -> Self {
+        CompileError::UnexpectedToken {
+            found: found.clone(),
+            expected: expected.iter().map(|s| s.to_string()).collect(),
+            span,
+        }
+    }
+
+    /// Location of the source text that caused the error
+    pub fn span(&self) -> Span {
+        match self {
+            CompileError::UnexpectedToken { span, .. } => *span,
+            CompileError::UnexpectedEof { span, .. } => *span,
+            CompileError::UnterminatedString { span } => *span,
+            CompileError::UnterminatedComment { span } => *span,
+            CompileError::IllegalCharacter { span, .. } => *span,
+            CompileError::InvalidIdentifier { span, .. } => *span,
+            CompileError::IntegerOutOfRange { span, .. } => *span,
+            CompileError::UndefinedVariable { span, .. } => *span,
+            CompileError::DuplicateDeclaration { span, .. } => *span,
+            CompileError::BadConstructorReturnType { span, .. } => *span,
+            CompileError::TypeMismatch { span, .. } => *span,
+            CompileError::InvalidOperand { span, .. } => *span,
+            CompileError::UndefinedClass { span, .. } => *span,
+            CompileError::UndefinedSubroutine { span, .. } => *span,
+            CompileError::WrongCallStyle { span, .. } => *span,
+            CompileError::WrongNumberOfArguments { span, .. } => *span,
+            CompileError::MethodCallInFunction { span, .. } => *span,
+            CompileError::FieldInFunction { span, .. } => *span,
+            CompileError::ThisInFunction { span, .. } => *span,
+            CompileError::MissingReturn { span, .. } => *span,
+            CompileError::MissingReturnThis { span, .. } => *span,
+            CompileError::ConstructorReturnsOther { span, .. } => *span,
+            CompileError::NewIsNotAConstructor { span, .. } => *span,
+            CompileError::MissingReturnValue { span, .. } => *span,
+            CompileError::ReturnValueInVoid { span, .. } => *span,
+            CompileError::NotAnObject { span, .. } => *span,
+        }
+    }
+}
+
+impl<'a> From<&'a CompileError> for Diagnostic {
+    fn from(error: &'a CompileError) -> Self {
+        let diagnostic = Diagnostic::error(error.to_string()).with_span(error.span());
+        match error {
+            CompileError::UnterminatedString { .. } => {
+                diagnostic.with_note("a string literal ends with a `\"` on the line it starts on")
+            }
+            CompileError::UnterminatedComment { .. } => {
+                diagnostic.with_help("close the comment with `*/`")
+            }
+            CompileError::IllegalCharacter { character, .. } if character.is_ascii() => {
+                diagnostic.with_note("identifiers consist of letters, digits and `_`")
+            }
+            CompileError::IllegalCharacter { .. } => {
+                diagnostic.with_note("outside string literals, Jack code consists of ASCII characters only")
+            }
+            CompileError::InvalidIdentifier { .. } => {
+                diagnostic.with_note("identifiers cannot start with a digit")
+            }
+            CompileError::IntegerOutOfRange { .. } => diagnostic
+                .with_note("integer constants range from 0 to 32767, the smallest value can be written as `-32768`"),
+            CompileError::UndefinedVariable { .. } => diagnostic.with_help(
+                "declare it with `var`, as a parameter, or as a `field` or `static` of the class",
+            ),
+            CompileError::DuplicateDeclaration { .. } => diagnostic
+                .with_note("locals and parameters share one scope, fields and statics share another"),
+            CompileError::BadConstructorReturnType { class_name, .. } => diagnostic.with_help(
+                format!("declare the constructor as `constructor {} ...`", class_name),
+            ),
+            CompileError::TypeMismatch {
+                expected, found, ..
+            } if expected != "void" && found != "void" => diagnostic.with_note(
+                "`int` and `char` are interchangeable, `null` and `Array` fit every object",
+            ),
+            CompileError::WrongCallStyle {
+                kind: FunctionKind::Jmethod,
+                ..
+            } => diagnostic.with_help("call the method on an object, e.g. `object.method()`"),
+            CompileError::WrongCallStyle { name, .. } => diagnostic.with_help(format!(
+                "call it on the class, e.g. `{}()`",
+                name
+            )),
+            CompileError::MethodCallInFunction { function, .. } => diagnostic.with_help(format!(
+                "call the method on an object, or declare `{}` as a method",
+                function
+            )),
+            CompileError::FieldInFunction { function, .. }
+            | CompileError::ThisInFunction { function, .. } => diagnostic.with_help(format!(
+                "functions have no object, declare `{}` as a method to use one",
+                function
+            )),
+            CompileError::MissingReturn { .. } => diagnostic.with_note(
+                "a `return` inside a `while` loop or an `if` without `else` does not cover every path",
+            ),
+            CompileError::MissingReturnThis { .. } => {
+                diagnostic.with_help("end the constructor with `return this;`")
+            }
+            CompileError::ConstructorReturnsOther { .. } => {
+                diagnostic.with_note("callers of a constructor expect the object it created")
+            }
+            CompileError::NewIsNotAConstructor { .. } => {
+                diagnostic.with_help("declare it as a `constructor`, or give it another name")
+            }
+            CompileError::ReturnValueInVoid { .. } => {
+                diagnostic.with_help("remove the value, or declare the type the subroutine returns")
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+/// "1 argument", "2 arguments"
+fn count_arguments(count: usize) -> String {
+    if count == 1 {
+        "1 argument".to_string()
+    } else {
+        format!("{} arguments", count)
+    }
+}
+
+/// The keyword of a subroutine kind
+fn kind_name(kind: FunctionKind) -> &'static str {
+    match kind {
+        FunctionKind::Jmethod => "method",
+        FunctionKind::Jconstructor => "constructor",
+        FunctionKind::Jfunction => "function",
+    }
+}
+
+/// Joins alternatives like "`a`, `b` or `c`"
+fn format_alternatives(alternatives: &[String]) -> String {
+    let quoted: Vec<String> = alternatives.iter().map(|a| format!("`{}`", a)).collect();
+    match quoted.split_last() {
+        None => "nothing".to_string(),
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::UnexpectedToken {
+                found, expected, ..
+            } => write!(
+                f,
+                "expected {}, found `{}`",
+                format_alternatives(expected),
+                found
+            ),
+            CompileError::UnexpectedEof { expected, .. } => write!(
+                f,
+                "unexpected end of file, expected {}",
+                format_alternatives(expected)
+            ),
+            CompileError::UnterminatedString { .. } => write!(f, "unterminated string literal"),
+            CompileError::UnterminatedComment { .. } => write!(f, "unterminated block comment"),
+            CompileError::IllegalCharacter { character, .. } if character.is_ascii() => {
+                write!(f, "illegal character `{}`", character.escape_debug())
+            }
+            CompileError::IllegalCharacter { character, .. } => write!(
+                f,
+                "non-ASCII character `{}` outside a string literal",
+                character
+            ),
+            CompileError::InvalidIdentifier { name, .. } => {
+                write!(f, "invalid identifier `{}`", name)
+            }
+            CompileError::IntegerOutOfRange { value, .. } => {
+                write!(f, "integer constant `{}` is out of range", value)
+            }
+            CompileError::UndefinedVariable { name, .. } => {
+                write!(f, "cannot find variable `{}` in this scope", name)
+            }
+            CompileError::DuplicateDeclaration { name, .. } => {
+                write!(f, "the name `{}` is declared more than once", name)
+            }
+            CompileError::BadConstructorReturnType {
+                class_name, found, ..
+            } => write!(
+                f,
+                "the return type of a constructor must be `{}`, found `{}`",
+                class_name, found
+            ),
+            CompileError::TypeMismatch {
+                expected, found, ..
+            } => write!(f, "mismatched types: expected `{}`, found `{}`", expected, found),
+            CompileError::InvalidOperand {
+                operator, found, ..
+            } => write!(f, "`{}` cannot be applied to a value of type `{}`", operator, found),
+            CompileError::UndefinedClass { name, .. } => write!(f, "cannot find class `{}`", name),
+            CompileError::UndefinedSubroutine {
+                class_name, name, ..
+            } => write!(
+                f,
+                "cannot find subroutine `{}` in class `{}`",
+                name, class_name
+            ),
+            CompileError::WrongCallStyle {
+                name,
+                kind: FunctionKind::Jmethod,
+                ..
+            } => write!(f, "`{}` is a method and cannot be called on a class", name),
+            CompileError::WrongCallStyle { name, kind, .. } => write!(
+                f,
+                "`{}` is a {} and cannot be called on an object",
+                name,
+                kind_name(*kind)
+            ),
+            CompileError::WrongNumberOfArguments {
+                name,
+                expected,
+                found,
+                ..
+            } => write!(
+                f,
+                "`{}` takes {} but {} {} supplied",
+                name,
+                count_arguments(*expected),
+                count_arguments(*found),
+                if *found == 1 { "was" } else { "were" }
+            ),
+            CompileError::MethodCallInFunction { name, function, .. } => write!(
+                f,
+                "the method `{}` cannot be called without an object in the function `{}`",
+                name, function
+            ),
+            CompileError::FieldInFunction { name, function, .. } => write!(
+                f,
+                "the field `{}` cannot be used in the function `{}`",
+                name, function
+            ),
+            CompileError::ThisInFunction { function, .. } => {
+                write!(f, "`this` cannot be used in the function `{}`", function)
+            }
+            CompileError::MissingReturn {
+                function,
+                return_type,
+                ..
+            } => write!(
+                f,
+                "`{}` can reach its end without returning a value of type `{}`",
+                function, return_type
+            ),
+            CompileError::MissingReturnThis { constructor, .. } => write!(
+                f,
+                "the constructor `{}` does not end every path with `return this;`",
+                constructor
+            ),
+            CompileError::ConstructorReturnsOther { constructor, .. } => write!(
+                f,
+                "the constructor `{}` returns something other than `this`",
+                constructor
+            ),
+            CompileError::NewIsNotAConstructor {
+                class_name, kind, ..
+            } => write!(
+                f,
+                "`{}.new` is a {}, but callers will take it for a constructor",
+                class_name,
+                kind_name(*kind)
+            ),
+            CompileError::MissingReturnValue {
+                function,
+                return_type,
+                ..
+            } => write!(
+                f,
+                "`{}` must return a value of type `{}`",
+                function, return_type
+            ),
+            CompileError::ReturnValueInVoid { function, .. } => {
+                write!(f, "the void subroutine `{}` cannot return a value", function)
+            }
+            CompileError::NotAnObject { name, found, .. } => write!(
+                f,
+                "`{}` has the type `{}`, subroutines can only be called on objects",
+                name, found
+            ),
+        }
+    }
+}
+
+impl Error for CompileError {}
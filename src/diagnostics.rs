@@ -0,0 +1,222 @@
+//! diagnostics
+//! Renders errors and warnings in the style of rustc, i.e. with file name, line and column,
+//! the offending source line and a caret underline.
+
+use jack_tokenizer::Span;
+
+use std::fmt::Write;
+
+/// How bad a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// Compilation cannot succeed
+    Error,
+    /// Suspicious code that still compiles
+    Warning,
+}
+
+impl Severity {
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// A message for the user, optionally pointing into a source file
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// Error or warning
+    pub severity: Severity,
+    /// Main message, printed in the first line
+    pub message: String,
+    /// Source location the diagnostic points to
+    pub span: Option<Span>,
+    /// Additional information printed below the source snippet
+    pub notes: Vec<String>,
+    /// Suggestion how to fix the problem
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    /// Creates an error without a source location
+    pub fn error<S: Into<String>>(message: S) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            span: None,
+            notes: vec![],
+            help: None,
+        }
+    }
+
+    /// Creates a warning without a source location
+    pub fn warning<S: Into<String>>(message: S) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(message)
+        }
+    }
+
+    /// Points the diagnostic to a location in a source file
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Adds a note that is printed below the source snippet
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Adds a suggestion how to fix the problem
+    pub fn with_help<S: Into<String>>(mut self, help: S) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+}
+
+/// A source file that diagnostics can point into
+pub struct SourceFile {
+    /// File name as it is shown to the user
+    pub name: String,
+    /// Complete content of the file
+    pub content: String,
+}
+
+/// All source files of a compilation, indexed by the `file_id` of a `Span`
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// Constructor
+    pub fn new() -> Self {
+        SourceMap { files: vec![] }
+    }
+
+    /// Registers a file and returns its file id
+    pub fn add(&mut self, name: String, content: String) -> usize {
+        self.files.push(SourceFile { name, content });
+        self.files.len() - 1
+    }
+
+    /// Looks up a file by its id
+    pub fn get(&self, file_id: usize) -> Option<&SourceFile> {
+        self.files.get(file_id)
+    }
+}
+
+/// Number of characters of `line_text` covered by `span`, at least one so that the caret is
+/// visible for spans that point to the end of a line
+fn underline_width(line_text: &str, span: Span) -> usize {
+    let start = line_text
+        .char_indices()
+        .nth(span.column - 1)
+        .map(|(i, _)| i)
+        .unwrap_or_else(|| line_text.len());
+    let mut covered_bytes = 0;
+    let width = line_text[start..]
+        .chars()
+        .take_while(|c| {
+            covered_bytes += c.len_utf8();
+            covered_bytes <= span.len
+        })
+        .count();
+    width.max(1)
+}
+
+/// Renders a diagnostic to a string. If `color` is true, ANSI escape codes are used.
+pub fn render(diagnostic: &Diagnostic, source_map: &SourceMap, color: bool) -> String {
+    let paint = |code: &'static str, text: &str| -> String {
+        if color {
+            format!("{}{}{}", code, text, RESET)
+        } else {
+            text.to_string()
+        }
+    };
+    let severity = diagnostic.severity;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{}{}",
+        paint(severity.color(), severity.label()),
+        paint(BOLD, &format!(": {}", diagnostic.message))
+    );
+
+    let snippet = diagnostic
+        .span
+        .and_then(|span| source_map.get(span.file_id).map(|file| (span, file)));
+
+    // width of the line number column
+    let gutter = match snippet {
+        Some((span, _)) => span.line.to_string().len(),
+        None => 0,
+    };
+    let pad = " ".repeat(gutter);
+
+    if let Some((span, file)) = snippet {
+        let line_text = file.content.lines().nth(span.line - 1).unwrap_or("");
+        let line_text = line_text.trim_end_matches('\r');
+
+        // the text in front of the span, with every character except tabs replaced by a space
+        let prefix: String = line_text
+            .chars()
+            .take(span.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underline_len = underline_width(line_text, span);
+
+        let _ = writeln!(
+            out,
+            "{}{} {}:{}:{}",
+            pad,
+            paint(BLUE, "-->"),
+            file.name,
+            span.line,
+            span.column
+        );
+        let _ = writeln!(out, "{} {}", pad, paint(BLUE, "|"));
+        let _ = writeln!(
+            out,
+            "{} {} {}",
+            paint(BLUE, &span.line.to_string()),
+            paint(BLUE, "|"),
+            line_text
+        );
+        let _ = writeln!(
+            out,
+            "{} {} {}{}",
+            pad,
+            paint(BLUE, "|"),
+            prefix,
+            paint(severity.color(), &"^".repeat(underline_len))
+        );
+    }
+
+    for note in &diagnostic.notes {
+        let _ = writeln!(out, "{} {} {}", pad, paint(BLUE, "="), paint(BOLD, "note:") + " " + note);
+    }
+    if let Some(help) = &diagnostic.help {
+        let _ = writeln!(out, "{} {} {}", pad, paint(BLUE, "="), paint(BOLD, "help:") + " " + help);
+    }
+
+    out
+}
@@ -0,0 +1,235 @@
+//! hack_assembler
+//! Translates Hack assembly into Hack machine code. Labels `(NAME)` are resolved in a first pass,
+//! all other symbols that are not predefined are allocated as variables starting at address 16.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// A line of assembly code that cannot be translated
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    /// 1-based line number
+    pub line: usize,
+    /// What is wrong with the line
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+/// Address of the first variable
+const FIRST_VARIABLE_ADDRESS: u16 = 16;
+
+fn predefined_symbols() -> HashMap<String, u16> {
+    let mut symbols = HashMap::new();
+    for (name, address) in &[
+        ("SP", 0),
+        ("LCL", 1),
+        ("ARG", 2),
+        ("THIS", 3),
+        ("THAT", 4),
+        ("SCREEN", 16384),
+        ("KBD", 24576),
+    ] {
+        symbols.insert(name.to_string(), *address);
+    }
+    for register in 0..16 {
+        symbols.insert(format!("R{}", register), register);
+    }
+    symbols
+}
+
+/// The `a` bit and the six `c` bits of a computation
+fn comp_bits(comp: &str) -> Option<u16> {
+    let bits = match comp {
+        "0" => 0b0_101010,
+        "1" => 0b0_111111,
+        "-1" => 0b0_111010,
+        "D" => 0b0_001100,
+        "A" => 0b0_110000,
+        "!D" => 0b0_001101,
+        "!A" => 0b0_110001,
+        "-D" => 0b0_001111,
+        "-A" => 0b0_110011,
+        "D+1" | "1+D" => 0b0_011111,
+        "A+1" | "1+A" => 0b0_110111,
+        "D-1" => 0b0_001110,
+        "A-1" => 0b0_110010,
+        "D+A" | "A+D" => 0b0_000010,
+        "D-A" => 0b0_010011,
+        "A-D" => 0b0_000111,
+        "D&A" | "A&D" => 0b0_000000,
+        "D|A" | "A|D" => 0b0_010101,
+        "M" => 0b1_110000,
+        "!M" => 0b1_110001,
+        "-M" => 0b1_110011,
+        "M+1" | "1+M" => 0b1_110111,
+        "M-1" => 0b1_110010,
+        "D+M" | "M+D" => 0b1_000010,
+        "D-M" => 0b1_010011,
+        "M-D" => 0b1_000111,
+        "D&M" | "M&D" => 0b1_000000,
+        "D|M" | "M|D" => 0b1_010101,
+        _ => return None,
+    };
+    Some(bits)
+}
+
+/// The three destination bits, the registers may be given in any order
+fn dest_bits(dest: &str) -> Option<u16> {
+    let mut bits = 0;
+    for register in dest.chars() {
+        let bit = match register {
+            'A' => 0b100,
+            'D' => 0b010,
+            'M' => 0b001,
+            _ => return None,
+        };
+        if bits & bit != 0 {
+            return None;
+        }
+        bits |= bit;
+    }
+    Some(bits)
+}
+
+fn jump_bits(jump: &str) -> Option<u16> {
+    let bits = match jump {
+        "JGT" => 0b001,
+        "JEQ" => 0b010,
+        "JGE" => 0b011,
+        "JLT" => 0b100,
+        "JNE" => 0b101,
+        "JLE" => 0b110,
+        "JMP" => 0b111,
+        _ => return None,
+    };
+    Some(bits)
+}
+
+fn is_symbol(symbol: &str) -> bool {
+    match symbol.chars().next() {
+        Some(c) if !c.is_ascii_digit() => {
+            symbol
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_.$:".contains(c))
+        }
+        _ => false,
+    }
+}
+
+fn encode_c_instruction(instruction: &str) -> Result<u16, String> {
+    let (dest, rest) = match instruction.find('=') {
+        Some(i) => (&instruction[..i], &instruction[i + 1..]),
+        None => ("", instruction),
+    };
+    let (comp, jump) = match rest.find(';') {
+        Some(i) => (&rest[..i], &rest[i + 1..]),
+        None => (rest, ""),
+    };
+    let comp = comp_bits(comp).ok_or_else(|| format!("invalid computation `{}`", comp))?;
+    let dest = dest_bits(dest).ok_or_else(|| format!("invalid destination `{}`", dest))?;
+    let jump = if jump.is_empty() {
+        0
+    } else {
+        jump_bits(jump).ok_or_else(|| format!("invalid jump `{}`", jump))?
+    };
+    Ok(0b111 << 13 | comp << 6 | dest << 3 | jump)
+}
+
+/// Assembles Hack assembly code into machine instructions
+pub fn assemble(asm_code: &str) -> Result<Vec<u16>, AsmError> {
+    // strip comments and whitespace, keep the line numbers for error messages
+    let lines: Vec<(usize, String)> = asm_code
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let code = match line.find("//") {
+                Some(comment_start) => &line[..comment_start],
+                None => line,
+            };
+            (i + 1, code.split_whitespace().collect::<String>())
+        })
+        .filter(|(_, code)| !code.is_empty())
+        .collect();
+
+    // first pass: labels
+    let mut symbols = predefined_symbols();
+    let mut address = 0usize;
+    for (line, code) in &lines {
+        if let Some(label) = code.strip_prefix('(') {
+            let label = label.strip_suffix(')').unwrap_or("");
+            if !is_symbol(label) {
+                return Err(AsmError {
+                    line: *line,
+                    message: format!("invalid label `{}`", code),
+                });
+            }
+            if symbols.insert(label.to_string(), address as u16).is_some() {
+                return Err(AsmError {
+                    line: *line,
+                    message: format!("the symbol `{}` is defined more than once", label),
+                });
+            }
+        } else {
+            address += 1;
+        }
+    }
+    if address > 32768 {
+        return Err(AsmError {
+            line: lines.last().map(|(line, _)| *line).unwrap_or(0),
+            message: format!("the program has {} instructions, the ROM holds 32768", address),
+        });
+    }
+
+    // second pass: instructions
+    let mut next_variable = FIRST_VARIABLE_ADDRESS;
+    let mut instructions = vec![];
+    for (line, code) in &lines {
+        if code.starts_with('(') {
+            continue;
+        }
+        let instruction = if let Some(value) = code.strip_prefix('@') {
+            if let Ok(constant) = value.parse::<u16>() {
+                if constant > 32767 {
+                    return Err(AsmError {
+                        line: *line,
+                        message: format!("constant {} is larger than 32767", constant),
+                    });
+                }
+                constant
+            } else if is_symbol(value) {
+                *symbols.entry(value.to_string()).or_insert_with(|| {
+                    next_variable += 1;
+                    next_variable - 1
+                })
+            } else {
+                return Err(AsmError {
+                    line: *line,
+                    message: format!("invalid symbol `{}`", value),
+                });
+            }
+        } else {
+            encode_c_instruction(code).map_err(|message| AsmError {
+                line: *line,
+                message,
+            })?
+        };
+        instructions.push(instruction);
+    }
+    Ok(instructions)
+}
+
+/// Formats machine instructions in the `.hack` text format, one 16 digit binary number per line
+pub fn to_hack_text(instructions: &[u16]) -> String {
+    instructions
+        .iter()
+        .map(|instruction| format!("{:016b}\n", instruction))
+        .collect()
+}
@@ -0,0 +1,283 @@
+//! jack_ast
+//! Typed syntax tree of a Jack class. It is produced by `jack_parser::JackParser` and consumed by
+//! the code generator `jack_compiler::JackCompiler`. The tree keeps every detail of the source
+//! (e.g. parentheses), so the original token sequence can be reconstructed from it.
+
+use jack_tokenizer::Span;
+
+/// The type of a variable, parameter or return value
+#[derive(Debug, Clone, PartialEq)]
+pub enum JackVariableType {
+    /// `int`
+    Jint,
+    /// `char`
+    Jchar,
+    /// `boolean`
+    Jboolean,
+    /// An object of the class with the given name
+    Jclass(String),
+}
+
+/// Where a variable lives, which determines the VM memory segment it is stored in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariableKind {
+    /// `static` variable of a class
+    Jstatic,
+    /// `field` of an object
+    Jfield,
+    /// Local variable declared with `var`
+    Jvar,
+    /// Parameter of a subroutine
+    Jarg,
+}
+
+/// The three kinds of subroutines
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FunctionKind {
+    /// `method`, called on an object
+    Jmethod,
+    /// `constructor`, allocates a new object
+    Jconstructor,
+    /// `function`, a static subroutine
+    Jfunction,
+}
+
+/// Binary operators
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JackOperation {
+    /// `+`
+    Add,
+    /// `-`
+    Subtract,
+    /// `*`
+    Multiply,
+    /// `/`
+    Divide,
+    /// `&`
+    And,
+    /// `|`
+    Or,
+    /// `<`
+    Less,
+    /// `>`
+    Larger,
+    /// `=`
+    Equal,
+}
+
+impl JackOperation {
+    /// The operator as it is written in Jack code
+    pub fn symbol(self) -> char {
+        match self {
+            JackOperation::Add => '+',
+            JackOperation::Subtract => '-',
+            JackOperation::Multiply => '*',
+            JackOperation::Divide => '/',
+            JackOperation::And => '&',
+            JackOperation::Or => '|',
+            JackOperation::Less => '<',
+            JackOperation::Larger => '>',
+            JackOperation::Equal => '=',
+        }
+    }
+}
+
+/// Unary operators
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperation {
+    /// `-`, arithmetic negation
+    Negate,
+    /// `~`, bitwise not
+    Not,
+}
+
+impl UnaryOperation {
+    /// The operator as it is written in Jack code
+    pub fn symbol(self) -> char {
+        match self {
+            UnaryOperation::Negate => '-',
+            UnaryOperation::Not => '~',
+        }
+    }
+}
+
+/// A name together with the place it was written
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identifier {
+    /// The name
+    pub name: String,
+    /// Location of the name
+    pub span: Span,
+}
+
+/// `class name { classVarDec* subroutineDec* }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Class {
+    /// Name of the class
+    pub name: Identifier,
+    /// `static` and `field` declarations
+    pub class_var_decs: Vec<ClassVarDec>,
+    /// Constructors, functions and methods
+    pub subroutine_decs: Vec<SubroutineDec>,
+}
+
+/// `(static | field) type name (, name)* ;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassVarDec {
+    /// Either `VariableKind::Jstatic` or `VariableKind::Jfield`
+    pub kind: VariableKind,
+    /// Declared type
+    pub var_type: JackVariableType,
+    /// Location of the type
+    pub type_span: Span,
+    /// Declared names
+    pub names: Vec<Identifier>,
+}
+
+/// `(constructor | function | method) (void | type) name ( parameterList ) subroutineBody`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubroutineDec {
+    /// Constructor, function or method
+    pub kind: FunctionKind,
+    /// Location of the `constructor`, `function` or `method` keyword
+    pub kind_span: Span,
+    /// Declared return type, `None` for `void`
+    pub return_type: Option<JackVariableType>,
+    /// Location of the return type
+    pub return_type_span: Span,
+    /// Name of the subroutine
+    pub name: Identifier,
+    /// Parameters in declaration order
+    pub parameters: Vec<Parameter>,
+    /// Local variable declarations at the beginning of the body
+    pub var_decs: Vec<VarDec>,
+    /// Statements of the body
+    pub statements: Vec<Statement>,
+    /// Location of the `}` that closes the body
+    pub end_span: Span,
+}
+
+/// `type name` in a parameter list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    /// Declared type
+    pub var_type: JackVariableType,
+    /// Location of the type
+    pub type_span: Span,
+    /// Name of the parameter
+    pub name: Identifier,
+}
+
+/// `var type name (, name)* ;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarDec {
+    /// Declared type
+    pub var_type: JackVariableType,
+    /// Location of the type
+    pub type_span: Span,
+    /// Declared names
+    pub names: Vec<Identifier>,
+}
+
+/// A statement together with the place it was written
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    /// The statement itself
+    pub kind: StatementKind,
+    /// Location from the first keyword to the final `;` or `}`
+    pub span: Span,
+}
+
+/// The five kinds of statements
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind {
+    /// `let name ([ index ])? = value ;`
+    Let {
+        /// The variable that is assigned to
+        target: Identifier,
+        /// Array index, if an array element is assigned
+        index: Option<Box<Expression>>,
+        /// The assigned value
+        value: Expression,
+    },
+    /// `if ( condition ) { statements } (else { statements })?`
+    If {
+        /// The condition
+        condition: Expression,
+        /// Statements executed if the condition is true
+        then_statements: Vec<Statement>,
+        /// Statements of the `else` branch, if there is one
+        else_statements: Option<Vec<Statement>>,
+    },
+    /// `while ( condition ) { statements }`
+    While {
+        /// The condition
+        condition: Expression,
+        /// The loop body
+        statements: Vec<Statement>,
+    },
+    /// `do subroutineCall ;`
+    Do(SubroutineCall),
+    /// `return expression? ;`
+    Return(Option<Expression>),
+}
+
+/// `term (op term)*`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    /// The first term
+    pub term: Term,
+    /// The following operators and terms, evaluated from left to right
+    pub operations: Vec<(JackOperation, Term)>,
+    /// Location of the whole expression
+    pub span: Span,
+}
+
+/// A term together with the place it was written
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    /// The term itself
+    pub kind: TermKind,
+    /// Location of the whole term
+    pub span: Span,
+}
+
+/// The different kinds of terms
+#[derive(Debug, Clone, PartialEq)]
+pub enum TermKind {
+    /// Integer literal
+    IntConstant(i32),
+    /// String literal
+    StringConstant(String),
+    /// `true`
+    True,
+    /// `false`
+    False,
+    /// `null`
+    Null,
+    /// `this`
+    This,
+    /// A variable
+    Variable(Identifier),
+    /// `name [ expression ]`
+    ArrayAccess(Identifier, Box<Expression>),
+    /// A subroutine call
+    SubroutineCall(SubroutineCall),
+    /// `( expression )`
+    Parenthesized(Box<Expression>),
+    /// `unaryOp term`
+    Unary(UnaryOperation, Box<Term>),
+}
+
+/// `name ( expressionList )` or `receiver . name ( expressionList )`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubroutineCall {
+    /// Class or variable name in front of the dot
+    pub receiver: Option<Identifier>,
+    /// Name of the called subroutine
+    pub name: Identifier,
+    /// Arguments
+    pub arguments: Vec<Expression>,
+    /// Location of the whole call
+    pub span: Span,
+}
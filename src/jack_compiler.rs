@@ -1,63 +1,39 @@
 //! jack_compiler
-extern crate peek_nth;
-
-use self::peek_nth::{IteratorExt, PeekableNth};
-
-use jack_tokenizer::{Keyword, Token};
-
-use std::collections::HashMap;
-use std::slice::Iter;
-
-#[derive(Debug, Clone)]
-enum JackVariableType {
-    Jint,
-    Jchar,
-    Jboolean,
-    Jclass(String),
-}
-
-#[derive(Debug, Clone)]
-struct SymbolTableEntry {
-    pub var_type: JackVariableType,
-    pub num: usize,
+//! Generates VM code from the syntax tree of a Jack class.
+
+use compile_error::{CompileError, DEFAULT_MAX_ERRORS};
+use jack_ast::*;
+use jack_parser::JackParser;
+use jack_signatures::ProgramSignatures;
+use jack_tokenizer::{Span, SpannedToken};
+use symbol_table::{Symbol, SymbolTable};
+
+use std::mem;
+
+/// Whether every path through the statements ends in a `return`. The body of a `while` loop
+/// might not run at all, so it never counts.
+fn always_returns(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match &statement.kind {
+        StatementKind::Return(_) => true,
+        StatementKind::If { then_statements, else_statements: Some(else_statements), .. } => {
+            always_returns(then_statements) && always_returns(else_statements)
+        }
+        _ => false,
+    })
 }
 
-impl SymbolTableEntry {
-    fn new(var_type: JackVariableType, num: usize) -> Self {
-        SymbolTableEntry { var_type, num }
+/// The name of a type as it is written in Jack code
+fn jack_type_name(var_type: &JackVariableType) -> String {
+    match var_type {
+        JackVariableType::Jint => "int".to_string(),
+        JackVariableType::Jchar => "char".to_string(),
+        JackVariableType::Jboolean => "boolean".to_string(),
+        JackVariableType::Jclass(name) => name.clone(),
     }
 }
 
-#[derive(Debug, Clone)]
-enum VariableKind {
-    Jstatic,
-    Jfield,
-    Jvar,
-    Jarg,
-}
-
-#[derive(Debug, PartialEq, Clone)]
-enum FunctionKind {
-    Jmethod,
-    Jconstructor,
-    Jfunction,
-}
-
-#[derive(Debug, Clone)]
-enum JackOperation {
-    Add,
-    Subtract,
-    Multiply,
-    Divide,
-    And,
-    Or,
-    Less,
-    Larger,
-    Equal,
-}
-
 impl JackOperation {
-    fn to_vm_command_string(&self) -> String {
+    fn to_vm_command_string(self) -> String {
         match self {
             JackOperation::Add => "add\n".to_string(),
             JackOperation::Subtract => "sub\n".to_string(),
@@ -72,34 +48,154 @@ impl JackOperation {
     }
 }
 
+/// How a subroutine is called
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CallStyle {
+    /// `varName.subroutineName(...)`, the subroutine must be a method
+    OnObject,
+    /// `className.subroutineName(...)`, the subroutine must be a function or a constructor
+    OnClass,
+    /// `subroutineName(...)`, a subroutine of the current class
+    Bare,
+}
+
+/// Parses and compiles the tokens of a class, read from the file `file_id`. Syntax errors and the
+/// errors that code generation finds in the correctly parsed parts of the class are returned
+/// together, ordered by position.
+pub fn compile(
+    tokens: &[SpannedToken],
+    file_id: usize,
+    max_errors: usize,
+) -> Result<String, Vec<CompileError>> {
+    let (class, errors) = JackParser::new(tokens, file_id)
+        .with_max_errors(max_errors)
+        .parse_class_partial();
+    compile_with_signatures(class.as_ref(), errors, max_errors, None, false)
+}
+
+/// Compiles a class that was parsed with `JackParser::parse_class_partial`, together with the
+/// syntax errors found while parsing it. Unlike `compile`, every call is also resolved against
+/// the signatures of the whole program, which reports calls of classes and subroutines that do
+/// not exist and calls with the wrong number of arguments. If `implicit_return_this` is true,
+/// constructors that can reach their end return `this` there instead of being rejected.
+pub fn compile_in_program(
+    class: &Class,
+    syntax_errors: &[CompileError],
+    max_errors: usize,
+    program: &ProgramSignatures,
+    implicit_return_this: bool,
+) -> Result<String, Vec<CompileError>> {
+    compile_with_signatures(
+        Some(class),
+        syntax_errors.to_vec(),
+        max_errors,
+        Some(program),
+        implicit_return_this,
+    )
+}
+
+fn compile_with_signatures(
+    class: Option<&Class>,
+    mut errors: Vec<CompileError>,
+    max_errors: usize,
+    program: Option<&ProgramSignatures>,
+    implicit_return_this: bool,
+) -> Result<String, Vec<CompileError>> {
+    if let Some(class) = class {
+        let mut compiler = JackCompiler::new(class)
+            .with_max_errors(max_errors)
+            .with_implicit_return_this(implicit_return_this)
+            .with_syntax_errors(&errors);
+        if let Some(program) = program {
+            compiler = compiler.with_program(program);
+        }
+        match compiler.compile_class() {
+            Ok(vm_output) => {
+                if errors.is_empty() {
+                    return Ok(vm_output);
+                }
+            }
+            Err(generator_errors) => errors.extend(generator_errors),
+        }
+    }
+    errors.sort_by_key(|error| error.span().offset);
+    errors.truncate(max_errors.max(1));
+    Err(errors)
+}
+
 /// JackCompiler struct
 pub struct JackCompiler<'a> {
-    token_iterator: PeekableNth<Iter<'a, Token>>,
+    class: &'a Class,
     vm_output: String,
     class_name: String,
-    field_symbol_table: HashMap<String, SymbolTableEntry>,
-    static_symbol_table: HashMap<String, SymbolTableEntry>,
-    var_symbol_table: HashMap<String, SymbolTableEntry>,
-    arg_symbol_table: HashMap<String, SymbolTableEntry>,
+    symbol_table: SymbolTable,
     if_label_num : usize,
     while_label_num : usize,
-    currently_in_void_function : bool,
+    current_return_type: Option<JackVariableType>,
+    current_subroutine_kind: FunctionKind,
+    current_subroutine_name: String,
+    errors: Vec<CompileError>,
+    max_errors: usize,
+    program: Option<&'a ProgramSignatures>,
+    implicit_return_this: bool,
+    syntax_error_offsets: Vec<usize>,
 }
 
 impl<'a> JackCompiler<'a> {
     /// Constructor
-    pub fn new(tokens: &'a [Token]) -> Self {
+    pub fn new(class: &'a Class) -> Self {
         JackCompiler {
-            token_iterator: tokens.iter().peekable_nth(),
+            class,
             vm_output: "".to_string(),
             class_name: "".to_string(),
-            field_symbol_table: HashMap::new(),
-            static_symbol_table: HashMap::new(),
-            var_symbol_table: HashMap::new(),
-            arg_symbol_table: HashMap::new(),
+            symbol_table: SymbolTable::new(),
             if_label_num : 0,
             while_label_num : 0,
-            currently_in_void_function : false,
+            current_return_type: None,
+            current_subroutine_kind: FunctionKind::Jfunction,
+            current_subroutine_name: "".to_string(),
+            errors: vec![],
+            max_errors: DEFAULT_MAX_ERRORS,
+            program: None,
+            implicit_return_this: false,
+            syntax_error_offsets: vec![],
+        }
+    }
+
+    /// Resolves calls against the signatures of the whole program, see `compile_in_program`
+    pub fn with_program(mut self, program: &'a ProgramSignatures) -> Self {
+        self.program = Some(program);
+        self
+    }
+
+    /// Lets constructors that can reach their end without a `return` return `this` there
+    pub fn with_implicit_return_this(mut self, implicit_return_this: bool) -> Self {
+        self.implicit_return_this = implicit_return_this;
+        self
+    }
+
+    /// Tells the compiler about the syntax errors of the class, so subroutines and statements
+    /// that could not be parsed are missing from it. Calls of the missing subroutines are then
+    /// not reported, nor missing returns in subroutines with syntax errors.
+    pub fn with_syntax_errors(mut self, syntax_errors: &[CompileError]) -> Self {
+        self.syntax_error_offsets = syntax_errors.iter().map(|error| error.span().offset).collect();
+        self
+    }
+
+    /// Sets the number of errors after which compilation stops. At least one error is always reported.
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors.max(1);
+        self
+    }
+
+    /// Records an error and continues with the next statement or declaration. Returns the error
+    /// instead if the error limit is reached.
+    fn record_error(&mut self, error: CompileError) -> Result<(), CompileError> {
+        if self.errors.len() + 1 >= self.max_errors {
+            Err(error)
+        } else {
+            self.errors.push(error);
+            Ok(())
         }
     }
 
@@ -108,615 +204,507 @@ impl<'a> JackCompiler<'a> {
         var_type: JackVariableType,
         var_kind: VariableKind,
         var_name: String,
-    ) -> Result<(), &'static str> {
-        match var_kind {
-            VariableKind::Jstatic => {
-                if !self.static_symbol_table.contains_key(&var_name)
-                    && !self.field_symbol_table.contains_key(&var_name)
-                {
-                    let len = self.static_symbol_table.len();
-                    self.static_symbol_table
-                        .insert(var_name, SymbolTableEntry::new(var_type, len));
-                } else {
-                    return Err("This variable name is already in use!");
-                }
-            }
-            VariableKind::Jfield => {
-                if !self.static_symbol_table.contains_key(&var_name)
-                    && !self.field_symbol_table.contains_key(&var_name)
-                {
-                    let len = self.field_symbol_table.len();
-                    self.field_symbol_table
-                        .insert(var_name, SymbolTableEntry::new(var_type, len));
-                } else {
-                    return Err("This variable name is already in use!");
-                }
-            }
-            VariableKind::Jvar => {
-                if !self.var_symbol_table.contains_key(&var_name)
-                    && !self.arg_symbol_table.contains_key(&var_name)
-                {
-                    let len = self.var_symbol_table.len();
-                    self.var_symbol_table
-                        .insert(var_name, SymbolTableEntry::new(var_type, len));
-                } else {
-                    return Err("This variable name is already in use!");
-                }
-            }
-            VariableKind::Jarg => {
-                if !self.var_symbol_table.contains_key(&var_name)
-                    && !self.arg_symbol_table.contains_key(&var_name)
-                {
-                    let len = self.arg_symbol_table.len();
-                    self.arg_symbol_table
-                        .insert(var_name, SymbolTableEntry::new(var_type, len));
-                } else {
-                    return Err("This variable name is already in use!");
-                }
-            }
+        span: Span,
+    ) -> Result<(), CompileError> {
+        if !self.symbol_table.define(&var_name, var_type, var_kind) {
+            self.record_error(CompileError::DuplicateDeclaration { name: var_name, span })?;
         }
         Ok(())
     }
 
-    fn get_vm_code_for_var_name(&self, var_name: &str) -> Result<String,&'static str>  {
-        if let Some(entry) = self.var_symbol_table.get(var_name) {
-            Ok(format!("local {}", entry.num))
-        } else if let Some(entry) = self.arg_symbol_table.get(var_name) {
-            Ok(format!("argument {}", entry.num))
-        } else if let Some(entry) = self.field_symbol_table.get(var_name) { // ????
-            Ok(format!("this {}", entry.num))
-        } else if let Some(entry) = self.static_symbol_table.get(var_name) { // ????
-            Ok(format!("static {}", entry.num))
-        } else {
-            return Err("This variable was not defined before");
-        }       
+    fn lookup_symbol(&self, var_name: &str, span: Span) -> Result<&Symbol, CompileError> {
+        let symbol = self.symbol_table
+            .lookup(var_name)
+            .ok_or_else(|| CompileError::UndefinedVariable { name: var_name.to_string(), span })?;
+        self.check_object_access(symbol, var_name, span)?;
+        Ok(symbol)
     }
 
-    fn get_symbol_table_entry(&self, var_name: &str) -> Result<&SymbolTableEntry,&'static str>  {
-        if let Some(entry) = self.var_symbol_table.get(var_name) {
-            Ok(entry)
-        } else if let Some(entry) = self.arg_symbol_table.get(var_name) {
-            Ok(entry)
-        } else if let Some(entry) = self.field_symbol_table.get(var_name) { // ????
-            Ok(entry)
-        } else {
-            return Err("This variable is not in the symbol table.");
-        }       
+    /// Functions have no object, so they cannot use fields
+    fn check_object_access(&self, symbol: &Symbol, var_name: &str, span: Span) -> Result<(), CompileError> {
+        if symbol.kind == VariableKind::Jfield && self.current_subroutine_kind == FunctionKind::Jfunction {
+            return Err(CompileError::FieldInFunction {
+                name: var_name.to_string(),
+                function: self.current_subroutine_name.clone(),
+                span,
+            });
+        }
+        Ok(())
     }
 
-    /// Main function. Returns a string containing VM code corresponding to a Jack class
-    /// TODO: Write custom Err structs and use them instead of static str
-    /// https://doc.rust-lang.org/rust-by-example/error/multiple_error_types/define_error_type.html
-    pub fn compile_class(&mut self) -> Result<String, &'static str> {
-        if Token::Keyword(Keyword::Class) == *self.token_iterator.next().unwrap() {
-            // className
-            self.class_name = self.parse_name()?.to_owned();
-            self.parse_specific_symbol('{')?;
-
-            // classVarDec*
-            while self.compile_class_var_dec()? {
-                // do nothing
-            }
-
-            // subRoutineDec*
-            while self.compile_subroutine_dec()? {
-                // do nothing
-            }
+    fn get_vm_code_for_var_name(&self, var_name: &str, span: Span) -> Result<String, CompileError> {
+        Ok(self.lookup_symbol(var_name, span)?.vm_location())
+    }
 
-            // }
-            self.parse_specific_symbol('}')?;
+    /// Main function. Returns a string containing VM code corresponding to a Jack class,
+    /// or all errors found in the class up to the configured maximum.
+    pub fn compile_class(&mut self) -> Result<String, Vec<CompileError>> {
+        if let Err(error) = self.compile_class_dec() {
+            self.errors.push(error);
+        }
 
+        if self.errors.is_empty() {
+            Ok(self.vm_output.clone())
         } else {
-            return Err("This is no class!");
+            Err(mem::take(&mut self.errors))
         }
-
-        return Ok(self.vm_output.clone());
     }
 
-    fn compile_class_var_dec(&mut self) -> Result<bool, &'static str> {
-        // ( static | field )
-        let var_kind = match self.token_iterator.peek().unwrap() {
-            Token::Keyword(Keyword::Static) => {
-                VariableKind::Jstatic
-            }
-            Token::Keyword(Keyword::Field) => {
-                VariableKind::Jfield
-            }
-            _ => return Ok(false),
-        };
-        self.token_iterator.next();
-
-        // type
-        let var_type = self.parse_type()?;
-
-        // varName
-        let mut var_name = self.parse_name()?.to_owned();
-
-        self.add_to_symbol_table(var_type.clone(), var_kind.clone(), var_name)?;
+    fn compile_class_dec(&mut self) -> Result<(), CompileError> {
+        let class = self.class;
+        self.class_name = class.name.name.clone();
 
-        // (, varName)*
-        while **self.token_iterator.peek().unwrap() == Token::Symbol(',') {
-            self.token_iterator.next(); // peek successful, hence next()
-
-            var_name = self.parse_name()?.to_owned();
-
-            self.add_to_symbol_table(var_type.clone(), var_kind.clone(), var_name)?;
+        // classVarDec*
+        for class_var_dec in &class.class_var_decs {
+            for name in &class_var_dec.names {
+                self.add_to_symbol_table(
+                    class_var_dec.var_type.clone(),
+                    class_var_dec.kind,
+                    name.name.clone(),
+                    name.span,
+                )?;
+            }
         }
 
-        // ;
-        self.parse_specific_symbol(';')?;
+        // subRoutineDec*
+        for subroutine_dec in &class.subroutine_decs {
+            if let Err(error) = self.compile_subroutine_dec(subroutine_dec) {
+                self.record_error(error)?;
+            }
+        }
 
-        return Ok(true);
+        Ok(())
     }
 
-    fn compile_subroutine_dec(&mut self) -> Result<bool, &'static str> {
+    fn compile_subroutine_dec(&mut self, subroutine_dec: &SubroutineDec) -> Result<(), CompileError> {
         // forget about last symbol table from last function and initialize new one
-        self.arg_symbol_table = HashMap::new();
-        self.var_symbol_table = HashMap::new();
+        self.symbol_table.start_subroutine();
+
+        if subroutine_dec.kind == FunctionKind::Jmethod {
+            let class_name = self.class_name.clone();
+            self.add_to_symbol_table(
+                JackVariableType::Jclass(class_name),
+                VariableKind::Jarg,
+                "this".to_string(),
+                subroutine_dec.kind_span,
+            )?;
+        }
 
-        // ( constructor | function | method )
-        let function_kind = match self.token_iterator.peek().unwrap() {
-            Token::Keyword(Keyword::Constructor) => {
-                FunctionKind::Jconstructor
-            }
-            Token::Keyword(Keyword::Function) => {
-                FunctionKind::Jfunction
-            }
-            Token::Keyword(Keyword::Method) => {
-                let class_name = self.class_name.clone();
-                self.add_to_symbol_table(
-                    JackVariableType::Jclass(class_name),
-                    VariableKind::Jarg,
-                    "this".to_string(),
-                )?;
-                FunctionKind::Jmethod
-            }
-            _ => return Ok(false),
-        };
-        self.token_iterator.next();
-
-        // ( 'void' | type)
-        self.currently_in_void_function = false;
-        match self.token_iterator.next().unwrap() {
-            Token::Keyword(Keyword::Int)     => {},
-            Token::Keyword(Keyword::Char)    => {},
-            Token::Keyword(Keyword::Boolean) => {},
-            Token::Keyword(Keyword::Void)    => self.currently_in_void_function = true,
-            Token::Identifier(id)            => {
-                if function_kind == FunctionKind::Jconstructor && *id != self.class_name {
-                    return Err("The return type of a constructor must be the class type!");
+        self.current_return_type = subroutine_dec.return_type.clone();
+        self.current_subroutine_kind = subroutine_dec.kind;
+        self.current_subroutine_name = subroutine_dec.name.name.clone();
+        if subroutine_dec.kind == FunctionKind::Jconstructor {
+            match subroutine_dec.return_type {
+                Some(JackVariableType::Jclass(ref id)) if *id == self.class_name => {}
+                ref return_type => {
+                    return Err(CompileError::BadConstructorReturnType {
+                        class_name: self.class_name.clone(),
+                        found: return_type.as_ref().map_or("void".to_string(), jack_type_name),
+                        span: subroutine_dec.return_type_span,
+                    })
                 }
-            },
-            _ => {
-                return Err(
-                    "Expected void or a type! Type has to be int, char, boolean, or class name!",
-                )
-            }
-        };
-                
-
-        // subRoutineName
-        let fname = match self.token_iterator.next().unwrap() {
-            Token::Identifier(name) => name,
-            _ => return Err("Expected a subRoutine name here!")
-        };
-
-
-        self.vm_output += &format!("function {class_name:}.{fname:} ", class_name=self.class_name, fname=fname);
-
-        // ( parameterList )
-        self.parse_specific_symbol('(')?;
-
-        if **self.token_iterator.peek().unwrap() != Token::Symbol(')') {
-            // if function has more than zero arguments
-            let var_type = self.parse_type()?;
-            let var_name = format!("{}", self.parse_name()?);
-            self.add_to_symbol_table(var_type, VariableKind::Jarg, var_name)?;
-
-            while **self.token_iterator.peek().unwrap() == Token::Symbol(',') {
-                self.token_iterator.next();
-                let var_type = self.parse_type()?;
-                let var_name = format!("{}", self.parse_name()?);
-                self.add_to_symbol_table(var_type, VariableKind::Jarg, var_name)?;
             }
         }
 
-        self.parse_specific_symbol(')')?;
-
-        // subRoutineBody
+        self.vm_output += &format!("function {class_name:}.{fname:} ", class_name=self.class_name, fname=subroutine_dec.name.name);
 
-        // {
-        self.parse_specific_symbol('{')?;
+        // parameterList
+        for parameter in &subroutine_dec.parameters {
+            self.add_to_symbol_table(
+                parameter.var_type.clone(),
+                VariableKind::Jarg,
+                parameter.name.name.clone(),
+                parameter.name.span,
+            )?;
+        }
 
         // varDec*
-        while **self.token_iterator.peek().unwrap() == Token::Keyword(Keyword::Var) {
-            self.token_iterator.next();
-            // type
-            let var_type = self.parse_type()?;
-
-            // varName
-            let var_name = format!("{}", self.parse_name()?);
-            self.add_to_symbol_table(var_type.clone(), VariableKind::Jvar, var_name)?;
-
-            // (, varName)*
-            while **self.token_iterator.peek().unwrap() == Token::Symbol(',') {
-                self.token_iterator.next();
-                let var_name = self.parse_name()?.to_owned();
-                self.add_to_symbol_table(var_type.clone(), VariableKind::Jvar, var_name)?;
+        for var_dec in &subroutine_dec.var_decs {
+            for name in &var_dec.names {
+                self.add_to_symbol_table(
+                    var_dec.var_type.clone(),
+                    VariableKind::Jvar,
+                    name.name.clone(),
+                    name.span,
+                )?;
             }
-
-            // ;
-            self.parse_specific_symbol(';')?;
         }
 
-        self.vm_output += &format!("{nlocals:}\n",nlocals=self.var_symbol_table.len());
-
+        self.vm_output += &format!("{nlocals:}\n",nlocals=self.symbol_table.count(VariableKind::Jvar));
 
-        match function_kind {
+        match subroutine_dec.kind {
             FunctionKind::Jmethod => self.vm_output += "push argument 0\npop pointer 0\n",
             FunctionKind::Jconstructor => {
-                self.vm_output += &format!("push constant {}\n", self.field_symbol_table.len());
+                self.vm_output += &format!("push constant {}\n", self.symbol_table.count(VariableKind::Jfield));
                 self.vm_output += "call Memory.alloc 1\n";
                 self.vm_output += "pop pointer 0\n";
             },
             FunctionKind::Jfunction => {},
-        }  
+        }
 
         // statements
-        while self.compile_statement()? {
-            // do nothing
+        self.compile_statements(&subroutine_dec.statements)?;
+
+        // a statement with a syntax error may have been the missing return
+        let has_syntax_errors = self.has_syntax_errors(subroutine_dec);
+        if subroutine_dec.kind == FunctionKind::Jconstructor {
+            if !always_returns(&subroutine_dec.statements) {
+                if !self.implicit_return_this && !has_syntax_errors {
+                    return Err(CompileError::MissingReturnThis {
+                        constructor: self.current_subroutine_name.clone(),
+                        span: subroutine_dec.end_span,
+                    });
+                }
+                self.vm_output += "push pointer 0\nreturn\n";
+            }
+        } else if let Some(ref return_type) = subroutine_dec.return_type {
+            if !always_returns(&subroutine_dec.statements) && !has_syntax_errors {
+                return Err(CompileError::MissingReturn {
+                    function: self.current_subroutine_name.clone(),
+                    return_type: jack_type_name(return_type),
+                    span: subroutine_dec.end_span,
+                });
+            }
         }
-
-        // }
-        self.parse_specific_symbol('}')?;
-
-        return Ok(true);
+        Ok(())
     }
 
-
-
-
-    fn compile_statement(&mut self) -> Result<bool, &'static str> {
-        match self.token_iterator.peek().unwrap() {
-            Token::Keyword(kw) => match kw {
-                Keyword::Let => self.compile_let_statement()?,
-                Keyword::If => self.compile_if_statement()?,
-                Keyword::While => self.compile_while_statement()?,
-                Keyword::Do => self.compile_do_statement()?,
-                Keyword::Return => self.compile_return_statement()?,
-                _ => {
-                    return Err("Expected a statement beginning with let, if, while, do, or return!")
-                }
-            },
-            _ => return Ok(false),
+    /// Compiles a block of statements. Errors within a statement are recorded and compilation
+    /// continues with the next statement.
+    fn compile_statements(&mut self, statements: &[Statement]) -> Result<(), CompileError> {
+        for statement in statements {
+            if let Err(error) = self.compile_statement(statement) {
+                self.record_error(error)?;
+            }
         }
-        return Ok(true);
+        Ok(())
     }
 
-    fn compile_let_statement(&mut self) -> Result<(), &'static str> {
-        self.token_iterator.next();
-
-        // varName
-        let var_name = self.parse_name()?.to_owned();        
+    fn compile_statement(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        match &statement.kind {
+            StatementKind::Let { target, index, value } => self.compile_let_statement(target, index.as_deref(), value),
+            StatementKind::If { condition, then_statements, else_statements } => {
+                self.compile_if_statement(condition, then_statements, else_statements.as_ref())
+            }
+            StatementKind::While { condition, statements } => self.compile_while_statement(condition, statements),
+            StatementKind::Do(call) => self.compile_do_statement(call),
+            StatementKind::Return(value) => self.compile_return_statement(value.as_ref(), statement.span),
+        }
+    }
 
+    fn compile_let_statement(
+        &mut self,
+        target: &Identifier,
+        index: Option<&Expression>,
+        value: &Expression,
+    ) -> Result<(), CompileError> {
         // [ expression ]
-        let mut left_hand_side_is_array = false;
-        if **self.token_iterator.peek().unwrap() == Token::Symbol('[') {
-            self.parse_specific_symbol('[')?;
-            self.compile_expression()?;
-            self.parse_specific_symbol(']')?;
-            self.vm_output += &format!("push {}\n", &self.get_vm_code_for_var_name(&var_name)?);
+        if let Some(index) = index {
+            self.compile_expression(index)?;
+            self.vm_output += &format!("push {}\n", &self.get_vm_code_for_var_name(&target.name, target.span)?);
             self.vm_output += "add\n";
-            left_hand_side_is_array = true;
         }
 
-        // =
-        self.parse_specific_symbol('=')?;
-        // expression
-        self.compile_expression()?;
-        // ;
-        self.parse_specific_symbol(';')?;
+        // = expression
+        self.compile_expression(value)?;
 
-        if left_hand_side_is_array {
+        if index.is_some() {
             self.vm_output += "pop temp 0\npop pointer 1\npush temp 0\npop that 0\n";
         } else {
-            self.vm_output += &format!("pop {}\n",&self.get_vm_code_for_var_name(&var_name)?);
+            self.vm_output += &format!("pop {}\n",&self.get_vm_code_for_var_name(&target.name, target.span)?);
         }
 
-        return Ok(());
+        Ok(())
     }
 
-    fn compile_if_statement(&mut self) -> Result<(), &'static str> {
-        self.token_iterator.next();
-
+    fn compile_if_statement(
+        &mut self,
+        condition: &Expression,
+        then_statements: &[Statement],
+        else_statements: Option<&Vec<Statement>>,
+    ) -> Result<(), CompileError> {
         let current_if_statement_num =  self.if_label_num;
         self.if_label_num += 1;
 
         // ( expression )
-        self.parse_specific_symbol('(')?;
-
-        self.compile_expression()?;
-
-        self.parse_specific_symbol(')')?;
+        self.compile_expression(condition)?;
 
         self.vm_output += &format!("if-goto {}_IF_TRUE{}\n", self.class_name, current_if_statement_num);
         self.vm_output += &format!("goto {}_IF_FALSE{}\n", self.class_name, current_if_statement_num);
         self.vm_output += &format!("label {}_IF_TRUE{}\n", self.class_name, current_if_statement_num);
 
         // { statements }
-        self.parse_specific_symbol('{')?;
-
-        while self.compile_statement()? {
-            //do nothing
-        }
+        self.compile_statements(then_statements)?;
 
         self.vm_output += &format!("goto {}_IF_END{}\n", self.class_name, current_if_statement_num);
 
         self.vm_output += &format!("label {}_IF_FALSE{}\n", self.class_name, current_if_statement_num);
 
-        self.parse_specific_symbol('}')?;
-
-        if Token::Keyword(Keyword::Else) == **self.token_iterator.peek().unwrap() {
-            // else
-            self.token_iterator.next();
-            // { statements }
-            self.parse_specific_symbol('{')?;
-            while self.compile_statement()? {
-                // do nothing
-            }
-            self.parse_specific_symbol('}')?;
+        if let Some(else_statements) = else_statements {
+            // else { statements }
+            self.compile_statements(else_statements)?;
         }
         self.vm_output += &format!("label {}_IF_END{}\n", self.class_name, current_if_statement_num);
 
-        return Ok(());
+        Ok(())
     }
 
-    fn compile_while_statement(&mut self) -> Result<(), &'static str> {
-        self.token_iterator.next();
-
+    fn compile_while_statement(&mut self, condition: &Expression, statements: &[Statement]) -> Result<(), CompileError> {
         let current_while_statement_num =  self.while_label_num;
         self.while_label_num += 1;
 
         self.vm_output += &format!("label {}_WHILE_EXP{}\n", self.class_name, current_while_statement_num);
 
         // ( expression )
-        self.parse_specific_symbol('(')?;
-
-        self.compile_expression()?;
-
-        self.parse_specific_symbol(')')?;
+        self.compile_expression(condition)?;
 
         self.vm_output += "not\n";
         self.vm_output += &format!("if-goto {}_WHILE_END{}\n", self.class_name, current_while_statement_num);
 
         // { statements }
-        self.parse_specific_symbol('{')?;
-        while self.compile_statement()? {
-            // do nothing
-        }
+        self.compile_statements(statements)?;
         self.vm_output += &format!("goto {}_WHILE_EXP{}\n", self.class_name, current_while_statement_num);
 
-        self.parse_specific_symbol('}')?;
-
         self.vm_output += &format!("label {}_WHILE_END{}\n", self.class_name, current_while_statement_num);
-        return Ok(());
+        Ok(())
     }
 
-    fn compile_do_statement(&mut self) -> Result<(), &'static str> {
-        self.token_iterator.next();
-
-        self.compile_subroutine_call()?;
-
-        self.parse_specific_symbol(';')?;
+    fn compile_do_statement(&mut self, call: &SubroutineCall) -> Result<(), CompileError> {
+        self.compile_subroutine_call(call)?;
 
         // in the do statement we do not do anything with the return value from the subroutine call
         // hence pop it somewhere to get rid of it
-        self.vm_output += "pop temp 0\n"; 
+        self.vm_output += "pop temp 0\n";
 
-        return Ok(());
+        Ok(())
     }
 
-    fn compile_return_statement(&mut self) -> Result<(), &'static str> {
-        if self.currently_in_void_function {
-            self.vm_output += "push constant 0\n";
-        }
-        self.token_iterator.next();
-
-        if **self.token_iterator.peek().unwrap() != Token::Symbol(';') {
-            self.compile_expression()?;
+    fn compile_return_statement(&mut self, value: Option<&Expression>, span: Span) -> Result<(), CompileError> {
+        match (value, &self.current_return_type) {
+            // a constructor returns the object it created, never the 0 of a `return;`
+            (None, _) if self.current_subroutine_kind == FunctionKind::Jconstructor => {
+                return Err(CompileError::MissingReturnThis {
+                    constructor: self.current_subroutine_name.clone(),
+                    span,
+                })
+            }
+            (Some(value), Some(_)) => self.compile_expression(value)?,
+            (None, None) => self.vm_output += "push constant 0\n",
+            (Some(value), None) => {
+                return Err(CompileError::ReturnValueInVoid {
+                    function: self.current_subroutine_name.clone(),
+                    span: value.span,
+                })
+            }
+            (None, Some(return_type)) => {
+                return Err(CompileError::MissingReturnValue {
+                    function: self.current_subroutine_name.clone(),
+                    return_type: jack_type_name(return_type),
+                    span,
+                })
+            }
         }
-        self.parse_specific_symbol(';')?;
         self.vm_output += "return\n";
 
-        return Ok(());
+        Ok(())
     }
 
-    fn compile_expression(&mut self) -> Result<(), &'static str> {
-        self.compile_term()?;
-        while let Some(operation) = self.get_operation()? {
-            self.compile_term()?;
+    fn compile_expression(&mut self, expression: &Expression) -> Result<(), CompileError> {
+        self.compile_term(&expression.term)?;
+        for (operation, term) in &expression.operations {
+            self.compile_term(term)?;
             self.vm_output += &operation.to_vm_command_string();
         }
-        return Ok(());
+        Ok(())
     }
 
-    fn compile_term(&mut self) -> Result<(), &'static str> {
-        match self.token_iterator.peek().unwrap() {
-            Token::IntConstant(i) => {
+    fn compile_term(&mut self, term: &Term) -> Result<(), CompileError> {
+        match &term.kind {
+            // the only negative constant is the folded -32768, which is ~32767
+            TermKind::IntConstant(i) if *i < 0 => {
+                self.vm_output += &format!("push constant {}\nnot\n", -i - 1);
+            }
+            TermKind::IntConstant(i) => {
                 self.vm_output += &format!("push constant {}\n", i);
-                self.token_iterator.next();
             }
-            Token::StringConstant(s) => {
+            TermKind::StringConstant(s) => {
                 self.vm_output += &format!("push constant {}\ncall String.new 1\n", s.len());
                 for c in s.chars(){
                     self.vm_output += &format!("push constant {:?}\ncall String.appendChar 2\n", c as u32);
                 }
-                self.token_iterator.next();
             }
-            Token::Keyword(Keyword::True) => {
+            TermKind::True => {
                 self.vm_output += "push constant 0\nnot\n";
-                self.token_iterator.next();
             }
-            Token::Keyword(Keyword::False) => {
+            TermKind::False => {
                 self.vm_output += "push constant 0\n";
-                self.token_iterator.next();
             }
-            Token::Keyword(Keyword::Null) => {
+            TermKind::Null => {
                 self.vm_output += "push constant 0\n";
-                self.token_iterator.next();
             }
-            Token::Keyword(Keyword::This) => {
+            TermKind::This => {
+                if self.current_subroutine_kind == FunctionKind::Jfunction {
+                    return Err(CompileError::ThisInFunction {
+                        function: self.current_subroutine_name.clone(),
+                        span: term.span,
+                    });
+                }
                 self.vm_output += "push pointer 0\n";
-                self.token_iterator.next();
             }
             // (expression)
-            Token::Symbol('(') => {
-                self.parse_specific_symbol('(')?;
-                self.compile_expression()?;
-                self.parse_specific_symbol(')')?;
+            TermKind::Parenthesized(expression) => {
+                self.compile_expression(expression)?;
             }
             // unaryOp term
-            Token::Symbol('-') => {
-                self.parse_specific_symbol('-')?;
-                self.compile_term()?;
+            TermKind::Unary(UnaryOperation::Negate, term) => {
+                self.compile_term(term)?;
                 self.vm_output += "neg\n";
             }
-            Token::Symbol('~') => {
-                self.parse_specific_symbol('~')?;
-                self.compile_term()?;
+            TermKind::Unary(UnaryOperation::Not, term) => {
+                self.compile_term(term)?;
                 self.vm_output += "not\n";
             }
-            // varname | varname[expression] | subroutineCall
-            Token::Identifier(name) => {
-                match **self.token_iterator.peek_nth(1).unwrap() {
-                    // varName[expression]
-                    Token::Symbol('[') => {
-                        self.token_iterator.next();
-                        self.parse_specific_symbol('[')?;
-                        self.compile_expression()?;
-                        self.parse_specific_symbol(']')?;
-                        self.vm_output += &format!("push {}\n", &self.get_vm_code_for_var_name(&name)?);
-                        self.vm_output += "add\npop pointer 1\npush that 0\n";                      
-                    }
-                    // subroutinecall, which is var_name.function_name() or function_name()
-                    Token::Symbol('.') |  Token::Symbol('(') => {
-                        self.compile_subroutine_call()?;
-                    }
-                    // simply the var_name
-                    _ => {
-                        self.token_iterator.next();
-                        self.vm_output += &format!("push {}\n", &self.get_vm_code_for_var_name(&name)?);
-                    }
-                }
+            // varName[expression]
+            TermKind::ArrayAccess(name, index) => {
+                self.compile_expression(index)?;
+                self.vm_output += &format!("push {}\n", &self.get_vm_code_for_var_name(&name.name, name.span)?);
+                self.vm_output += "add\npop pointer 1\npush that 0\n";
+            }
+            // subroutinecall, which is var_name.function_name() or function_name()
+            TermKind::SubroutineCall(call) => {
+                self.compile_subroutine_call(call)?;
+            }
+            // simply the var_name
+            TermKind::Variable(name) => {
+                self.vm_output += &format!("push {}\n", &self.get_vm_code_for_var_name(&name.name, name.span)?);
             }
-            Token::Symbol(_s) => return Err("This symbol is not a term"),
-            _ => return Err("This token is not a term"),
         }
 
-        return Ok(());
+        Ok(())
     }
 
-    fn compile_expression_list(&mut self) -> Result<usize, &'static str> {
-        // (
-        self.parse_specific_symbol('(')?;
-        let mut num_list_elements = 0;
-
-        if **self.token_iterator.peek().unwrap() != Token::Symbol(')') {
-            self.compile_expression()?;
-            num_list_elements += 1;
-        }
-
-        while **self.token_iterator.peek().unwrap() == Token::Symbol(',') {
-            self.parse_specific_symbol(',')?;
-            self.compile_expression()?;
-            num_list_elements += 1;
+    fn compile_expression_list(&mut self, expressions: &[Expression]) -> Result<usize, CompileError> {
+        for expression in expressions {
+            self.compile_expression(expression)?;
         }
-
-        // )
-        self.parse_specific_symbol(')')?;
-        return Ok(num_list_elements);
+        Ok(expressions.len())
     }
 
-    fn compile_subroutine_call(&mut self) -> Result<(), &'static str> {
-        let mut fun_name = self.parse_name()?.to_owned();
-        let mut num_args = 0;
-        // if a dot follows, we have the case className|varName . subRoutineName, otherwise it is just subroutineName
-        if **self.token_iterator.peek().unwrap() == Token::Symbol('.') { // something like Screen.draw()
-            self.parse_specific_symbol('.')?;
-            // if the left side of the dot has an object from our symbol table, we got to push it to the stack as an additional argument
-            match self.get_vm_code_for_var_name(&fun_name) {
-                Ok(vm_code) => {
-                    if let JackVariableType::Jclass(ref class_name) = self.get_symbol_table_entry(&fun_name)?.var_type{
-                        fun_name = class_name.to_owned();
-                    }
-                    self.vm_output+= &format!("push {}\n", vm_code);
-                    num_args = 1;
-                },
-                Err(_) => { } // fun_name not it symbol table, hence not an object
+    /// Checks that the called subroutine exists in the program, can be called in this style and
+    /// gets the right number of arguments. Nothing is checked without program signatures.
+    fn resolve_call(&self, call: &SubroutineCall, class_name: &str, style: CallStyle) -> Result<(), CompileError> {
+        let program = match self.program {
+            Some(program) => program,
+            None => return Ok(()),
+        };
+        let full_name = format!("{}.{}", class_name, call.name.name);
+        if !program.has_class(class_name) && !self.is_incomplete(class_name) {
+            let span = call.receiver.as_ref().map_or(call.name.span, |receiver| receiver.span);
+            return Err(CompileError::UndefinedClass { name: class_name.to_string(), span });
+        }
+        let signature = match program.subroutine(class_name, &call.name.name) {
+            Some(signature) => signature,
+            // the subroutine may be one that could not be parsed
+            None if program.is_incomplete(class_name) || self.is_incomplete(class_name) => return Ok(()),
+            None => {
+                return Err(CompileError::UndefinedSubroutine {
+                    class_name: class_name.to_string(),
+                    name: call.name.name.clone(),
+                    span: call.name.span,
+                })
             }
-            fun_name += &format!(".{}", self.parse_name()?);
-        } else { // something like draw()
-            // Assuming what we call is a method, we need to add the object as argument
-            self.vm_output += &format!("push pointer 0\n");
-            num_args = 1;
-            fun_name = format!("{}.{}",self.class_name, fun_name);
+        };
+        let is_method = signature.kind == FunctionKind::Jmethod;
+        if (style == CallStyle::OnObject && !is_method) || (style == CallStyle::OnClass && is_method) {
+            return Err(CompileError::WrongCallStyle { name: full_name, kind: signature.kind, span: call.span });
         }
-        num_args += self.compile_expression_list()?;
-        self.vm_output += &format!("call {} {}\n",fun_name, num_args);
-
-        return Ok(());
+        if signature.parameter_types.len() != call.arguments.len() {
+            return Err(CompileError::WrongNumberOfArguments {
+                name: full_name,
+                expected: signature.parameter_types.len(),
+                found: call.arguments.len(),
+                span: call.span,
+            });
+        }
+        Ok(())
     }
 
-    fn get_operation(&mut self) -> Result<Option<JackOperation>, &'static str> {
-        let operation = match self.token_iterator.peek().unwrap() {
-            Token::Symbol('+') => JackOperation::Add,
-            Token::Symbol('-') => JackOperation::Subtract,
-            Token::Symbol('*') => JackOperation::Multiply,
-            Token::Symbol('/') => JackOperation::Divide,
-            Token::Symbol('&') => JackOperation::And,
-            Token::Symbol('|') => JackOperation::Or,
-            Token::Symbol('<') => JackOperation::Less,
-            Token::Symbol('>') => JackOperation::Larger,
-            Token::Symbol('=') => JackOperation::Equal,
-            _ => return Ok(None),
-        };
-        self.token_iterator.next();
-        Ok(Some(operation))
+    /// Whether `class_name` is the class being compiled and it has syntax errors
+    fn is_incomplete(&self, class_name: &str) -> bool {
+        !self.syntax_error_offsets.is_empty() && class_name == self.class_name
     }
 
+    /// Whether the subroutine has syntax errors, so some of its statements may be missing
+    fn has_syntax_errors(&self, subroutine_dec: &SubroutineDec) -> bool {
+        let body = subroutine_dec.kind_span.offset..subroutine_dec.end_span.end();
+        self.syntax_error_offsets.iter().any(|offset| body.contains(offset))
+    }
 
-
-    fn parse_type(&mut self) -> Result<JackVariableType, &'static str> {
-        match self.token_iterator.next().unwrap() {
-            Token::Identifier(identifier) => {
-                Ok(JackVariableType::Jclass(identifier.to_string()))
-            }
-            Token::Keyword(Keyword::Int) => {
-                Ok(JackVariableType::Jint)
-            }
-            Token::Keyword(Keyword::Char) => {
-                Ok(JackVariableType::Jchar)
+    fn compile_subroutine_call(&mut self, call: &SubroutineCall) -> Result<(), CompileError> {
+        let mut num_args = 0;
+        let fun_name = match &call.receiver {
+            // className|varName . subRoutineName, something like Screen.draw()
+            Some(receiver) => {
+                let mut fun_name = receiver.name.clone();
+                // if the left side of the dot has an object from our symbol table, we got to push it to the stack as an additional argument
+                // (if fun_name is not in the symbol table, it is a class name and not an object)
+                if let Some(symbol) = self.symbol_table.lookup(&receiver.name).cloned() {
+                    self.check_object_access(&symbol, &receiver.name, receiver.span)?;
+                    match symbol.var_type {
+                        JackVariableType::Jclass(ref class_name) => fun_name = class_name.to_owned(),
+                        ref var_type if self.program.is_some() => {
+                            return Err(CompileError::NotAnObject {
+                                name: receiver.name.clone(),
+                                found: jack_type_name(var_type),
+                                span: receiver.span,
+                            })
+                        }
+                        _ => {}
+                    }
+                    self.resolve_call(call, &fun_name, CallStyle::OnObject)?;
+                    self.vm_output+= &format!("push {}\n", symbol.vm_location());
+                    num_args = 1;
+                } else {
+                    self.resolve_call(call, &fun_name, CallStyle::OnClass)?;
+                }
+                format!("{}.{}", fun_name, call.name.name)
             }
-            Token::Keyword(Keyword::Boolean) => {
-                Ok(JackVariableType::Jboolean)
+            // just subroutineName, something like draw(), which is always a subroutine of this class
+            None => {
+                let class_name = self.class_name.clone();
+                self.resolve_call(call, &class_name, CallStyle::Bare)?;
+                let subroutine_dec = self.class.subroutine_decs.iter()
+                    .find(|subroutine_dec| subroutine_dec.name.name == call.name.name);
+                let kind = match subroutine_dec {
+                    Some(subroutine_dec) => subroutine_dec.kind,
+                    // a subroutine with syntax errors, the class is not compiled to VM code anyway
+                    None if self.is_incomplete(&class_name) => FunctionKind::Jfunction,
+                    None => {
+                        return Err(CompileError::UndefinedSubroutine {
+                            class_name: class_name.clone(),
+                            name: call.name.name.clone(),
+                            span: call.name.span,
+                        })
+                    }
+                };
+                // a method is called on the current object, which we need to add as argument
+                if kind == FunctionKind::Jmethod {
+                    if self.current_subroutine_kind == FunctionKind::Jfunction {
+                        return Err(CompileError::MethodCallInFunction {
+                            name: call.name.name.clone(),
+                            function: self.current_subroutine_name.clone(),
+                            span: call.span,
+                        });
+                    }
+                    self.vm_output += "push pointer 0\n";
+                    num_args = 1;
+                }
+                format!("{}.{}", class_name, call.name.name)
             }
-            _ => Err("Expected a type! Type has to be int, char, boolean, or class name!"),
-        }
-    }
-
-    fn parse_name(&mut self) -> Result<&str, &'static str> {
-        if let Token::Identifier(id) = self.token_iterator.next().unwrap() {
-            Ok(id)
-        } else {
-            Err("Expected a name here!")
-        }
-    }
+        };
+        num_args += self.compile_expression_list(&call.arguments)?;
+        self.vm_output += &format!("call {} {}\n",fun_name, num_args);
 
-    fn parse_specific_symbol(&mut self, c: char) -> Result<(), &'static str> {
-        if *self.token_iterator.next().unwrap() == Token::Symbol(c) {
-            Ok(())
-        } else {
-            Err("Expected a different symbol")
-        }
+        Ok(())
     }
 }
@@ -0,0 +1,80 @@
+//! jack_lints
+//! Warnings about code that compiles but is probably not what was meant: constructors that
+//! return something other than `this`, and functions or methods named `new`, which callers take
+//! for constructors. `Array.new` of the OS API is a function and therefore allowed.
+
+use compile_error::CompileError;
+use jack_ast::*;
+use jack_os::JackOs;
+use jack_tokenizer::Span;
+
+/// Returns the warnings for a class, ordered by position
+pub fn lint_class(class: &Class) -> Vec<CompileError> {
+    let mut warnings = vec![];
+    for subroutine_dec in &class.subroutine_decs {
+        match subroutine_dec.kind {
+            FunctionKind::Jconstructor => {
+                let mut values = vec![];
+                returned_values(&subroutine_dec.statements, &mut values);
+                for (value, span) in values {
+                    match value {
+                        Some(value) if is_this(value) => {}
+                        _ => warnings.push(CompileError::ConstructorReturnsOther {
+                            constructor: subroutine_dec.name.name.clone(),
+                            span: value.map_or(span, |value| value.span),
+                        }),
+                    }
+                }
+            }
+            kind if subroutine_dec.name.name == "new"
+                && !JackOs::provides(&format!("{}.new", class.name.name)) =>
+            {
+                warnings.push(CompileError::NewIsNotAConstructor {
+                    class_name: class.name.name.clone(),
+                    kind,
+                    span: subroutine_dec.name.span,
+                });
+            }
+            _ => {}
+        }
+    }
+    warnings.sort_by_key(|warning| warning.span().offset);
+    warnings
+}
+
+/// Collects the values of all `return` statements, including those in nested blocks, together
+/// with the spans of the statements. A `return;` without a value is collected as `None`.
+fn returned_values<'a>(
+    statements: &'a [Statement],
+    values: &mut Vec<(Option<&'a Expression>, Span)>,
+) {
+    for statement in statements {
+        match &statement.kind {
+            StatementKind::Return(value) => values.push((value.as_ref(), statement.span)),
+            StatementKind::If {
+                then_statements,
+                else_statements,
+                ..
+            } => {
+                returned_values(then_statements, values);
+                if let Some(else_statements) = else_statements {
+                    returned_values(else_statements, values);
+                }
+            }
+            StatementKind::While { statements, .. } => returned_values(statements, values),
+            _ => {}
+        }
+    }
+}
+
+/// Whether an expression is just `this`, possibly in parentheses
+fn is_this(expression: &Expression) -> bool {
+    if !expression.operations.is_empty() {
+        return false;
+    }
+    match &expression.term.kind {
+        TermKind::This => true,
+        TermKind::Parenthesized(inner) => is_this(inner),
+        _ => false,
+    }
+}
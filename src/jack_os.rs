@@ -0,0 +1,803 @@
+//! jack_os
+//! Native implementations of the Jack OS classes `Math`, `String`, `Array`, `Output`, `Screen`,
+//! `Keyboard`, `Memory` and `Sys` for the VM interpreter. They follow the API and the error
+//! codes of the book, but keep their bookkeeping (free list, cursor, string layout) in Rust.
+//! A string object is stored in the heap as `[max_length, length, chars...]`.
+
+use vm_interpreter::{HEAP_BASE, KBD, SCREEN};
+
+/// Width of the screen in pixels
+pub const SCREEN_WIDTH: usize = 512;
+/// Height of the screen in pixels
+pub const SCREEN_HEIGHT: usize = 256;
+/// Number of 16 bit words of a screen row
+pub const WORDS_PER_ROW: usize = SCREEN_WIDTH / 16;
+
+/// Text rows of the screen
+const TEXT_ROWS: usize = 23;
+/// Characters per text row
+const TEXT_COLUMNS: usize = 64;
+/// Pixel rows of a character
+const CHAR_HEIGHT: usize = 11;
+
+/// Key code of the enter key and the character `String.newLine()`
+pub const NEW_LINE: i16 = 128;
+/// Key code of the backspace key and the character `String.backSpace()`
+pub const BACKSPACE: i16 = 129;
+
+/// Number of interpreter steps that `Sys.wait` waits per millisecond
+pub const STEPS_PER_MILLISECOND: u64 = 100;
+
+/// All functions provided by this module
+const BUILTINS: &[&str] = &[
+    "Math.init",
+    "Math.abs",
+    "Math.multiply",
+    "Math.divide",
+    "Math.min",
+    "Math.max",
+    "Math.sqrt",
+    "String.new",
+    "String.dispose",
+    "String.length",
+    "String.charAt",
+    "String.setCharAt",
+    "String.appendChar",
+    "String.eraseLastChar",
+    "String.intValue",
+    "String.setInt",
+    "String.backSpace",
+    "String.doubleQuote",
+    "String.newLine",
+    "Array.new",
+    "Array.dispose",
+    "Output.init",
+    "Output.moveCursor",
+    "Output.printChar",
+    "Output.printString",
+    "Output.printInt",
+    "Output.println",
+    "Output.backSpace",
+    "Screen.init",
+    "Screen.clearScreen",
+    "Screen.setColor",
+    "Screen.drawPixel",
+    "Screen.drawLine",
+    "Screen.drawRectangle",
+    "Screen.drawCircle",
+    "Keyboard.init",
+    "Keyboard.keyPressed",
+    "Keyboard.readChar",
+    "Keyboard.readLine",
+    "Keyboard.readInt",
+    "Memory.init",
+    "Memory.peek",
+    "Memory.poke",
+    "Memory.alloc",
+    "Memory.deAlloc",
+    "Sys.halt",
+    "Sys.error",
+    "Sys.wait",
+];
+
+/// Bitmaps of the characters 32 to 126 as in `Output.jack`, 11 rows each, bit 0 is the leftmost
+/// pixel
+const FONT: [[u8; CHAR_HEIGHT]; 95] = [
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],           // space
+    [12, 30, 30, 30, 12, 12, 0, 12, 12, 0, 0],   // !
+    [54, 54, 20, 0, 0, 0, 0, 0, 0, 0, 0],        // "
+    [0, 18, 18, 63, 18, 18, 63, 18, 18, 0, 0],   // #
+    [12, 30, 51, 3, 30, 48, 51, 30, 12, 12, 0],  // $
+    [0, 0, 35, 51, 24, 12, 6, 51, 49, 0, 0],     // %
+    [12, 30, 30, 12, 54, 27, 27, 27, 54, 0, 0],  // &
+    [12, 12, 6, 0, 0, 0, 0, 0, 0, 0, 0],         // '
+    [24, 12, 6, 6, 6, 6, 6, 12, 24, 0, 0],       // (
+    [6, 12, 24, 24, 24, 24, 24, 12, 6, 0, 0],    // )
+    [0, 0, 0, 51, 30, 63, 30, 51, 0, 0, 0],      // *
+    [0, 0, 0, 12, 12, 63, 12, 12, 0, 0, 0],      // +
+    [0, 0, 0, 0, 0, 0, 0, 12, 12, 6, 0],         // ,
+    [0, 0, 0, 0, 0, 63, 0, 0, 0, 0, 0],          // -
+    [0, 0, 0, 0, 0, 0, 0, 12, 12, 0, 0],         // .
+    [0, 0, 32, 48, 24, 12, 6, 3, 1, 0, 0],       // /
+    [12, 30, 51, 51, 51, 51, 51, 30, 12, 0, 0],  // 0
+    [12, 14, 15, 12, 12, 12, 12, 12, 63, 0, 0],  // 1
+    [30, 51, 48, 24, 12, 6, 3, 51, 63, 0, 0],    // 2
+    [30, 51, 48, 48, 28, 48, 48, 51, 30, 0, 0],  // 3
+    [16, 24, 28, 26, 25, 63, 24, 24, 60, 0, 0],  // 4
+    [63, 3, 3, 31, 48, 48, 48, 51, 30, 0, 0],    // 5
+    [28, 6, 3, 3, 31, 51, 51, 51, 30, 0, 0],     // 6
+    [63, 49, 48, 48, 24, 12, 12, 12, 12, 0, 0],  // 7
+    [30, 51, 51, 51, 30, 51, 51, 51, 30, 0, 0],  // 8
+    [30, 51, 51, 51, 62, 48, 48, 24, 14, 0, 0],  // 9
+    [0, 0, 12, 12, 0, 0, 12, 12, 0, 0, 0],       // :
+    [0, 0, 12, 12, 0, 0, 12, 12, 6, 0, 0],       // ;
+    [0, 0, 24, 12, 6, 3, 6, 12, 24, 0, 0],       // <
+    [0, 0, 0, 63, 0, 0, 63, 0, 0, 0, 0],         // =
+    [0, 0, 3, 6, 12, 24, 12, 6, 3, 0, 0],        // >
+    [30, 51, 51, 24, 12, 12, 0, 12, 12, 0, 0],   // ?
+    [30, 51, 51, 59, 59, 59, 27, 3, 30, 0, 0],   // @
+    [12, 30, 51, 51, 63, 51, 51, 51, 51, 0, 0],  // A
+    [31, 51, 51, 51, 31, 51, 51, 51, 31, 0, 0],  // B
+    [28, 54, 35, 3, 3, 3, 35, 54, 28, 0, 0],     // C
+    [15, 27, 51, 51, 51, 51, 51, 27, 15, 0, 0],  // D
+    [63, 51, 35, 11, 15, 11, 35, 51, 63, 0, 0],  // E
+    [63, 51, 35, 11, 15, 11, 3, 3, 3, 0, 0],     // F
+    [28, 54, 35, 3, 59, 51, 51, 54, 44, 0, 0],   // G
+    [51, 51, 51, 51, 63, 51, 51, 51, 51, 0, 0],  // H
+    [30, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0],  // I
+    [60, 24, 24, 24, 24, 24, 27, 27, 14, 0, 0],  // J
+    [51, 51, 51, 27, 15, 27, 51, 51, 51, 0, 0],  // K
+    [3, 3, 3, 3, 3, 3, 35, 51, 63, 0, 0],        // L
+    [33, 51, 63, 63, 51, 51, 51, 51, 51, 0, 0],  // M
+    [51, 51, 55, 55, 63, 59, 59, 51, 51, 0, 0],  // N
+    [30, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0],  // O
+    [31, 51, 51, 51, 31, 3, 3, 3, 3, 0, 0],      // P
+    [30, 51, 51, 51, 51, 51, 63, 59, 30, 48, 0], // Q
+    [31, 51, 51, 51, 31, 27, 51, 51, 51, 0, 0],  // R
+    [30, 51, 51, 6, 28, 48, 51, 51, 30, 0, 0],   // S
+    [63, 63, 45, 12, 12, 12, 12, 12, 30, 0, 0],  // T
+    [51, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0],  // U
+    [51, 51, 51, 51, 51, 30, 30, 12, 12, 0, 0],  // V
+    [51, 51, 51, 51, 51, 63, 63, 63, 18, 0, 0],  // W
+    [51, 51, 30, 30, 12, 30, 30, 51, 51, 0, 0],  // X
+    [51, 51, 51, 51, 30, 12, 12, 12, 30, 0, 0],  // Y
+    [63, 51, 49, 24, 12, 6, 35, 51, 63, 0, 0],   // Z
+    [30, 6, 6, 6, 6, 6, 6, 6, 30, 0, 0],         // [
+    [0, 0, 1, 3, 6, 12, 24, 48, 32, 0, 0],       // \
+    [30, 24, 24, 24, 24, 24, 24, 24, 30, 0, 0],  // ]
+    [8, 28, 54, 0, 0, 0, 0, 0, 0, 0, 0],         // ^
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 63, 0],          // _
+    [6, 12, 24, 0, 0, 0, 0, 0, 0, 0, 0],         // `
+    [0, 0, 0, 14, 24, 30, 27, 27, 54, 0, 0],     // a
+    [3, 3, 3, 15, 27, 51, 51, 51, 30, 0, 0],     // b
+    [0, 0, 0, 30, 51, 3, 3, 51, 30, 0, 0],       // c
+    [48, 48, 48, 60, 54, 51, 51, 51, 30, 0, 0],  // d
+    [0, 0, 0, 30, 51, 63, 3, 51, 30, 0, 0],      // e
+    [28, 54, 38, 6, 15, 6, 6, 6, 15, 0, 0],      // f
+    [0, 0, 30, 51, 51, 51, 62, 48, 51, 30, 0],   // g
+    [3, 3, 3, 27, 55, 51, 51, 51, 51, 0, 0],     // h
+    [12, 12, 0, 14, 12, 12, 12, 12, 30, 0, 0],   // i
+    [48, 48, 0, 56, 48, 48, 48, 48, 51, 30, 0],  // j
+    [3, 3, 3, 51, 27, 15, 15, 27, 51, 0, 0],     // k
+    [14, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0],  // l
+    [0, 0, 0, 29, 63, 43, 43, 43, 43, 0, 0],     // m
+    [0, 0, 0, 29, 51, 51, 51, 51, 51, 0, 0],     // n
+    [0, 0, 0, 30, 51, 51, 51, 51, 30, 0, 0],     // o
+    [0, 0, 0, 30, 51, 51, 51, 31, 3, 3, 0],      // p
+    [0, 0, 0, 30, 51, 51, 51, 62, 48, 48, 0],    // q
+    [0, 0, 0, 29, 55, 51, 3, 3, 7, 0, 0],        // r
+    [0, 0, 0, 30, 51, 6, 24, 51, 30, 0, 0],      // s
+    [4, 6, 6, 15, 6, 6, 6, 54, 28, 0, 0],        // t
+    [0, 0, 0, 27, 27, 27, 27, 27, 54, 0, 0],     // u
+    [0, 0, 0, 51, 51, 51, 51, 30, 12, 0, 0],     // v
+    [0, 0, 0, 51, 51, 51, 63, 63, 18, 0, 0],     // w
+    [0, 0, 0, 51, 30, 12, 12, 30, 51, 0, 0],     // x
+    [0, 0, 0, 51, 51, 51, 62, 48, 24, 15, 0],    // y
+    [0, 0, 0, 63, 27, 12, 6, 51, 63, 0, 0],      // z
+    [56, 12, 12, 12, 7, 12, 12, 12, 56, 0, 0],   // {
+    [12, 12, 12, 12, 12, 12, 12, 12, 12, 0, 0],  // |
+    [7, 12, 12, 12, 56, 12, 12, 12, 7, 0, 0],    // }
+    [38, 45, 25, 0, 0, 0, 0, 0, 0, 0, 0],        // ~
+];
+
+/// Bitmap of characters that have no glyph, also used as the cursor
+const BLACK_SQUARE: [u8; CHAR_HEIGHT] = [63, 63, 63, 63, 63, 63, 63, 63, 63, 0, 0];
+
+/// What the interpreter has to do after a builtin function was called
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuiltinResult {
+    /// The function returned the value
+    Return(i16),
+    /// The function waits for input or time and has to be called again with the same arguments
+    Block,
+    /// The program stops, e.g. after `Sys.halt` or `Sys.error`
+    Halt,
+}
+
+/// A memory access of a builtin function outside of the RAM
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BadAddress(pub i32);
+
+/// State of `Keyboard.readChar`, `Keyboard.readLine` and `Keyboard.readInt` between retries
+#[derive(Debug, Default)]
+struct KeyboardState {
+    /// The key that is held down and is returned when it is released
+    pressed_key: Option<i16>,
+    /// Whether the cursor is drawn
+    cursor_shown: bool,
+    /// The characters read so far by `readLine` or `readInt`
+    line: Option<Vec<i16>>,
+}
+
+/// Free list allocator for the heap in RAM[2048..16384]
+#[derive(Debug)]
+struct Heap {
+    /// Free blocks as (address, size), ordered by address
+    free_blocks: Vec<(usize, usize)>,
+    /// Allocated blocks as (address, size)
+    allocated: Vec<(usize, usize)>,
+}
+
+impl Heap {
+    fn new() -> Self {
+        Heap {
+            free_blocks: vec![(HEAP_BASE, SCREEN - HEAP_BASE)],
+            allocated: vec![],
+        }
+    }
+
+    /// First fit allocation
+    fn alloc(&mut self, size: usize) -> Option<usize> {
+        let index = self.free_blocks.iter().position(|&(_, free)| free >= size)?;
+        let (address, free) = self.free_blocks[index];
+        if free == size {
+            self.free_blocks.remove(index);
+        } else {
+            self.free_blocks[index] = (address + size, free - size);
+        }
+        self.allocated.push((address, size));
+        Some(address)
+    }
+
+    /// Frees a block and merges it with its free neighbours. Unknown addresses are ignored.
+    fn free(&mut self, address: usize) {
+        let size = match self.allocated.iter().position(|&(a, _)| a == address) {
+            Some(index) => self.allocated.swap_remove(index).1,
+            None => return,
+        };
+        let index = self
+            .free_blocks
+            .iter()
+            .position(|&(a, _)| a > address)
+            .unwrap_or(self.free_blocks.len());
+        self.free_blocks.insert(index, (address, size));
+        if index + 1 < self.free_blocks.len()
+            && self.free_blocks[index].0 + self.free_blocks[index].1 == self.free_blocks[index + 1].0
+        {
+            self.free_blocks[index].1 += self.free_blocks.remove(index + 1).1;
+        }
+        if index > 0
+            && self.free_blocks[index - 1].0 + self.free_blocks[index - 1].1 == self.free_blocks[index].0
+        {
+            self.free_blocks[index - 1].1 += self.free_blocks.remove(index).1;
+        }
+    }
+}
+
+/// State of the native OS
+#[derive(Debug)]
+pub struct JackOs {
+    heap: Heap,
+    color: bool,
+    cursor_row: usize,
+    cursor_column: usize,
+    output_text: String,
+    keyboard: KeyboardState,
+    wait_until: Option<u64>,
+    sys_error: Option<i16>,
+}
+
+impl Default for JackOs {
+    fn default() -> Self {
+        JackOs::new()
+    }
+}
+
+fn read(ram: &[i16], address: i32) -> Result<i16, BadAddress> {
+    if address < 0 || address as usize >= ram.len() {
+        return Err(BadAddress(address));
+    }
+    Ok(ram[address as usize])
+}
+
+fn write(ram: &mut [i16], address: i32, value: i16) -> Result<(), BadAddress> {
+    if address < 0 || address as usize >= ram.len() {
+        return Err(BadAddress(address));
+    }
+    ram[address as usize] = value;
+    Ok(())
+}
+
+/// Adds a character printed with `Output.printChar` to `text` like `JackOs::output_text` does:
+/// new line and backspace are applied, characters that cannot be printed become `U+FFFD`
+pub fn record_char(text: &mut String, c: i16) {
+    match c {
+        NEW_LINE => text.push('\n'),
+        BACKSPACE => {
+            text.pop();
+        }
+        _ => text.push(if (32..=126).contains(&c) { (c as u8) as char } else { '\u{fffd}' }),
+    }
+}
+
+/// Integer square root, rounded down
+fn sqrt(x: i16) -> i16 {
+    let mut y = 0i32;
+    while (y + 1) * (y + 1) <= i32::from(x) {
+        y += 1;
+    }
+    y as i16
+}
+
+impl JackOs {
+    /// Constructor
+    pub fn new() -> Self {
+        JackOs {
+            heap: Heap::new(),
+            color: true,
+            cursor_row: 0,
+            cursor_column: 0,
+            output_text: String::new(),
+            keyboard: KeyboardState::default(),
+            wait_until: None,
+            sys_error: None,
+        }
+    }
+
+    /// Whether this module implements the function with the given name
+    pub fn provides(name: &str) -> bool {
+        BUILTINS.contains(&name)
+    }
+
+    /// Everything that was printed with `Output`, with new lines as `\n`
+    pub fn output_text(&self) -> &str {
+        &self.output_text
+    }
+
+    /// The code passed to `Sys.error`, if the program was stopped by it
+    pub fn sys_error(&self) -> Option<i16> {
+        self.sys_error
+    }
+
+    /// Executes the function `name`. `steps` is the number of commands the interpreter has
+    /// executed so far and is the clock of `Sys.wait`.
+    pub fn call(
+        &mut self,
+        name: &str,
+        args: &[i16],
+        ram: &mut [i16],
+        steps: u64,
+    ) -> Result<BuiltinResult, BadAddress> {
+        let arg = |i: usize| args.get(i).cloned().unwrap_or(0);
+        let value = match name {
+            "Math.init" | "Output.init" | "Screen.init" | "Keyboard.init" | "Memory.init" => 0,
+            "Math.abs" => arg(0).wrapping_abs(),
+            "Math.multiply" => arg(0).wrapping_mul(arg(1)),
+            "Math.divide" => {
+                if arg(1) == 0 {
+                    return self.error(3, ram);
+                }
+                arg(0).wrapping_div(arg(1))
+            }
+            "Math.min" => arg(0).min(arg(1)),
+            "Math.max" => arg(0).max(arg(1)),
+            "Math.sqrt" => {
+                if arg(0) < 0 {
+                    return self.error(4, ram);
+                }
+                sqrt(arg(0))
+            }
+
+            "String.new" => {
+                if arg(0) < 0 {
+                    return self.error(14, ram);
+                }
+                match self.new_string(arg(0) as usize, ram) {
+                    Some(string) => string,
+                    None => return self.error(6, ram),
+                }
+            }
+            "String.dispose" | "Array.dispose" | "Memory.deAlloc" => {
+                self.heap.free(arg(0) as u16 as usize);
+                0
+            }
+            "String.length" => read(ram, i32::from(arg(0)) + 1)?,
+            "String.charAt" | "String.setCharAt" => {
+                let this = i32::from(arg(0));
+                let length = read(ram, this + 1)?;
+                if arg(1) < 0 || arg(1) >= length {
+                    return self.error(if name == "String.charAt" { 15 } else { 16 }, ram);
+                }
+                let address = this + 2 + i32::from(arg(1));
+                if name == "String.charAt" {
+                    read(ram, address)?
+                } else {
+                    write(ram, address, arg(2))?;
+                    0
+                }
+            }
+            "String.appendChar" => {
+                let this = i32::from(arg(0));
+                let length = read(ram, this + 1)?;
+                if length >= read(ram, this)? {
+                    return self.error(17, ram);
+                }
+                write(ram, this + 2 + i32::from(length), arg(1))?;
+                write(ram, this + 1, length + 1)?;
+                arg(0)
+            }
+            "String.eraseLastChar" => {
+                let this = i32::from(arg(0));
+                let length = read(ram, this + 1)?;
+                if length == 0 {
+                    return self.error(18, ram);
+                }
+                write(ram, this + 1, length - 1)?;
+                0
+            }
+            "String.intValue" => {
+                let text: String = self
+                    .string_chars(arg(0), ram)?
+                    .iter()
+                    .map(|&c| (c as u8) as char)
+                    .collect();
+                parse_int_prefix(&text)
+            }
+            "String.setInt" => {
+                let this = i32::from(arg(0));
+                let digits = arg(1).to_string();
+                if digits.len() as i16 > read(ram, this)? {
+                    return self.error(19, ram);
+                }
+                for (i, c) in digits.bytes().enumerate() {
+                    write(ram, this + 2 + i as i32, i16::from(c))?;
+                }
+                write(ram, this + 1, digits.len() as i16)?;
+                0
+            }
+            "String.backSpace" => BACKSPACE,
+            "String.doubleQuote" => i16::from(b'"'),
+            "String.newLine" => NEW_LINE,
+
+            "Array.new" => {
+                if arg(0) <= 0 {
+                    return self.error(2, ram);
+                }
+                match self.heap.alloc(arg(0) as usize) {
+                    Some(address) => address as i16,
+                    None => return self.error(6, ram),
+                }
+            }
+
+            "Output.moveCursor" => {
+                if arg(0) < 0 || arg(0) as usize >= TEXT_ROWS || arg(1) < 0 || arg(1) as usize >= TEXT_COLUMNS {
+                    return self.error(20, ram);
+                }
+                self.cursor_row = arg(0) as usize;
+                self.cursor_column = arg(1) as usize;
+                0
+            }
+            "Output.printChar" => {
+                self.print_char(arg(0), ram);
+                0
+            }
+            "Output.printString" => {
+                for c in self.string_chars(arg(0), ram)? {
+                    self.print_char(c, ram);
+                }
+                0
+            }
+            "Output.printInt" => {
+                self.print_str(&arg(0).to_string(), ram);
+                0
+            }
+            "Output.println" => {
+                self.print_char(NEW_LINE, ram);
+                0
+            }
+            "Output.backSpace" => {
+                self.print_char(BACKSPACE, ram);
+                0
+            }
+
+            "Screen.clearScreen" => {
+                for word in &mut ram[SCREEN..KBD] {
+                    *word = 0;
+                }
+                0
+            }
+            "Screen.setColor" => {
+                self.color = arg(0) != 0;
+                0
+            }
+            "Screen.drawPixel" => {
+                if !on_screen(arg(0), arg(1)) {
+                    return self.error(7, ram);
+                }
+                self.draw_pixel(arg(0) as usize, arg(1) as usize, ram);
+                0
+            }
+            "Screen.drawLine" => {
+                if !on_screen(arg(0), arg(1)) || !on_screen(arg(2), arg(3)) {
+                    return self.error(8, ram);
+                }
+                self.draw_line(arg(0), arg(1), arg(2), arg(3), ram);
+                0
+            }
+            "Screen.drawRectangle" => {
+                if !on_screen(arg(0), arg(1)) || !on_screen(arg(2), arg(3)) || arg(0) > arg(2) || arg(1) > arg(3) {
+                    return self.error(9, ram);
+                }
+                for y in arg(1)..=arg(3) {
+                    self.draw_horizontal_line(arg(0), arg(2), y, ram);
+                }
+                0
+            }
+            "Screen.drawCircle" => {
+                let (x, y, r) = (arg(0), arg(1), arg(2));
+                if !on_screen(x, y) {
+                    return self.error(12, ram);
+                }
+                if !(0..=181).contains(&r) || !on_screen(x - r, y - r) || !on_screen(x + r, y + r) {
+                    return self.error(13, ram);
+                }
+                for dy in -r..=r {
+                    let half = sqrt(r * r - dy * dy);
+                    self.draw_horizontal_line(x - half, x + half, y + dy, ram);
+                }
+                0
+            }
+
+            "Keyboard.keyPressed" => ram[KBD],
+            "Keyboard.readChar" => match self.read_key(ram) {
+                Some(c) => {
+                    self.print_char(c, ram);
+                    c
+                }
+                None => return Ok(BuiltinResult::Block),
+            },
+            "Keyboard.readLine" | "Keyboard.readInt" => {
+                let line = match self.read_line(arg(0), ram)? {
+                    Some(line) => line,
+                    None => return Ok(BuiltinResult::Block),
+                };
+                if name == "Keyboard.readLine" {
+                    let string = match self.new_string(line.len(), ram) {
+                        Some(string) => string,
+                        None => return self.error(6, ram),
+                    };
+                    for (i, c) in line.iter().enumerate() {
+                        ram[string as usize + 2 + i] = *c;
+                    }
+                    ram[string as usize + 1] = line.len() as i16;
+                    string
+                } else {
+                    let text: String = line.iter().map(|&c| (c as u8) as char).collect();
+                    parse_int_prefix(&text)
+                }
+            }
+
+            "Memory.peek" => read(ram, i32::from(arg(0)))?,
+            "Memory.poke" => {
+                write(ram, i32::from(arg(0)), arg(1))?;
+                0
+            }
+            "Memory.alloc" => {
+                if arg(0) <= 0 {
+                    return self.error(5, ram);
+                }
+                match self.heap.alloc(arg(0) as usize) {
+                    Some(address) => address as i16,
+                    None => return self.error(6, ram),
+                }
+            }
+
+            "Sys.halt" => return Ok(BuiltinResult::Halt),
+            "Sys.error" => return self.error(arg(0), ram),
+            "Sys.wait" => {
+                if arg(0) <= 0 {
+                    return self.error(1, ram);
+                }
+                let until = *self
+                    .wait_until
+                    .get_or_insert(steps + arg(0) as u64 * STEPS_PER_MILLISECOND);
+                if steps < until {
+                    return Ok(BuiltinResult::Block);
+                }
+                self.wait_until = None;
+                0
+            }
+            _ => unreachable!("{} is not a builtin function", name),
+        };
+        Ok(BuiltinResult::Return(value))
+    }
+
+    /// Prints `ERR<code>` and halts, as `Sys.error` does
+    fn error(&mut self, code: i16, ram: &mut [i16]) -> Result<BuiltinResult, BadAddress> {
+        self.print_str(&format!("ERR{}", code), ram);
+        self.sys_error = Some(code);
+        Ok(BuiltinResult::Halt)
+    }
+
+    fn new_string(&mut self, max_length: usize, ram: &mut [i16]) -> Option<i16> {
+        let address = self.heap.alloc(max_length + 2)?;
+        ram[address] = max_length as i16;
+        ram[address + 1] = 0;
+        Some(address as i16)
+    }
+
+    fn string_chars(&self, string: i16, ram: &[i16]) -> Result<Vec<i16>, BadAddress> {
+        let this = i32::from(string);
+        let length = read(ram, this + 1)?;
+        (0..i32::from(length)).map(|i| read(ram, this + 2 + i)).collect()
+    }
+
+    fn print_str(&mut self, text: &str, ram: &mut [i16]) {
+        for c in text.bytes() {
+            self.print_char(i16::from(c), ram);
+        }
+    }
+
+    /// Adds a printed character to `output_text`
+    fn record_char(&mut self, c: i16) {
+        record_char(&mut self.output_text, c);
+    }
+
+    /// Prints a character at the cursor and advances the cursor. New line and backspace move the
+    /// cursor instead.
+    fn print_char(&mut self, c: i16, ram: &mut [i16]) {
+        self.record_char(c);
+        match c {
+            NEW_LINE => {
+                self.cursor_column = 0;
+                self.cursor_row = (self.cursor_row + 1) % TEXT_ROWS;
+            }
+            BACKSPACE => {
+                if self.cursor_column > 0 {
+                    self.cursor_column -= 1;
+                } else if self.cursor_row > 0 {
+                    self.cursor_row -= 1;
+                    self.cursor_column = TEXT_COLUMNS - 1;
+                }
+                self.draw_char(i16::from(b' '), ram);
+            }
+            _ => {
+                self.draw_char(c, ram);
+                self.cursor_column += 1;
+                if self.cursor_column == TEXT_COLUMNS {
+                    self.cursor_column = 0;
+                    self.cursor_row = (self.cursor_row + 1) % TEXT_ROWS;
+                }
+            }
+        }
+    }
+
+    /// Draws a character at the cursor without moving it
+    fn draw_char(&self, c: i16, ram: &mut [i16]) {
+        let bitmap = if (32..=126).contains(&c) {
+            &FONT[(c - 32) as usize]
+        } else {
+            &BLACK_SQUARE
+        };
+        for (row, bits) in bitmap.iter().enumerate() {
+            let address = SCREEN
+                + (self.cursor_row * CHAR_HEIGHT + row) * WORDS_PER_ROW
+                + self.cursor_column / 2;
+            let word = ram[address] as u16;
+            ram[address] = if self.cursor_column % 2 == 0 {
+                (word & 0xff00) | u16::from(*bits)
+            } else {
+                (word & 0x00ff) | (u16::from(*bits) << 8)
+            } as i16;
+        }
+    }
+
+    /// Waits until a key is pressed and released, showing the cursor meanwhile
+    fn read_key(&mut self, ram: &mut [i16]) -> Option<i16> {
+        if !self.keyboard.cursor_shown {
+            self.draw_char(-1, ram);
+            self.keyboard.cursor_shown = true;
+        }
+        let key = ram[KBD];
+        match self.keyboard.pressed_key {
+            None => {
+                if key != 0 {
+                    self.keyboard.pressed_key = Some(key);
+                }
+                None
+            }
+            Some(pressed_key) => {
+                if key != 0 {
+                    return None;
+                }
+                self.keyboard.pressed_key = None;
+                self.keyboard.cursor_shown = false;
+                self.draw_char(i16::from(b' '), ram);
+                Some(pressed_key)
+            }
+        }
+    }
+
+    /// Prints the message, then reads characters until enter is pressed, handling backspace
+    fn read_line(&mut self, message: i16, ram: &mut [i16]) -> Result<Option<Vec<i16>>, BadAddress> {
+        if self.keyboard.line.is_none() {
+            for c in self.string_chars(message, ram)? {
+                self.print_char(c, ram);
+            }
+            self.keyboard.line = Some(vec![]);
+        }
+        let c = match self.read_key(ram) {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        match c {
+            NEW_LINE => {
+                self.print_char(NEW_LINE, ram);
+                Ok(self.keyboard.line.take())
+            }
+            BACKSPACE => {
+                let line = self.keyboard.line.as_mut().unwrap();
+                if line.pop().is_some() {
+                    self.print_char(BACKSPACE, ram);
+                }
+                Ok(None)
+            }
+            _ => {
+                self.keyboard.line.as_mut().unwrap().push(c);
+                self.print_char(c, ram);
+                Ok(None)
+            }
+        }
+    }
+
+    fn draw_pixel(&self, x: usize, y: usize, ram: &mut [i16]) {
+        let address = SCREEN + y * WORDS_PER_ROW + x / 16;
+        let bit = 1i16 << (x % 16);
+        if self.color {
+            ram[address] |= bit;
+        } else {
+            ram[address] &= !bit;
+        }
+    }
+
+    fn draw_horizontal_line(&self, x1: i16, x2: i16, y: i16, ram: &mut [i16]) {
+        for x in x1.min(x2)..=x1.max(x2) {
+            self.draw_pixel(x as usize, y as usize, ram);
+        }
+    }
+
+    /// Bresenham's line algorithm
+    fn draw_line(&self, x1: i16, y1: i16, x2: i16, y2: i16, ram: &mut [i16]) {
+        let (x1, y1, x2, y2) = (i32::from(x1), i32::from(y1), i32::from(x2), i32::from(y2));
+        let dx = (x2 - x1).abs();
+        let dy = -(y2 - y1).abs();
+        let step_x = if x1 < x2 { 1 } else { -1 };
+        let step_y = if y1 < y2 { 1 } else { -1 };
+        let (mut x, mut y, mut error) = (x1, y1, dx + dy);
+        loop {
+            self.draw_pixel(x as usize, y as usize, ram);
+            if x == x2 && y == y2 {
+                break;
+            }
+            if 2 * error >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if 2 * error <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+}
+
+fn on_screen(x: i16, y: i16) -> bool {
+    x >= 0 && (x as usize) < SCREEN_WIDTH && y >= 0 && (y as usize) < SCREEN_HEIGHT
+}
+
+/// Reads an optional minus sign followed by digits, like `String.intValue`
+fn parse_int_prefix(text: &str) -> i16 {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let mut value = 0i16;
+    for c in digits.bytes().take_while(u8::is_ascii_digit) {
+        value = value.wrapping_mul(10).wrapping_add(i16::from(c - b'0'));
+    }
+    if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    }
+}
@@ -0,0 +1,736 @@
+//! jack_parser
+//! Recursive-descent parser that turns the tokens of a Jack class into a `jack_ast::Class`.
+extern crate peek_nth;
+
+use self::peek_nth::{IteratorExt, PeekableNth};
+
+use compile_error::{CompileError, DEFAULT_MAX_ERRORS};
+use jack_ast::*;
+use jack_tokenizer::{Keyword, Span, SpannedToken, Token};
+
+use std::mem;
+use std::slice::Iter;
+
+const STATEMENT_START: [&str; 6] = ["let", "if", "while", "do", "return", "}"];
+const STATEMENT_KEYWORDS: [Keyword; 5] = [Keyword::Let, Keyword::If, Keyword::While, Keyword::Do, Keyword::Return];
+const VAR_DEC_RECOVERY: [Keyword; 6] = [Keyword::Var, Keyword::Let, Keyword::If, Keyword::While, Keyword::Do, Keyword::Return];
+const CLASS_MEMBER_KEYWORDS: [Keyword; 5] = [Keyword::Static, Keyword::Field, Keyword::Constructor, Keyword::Function, Keyword::Method];
+const SUBROUTINE_KEYWORDS: [Keyword; 3] = [Keyword::Constructor, Keyword::Function, Keyword::Method];
+const TYPES: [&str; 4] = ["int", "char", "boolean", "class name"];
+const TERM_START: [&str; 10] = [
+    "integer constant", "string constant", "true", "false", "null", "this", "(", "-", "~", "identifier",
+];
+
+/// JackParser struct
+pub struct JackParser<'a> {
+    token_iterator: PeekableNth<Iter<'a, SpannedToken>>,
+    previous_span: Span,
+    eof_span: Span,
+    errors: Vec<CompileError>,
+    max_errors: usize,
+}
+
+impl<'a> JackParser<'a> {
+    /// Constructor for the tokens of the file `file_id`
+    pub fn new(tokens: &'a [SpannedToken], file_id: usize) -> Self {
+        JackParser {
+            token_iterator: tokens.iter().peekable_nth(),
+            previous_span: Span::default(),
+            eof_span: Self::span_after_last_token(tokens, file_id),
+            errors: vec![],
+            max_errors: DEFAULT_MAX_ERRORS,
+        }
+    }
+
+    /// Sets the number of errors after which parsing stops. At least one error is always reported.
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors.max(1);
+        self
+    }
+
+    /// Main function. Parses a complete class.
+    /// The parser recovers from syntax errors, so all independent errors of the class are returned,
+    /// up to the configured maximum.
+    pub fn parse_class(&mut self) -> Result<Class, Vec<CompileError>> {
+        match self.parse_class_partial() {
+            (Some(class), ref errors) if errors.is_empty() => Ok(class),
+            (_, errors) => Err(errors),
+        }
+    }
+
+    /// Like `parse_class`, but also returns the parts of the class that could be parsed despite
+    /// syntax errors. Statements and subroutine declarations that contain errors are left out.
+    /// The class is `None` if parsing could not continue up to the end of the class.
+    pub fn parse_class_partial(&mut self) -> (Option<Class>, Vec<CompileError>) {
+        let class = match self.parse_class_dec() {
+            Ok(class) => Some(class),
+            Err(error) => {
+                if !self.errors.contains(&error) {
+                    self.errors.push(error);
+                }
+                None
+            }
+        };
+        (class, mem::take(&mut self.errors))
+    }
+
+    /// An empty span directly behind the last token, which is where a truncated file ends.
+    /// Without any tokens, that is the start of the file.
+    fn span_after_last_token(tokens: &[SpannedToken], file_id: usize) -> Span {
+        match tokens.last() {
+            Some(last) => Span {
+                file_id: last.span.file_id,
+                offset: last.span.end(),
+                line: last.span.line,
+                column: last.span.column + last.span.len,
+                len: 0,
+            },
+            None => Span {
+                file_id,
+                offset: 0,
+                line: 1,
+                column: 1,
+                len: 0,
+            },
+        }
+    }
+
+    fn unexpected_eof(&self, expected: &[&str]) -> CompileError {
+        CompileError::UnexpectedEof {
+            expected: expected.iter().map(|s| s.to_string()).collect(),
+            span: self.eof_span,
+        }
+    }
+
+    /// Consumes the next token. At the end of the input, an error listing `expected` is returned.
+    fn next_token(&mut self, expected: &[&str]) -> Result<&'a SpannedToken, CompileError> {
+        match self.token_iterator.next() {
+            Some(token) => {
+                self.read(token);
+                Ok(token)
+            }
+            None => Err(self.unexpected_eof(expected)),
+        }
+    }
+
+    /// Bookkeeping for a consumed token. Its lexical error is recorded, but parsing goes on
+    /// because the token itself is usable.
+    fn read(&mut self, token: &SpannedToken) {
+        self.previous_span = token.span;
+        match token.error {
+            Some(ref error) if !self.errors.contains(error) => self.errors.push(error.clone()),
+            _ => {}
+        }
+    }
+
+    /// Looks at the next token without consuming it. At the end of the input, an error listing
+    /// `expected` is returned.
+    fn peek_token(&mut self, expected: &[&str]) -> Result<&'a SpannedToken, CompileError> {
+        match self.token_iterator.peek() {
+            Some(token) => Ok(*token),
+            None => Err(self.unexpected_eof(expected)),
+        }
+    }
+
+    /// Consumes a token that was successfully peeked before
+    fn advance(&mut self) {
+        if let Some(token) = self.token_iterator.next() {
+            self.read(token);
+        }
+    }
+
+    /// The error for a token that does not fit the grammar. A token with a lexical error is
+    /// reported with that error alone, it explains the problem better.
+    fn unexpected(token: &SpannedToken, expected: &[&str]) -> CompileError {
+        match token.error {
+            Some(ref error) => error.clone(),
+            None => CompileError::unexpected_token(&token.token, expected, token.span),
+        }
+    }
+
+    /// Records an error the parser can recover from. Returns the error instead if it is fatal,
+    /// i.e. if the input ended or the error limit is reached.
+    fn record_error(&mut self, error: CompileError) -> Result<(), CompileError> {
+        // the lexical error of a token may be recorded when it is read and again when the token
+        // turns out to be unexpected
+        if self.errors.contains(&error) {
+            return Ok(());
+        }
+        if matches!(error, CompileError::UnexpectedEof { .. }) || self.errors.len() + 1 >= self.max_errors {
+            Err(error)
+        } else {
+            self.errors.push(error);
+            Ok(())
+        }
+    }
+
+    /// Panic-mode recovery: skips tokens until a `;` (which is consumed if `stop_after_semicolon`),
+    /// a keyword from `stop_at` or a `}` that closes the enclosing block. Blocks in braces are skipped
+    /// as a whole.
+    fn synchronize(&mut self, stop_at: &[Keyword], stop_after_semicolon: bool) {
+        let mut depth = 0;
+        while let Some(spanned_token) = self.token_iterator.peek().cloned() {
+            match &spanned_token.token {
+                Token::Symbol('}') if depth == 0 => return,
+                Token::Symbol('}') => depth -= 1,
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol(';') if depth == 0 && stop_after_semicolon => {
+                    self.advance();
+                    return;
+                }
+                Token::Keyword(kw) if depth == 0 && stop_at.contains(kw) => return,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    fn parse_class_dec(&mut self) -> Result<Class, CompileError> {
+        let class_keyword = self.next_token(&["class"])?;
+        if Token::Keyword(Keyword::Class) != class_keyword.token {
+            return Err(Self::unexpected(class_keyword, &["class"]));
+        }
+
+        // className
+        let name = self.parse_name()?;
+        self.parse_specific_symbol('{')?;
+
+        // classVarDec*
+        let mut class_var_decs = vec![];
+        loop {
+            match self.parse_class_var_dec(&mut class_var_decs) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(error) => {
+                    self.record_error(error)?;
+                    self.synchronize(&CLASS_MEMBER_KEYWORDS, true);
+                }
+            }
+        }
+
+        // subRoutineDec*
+        let mut subroutine_decs = vec![];
+        loop {
+            match self.parse_subroutine_dec() {
+                Ok(Some(subroutine_dec)) => subroutine_decs.push(subroutine_dec),
+                Ok(None) => break,
+                Err(error) => {
+                    self.record_error(error)?;
+                    self.synchronize(&SUBROUTINE_KEYWORDS, false);
+                }
+            }
+        }
+
+        // }
+        self.parse_specific_symbol('}')?;
+
+        // a file holds exactly one class
+        self.parse_end_of_file()?;
+
+        Ok(Class {
+            name,
+            class_var_decs,
+            subroutine_decs,
+        })
+    }
+
+    /// Reports tokens after the class. Their lexical errors are reported as well, they would go
+    /// unnoticed otherwise.
+    fn parse_end_of_file(&mut self) -> Result<(), CompileError> {
+        if let Some(token) = self.token_iterator.next() {
+            self.record_error(Self::unexpected(token, &["end of file"]))?;
+            self.read(token);
+            while let Some(token) = self.token_iterator.next() {
+                self.read(token);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a declaration into `class_var_decs`. The declaration is added as soon as its first
+    /// name is known, so that it is kept if a syntax error follows.
+    fn parse_class_var_dec(&mut self, class_var_decs: &mut Vec<ClassVarDec>) -> Result<bool, CompileError> {
+        // ( static | field )
+        let kind = match &self.peek_token(&["static", "field", "constructor", "function", "method", "}"])?.token {
+            Token::Keyword(Keyword::Static) => VariableKind::Jstatic,
+            Token::Keyword(Keyword::Field) => VariableKind::Jfield,
+            _ => return Ok(false),
+        };
+        self.advance();
+
+        // type
+        let (var_type, type_span) = self.parse_type()?;
+
+        // varName
+        let name = self.parse_name()?;
+        class_var_decs.push(ClassVarDec {
+            kind,
+            var_type,
+            type_span,
+            names: vec![name],
+        });
+
+        // (, varName)*
+        while self.peek_token(&[",", ";"])?.token == Token::Symbol(',') {
+            self.advance(); // peek successful, hence next()
+            let name = self.parse_name()?;
+            class_var_decs.last_mut().unwrap().names.push(name);
+        }
+
+        // ;
+        self.parse_specific_symbol(';')?;
+
+        Ok(true)
+    }
+
+    fn parse_subroutine_dec(&mut self) -> Result<Option<SubroutineDec>, CompileError> {
+        // ( constructor | function | method )
+        let function_keyword = self.peek_token(&["constructor", "function", "method", "}"])?;
+        let kind = match &function_keyword.token {
+            Token::Keyword(Keyword::Constructor) => FunctionKind::Jconstructor,
+            Token::Keyword(Keyword::Function) => FunctionKind::Jfunction,
+            Token::Keyword(Keyword::Method) => FunctionKind::Jmethod,
+            Token::Symbol('}') => return Ok(None),
+            _ => {
+                return Err(Self::unexpected(function_keyword, &["constructor", "function", "method", "}"]))
+            }
+        };
+        self.advance();
+
+        // ( 'void' | type)
+        let return_type_token = self.next_token(&["void", "int", "char", "boolean", "class name"])?;
+        let return_type = match &return_type_token.token {
+            Token::Keyword(Keyword::Int) => Some(JackVariableType::Jint),
+            Token::Keyword(Keyword::Char) => Some(JackVariableType::Jchar),
+            Token::Keyword(Keyword::Boolean) => Some(JackVariableType::Jboolean),
+            Token::Keyword(Keyword::Void) => None,
+            Token::Identifier(id) => Some(JackVariableType::Jclass(id.to_string())),
+            _ => {
+                return Err(Self::unexpected(return_type_token, &["void", "int", "char", "boolean", "class name"]))
+            }
+        };
+
+        // subRoutineName
+        let name = self.parse_name()?;
+
+        // ( parameterList )
+        self.parse_specific_symbol('(')?;
+
+        let mut parameters = vec![];
+        if self.peek_token(&["int", "char", "boolean", "class name", ")"])?.token != Token::Symbol(')') {
+            // if function has more than zero arguments
+            parameters.push(self.parse_parameter()?);
+
+            while self.peek_token(&[",", ")"])?.token == Token::Symbol(',') {
+                self.advance();
+                parameters.push(self.parse_parameter()?);
+            }
+        }
+
+        self.parse_specific_symbol(')')?;
+
+        // subRoutineBody
+
+        // {
+        self.parse_specific_symbol('{')?;
+
+        // varDec*
+        let mut var_decs = vec![];
+        loop {
+            match self.parse_var_dec(&mut var_decs) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(error) => {
+                    self.record_error(error)?;
+                    self.synchronize(&VAR_DEC_RECOVERY, true);
+                }
+            }
+        }
+
+        // statements
+        let statements = self.parse_statements()?;
+
+        // }
+        self.parse_specific_symbol('}')?;
+
+        Ok(Some(SubroutineDec {
+            kind,
+            kind_span: function_keyword.span,
+            return_type,
+            return_type_span: return_type_token.span,
+            name,
+            parameters,
+            var_decs,
+            statements,
+            end_span: self.previous_span,
+        }))
+    }
+
+    fn parse_parameter(&mut self) -> Result<Parameter, CompileError> {
+        let (var_type, type_span) = self.parse_type()?;
+        let name = self.parse_name()?;
+        Ok(Parameter {
+            var_type,
+            type_span,
+            name,
+        })
+    }
+
+    /// Parses a declaration into `var_decs`. The declaration is added as soon as its first
+    /// name is known, so that it is kept if a syntax error follows.
+    fn parse_var_dec(&mut self, var_decs: &mut Vec<VarDec>) -> Result<bool, CompileError> {
+        // var
+        if self.peek_token(&["var", "let", "if", "while", "do", "return", "}"])?.token != Token::Keyword(Keyword::Var) {
+            return Ok(false);
+        }
+        self.advance();
+
+        // type
+        let (var_type, type_span) = self.parse_type()?;
+
+        // varName
+        let name = self.parse_name()?;
+        var_decs.push(VarDec {
+            var_type,
+            type_span,
+            names: vec![name],
+        });
+
+        // (, varName)*
+        while self.peek_token(&[",", ";"])?.token == Token::Symbol(',') {
+            self.advance();
+            let name = self.parse_name()?;
+            var_decs.last_mut().unwrap().names.push(name);
+        }
+
+        // ;
+        self.parse_specific_symbol(';')?;
+
+        Ok(true)
+    }
+
+    /// Parses statements up to the closing `}` of the enclosing block. Statements with errors are
+    /// recorded, skipped and left out of the result.
+    fn parse_statements(&mut self) -> Result<Vec<Statement>, CompileError> {
+        let mut statements = vec![];
+        loop {
+            match self.parse_statement() {
+                Ok(Some(statement)) => statements.push(statement),
+                Ok(None) => return Ok(statements),
+                Err(error) => {
+                    self.record_error(error)?;
+                    self.synchronize(&STATEMENT_KEYWORDS, true);
+                }
+            }
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<Option<Statement>, CompileError> {
+        let statement_keyword = self.peek_token(&STATEMENT_START)?;
+        let kind = match &statement_keyword.token {
+            Token::Keyword(Keyword::Let) => self.parse_let_statement()?,
+            Token::Keyword(Keyword::If) => self.parse_if_statement()?,
+            Token::Keyword(Keyword::While) => self.parse_while_statement()?,
+            Token::Keyword(Keyword::Do) => self.parse_do_statement()?,
+            Token::Keyword(Keyword::Return) => self.parse_return_statement()?,
+            Token::Symbol('}') => return Ok(None),
+            _ => {
+                return Err(Self::unexpected(statement_keyword, &STATEMENT_START))
+            }
+        };
+        Ok(Some(Statement {
+            kind,
+            span: statement_keyword.span.to(self.previous_span),
+        }))
+    }
+
+    fn parse_let_statement(&mut self) -> Result<StatementKind, CompileError> {
+        self.advance();
+
+        // varName
+        let target = self.parse_name()?;
+
+        // [ expression ]
+        let mut index = None;
+        if self.peek_token(&["[", "="])?.token == Token::Symbol('[') {
+            self.parse_specific_symbol('[')?;
+            index = Some(Box::new(self.parse_expression()?));
+            self.parse_specific_symbol(']')?;
+        }
+
+        // =
+        self.parse_specific_symbol('=')?;
+        // expression
+        let value = self.parse_expression()?;
+        // ;
+        self.parse_specific_symbol(';')?;
+
+        Ok(StatementKind::Let {
+            target,
+            index,
+            value,
+        })
+    }
+
+    fn parse_if_statement(&mut self) -> Result<StatementKind, CompileError> {
+        self.advance();
+
+        // ( expression )
+        self.parse_specific_symbol('(')?;
+        let condition = self.parse_expression()?;
+        self.parse_specific_symbol(')')?;
+
+        // { statements }
+        self.parse_specific_symbol('{')?;
+        let then_statements = self.parse_statements()?;
+        self.parse_specific_symbol('}')?;
+
+        let mut else_statements = None;
+        if Token::Keyword(Keyword::Else) == self.peek_token(&["else", "let", "if", "while", "do", "return", "}"])?.token {
+            // else
+            self.advance();
+            // { statements }
+            self.parse_specific_symbol('{')?;
+            else_statements = Some(self.parse_statements()?);
+            self.parse_specific_symbol('}')?;
+        }
+
+        Ok(StatementKind::If {
+            condition,
+            then_statements,
+            else_statements,
+        })
+    }
+
+    fn parse_while_statement(&mut self) -> Result<StatementKind, CompileError> {
+        self.advance();
+
+        // ( expression )
+        self.parse_specific_symbol('(')?;
+        let condition = self.parse_expression()?;
+        self.parse_specific_symbol(')')?;
+
+        // { statements }
+        self.parse_specific_symbol('{')?;
+        let statements = self.parse_statements()?;
+        self.parse_specific_symbol('}')?;
+
+        Ok(StatementKind::While {
+            condition,
+            statements,
+        })
+    }
+
+    fn parse_do_statement(&mut self) -> Result<StatementKind, CompileError> {
+        self.advance();
+
+        let call = self.parse_subroutine_call()?;
+
+        self.parse_specific_symbol(';')?;
+
+        Ok(StatementKind::Do(call))
+    }
+
+    fn parse_return_statement(&mut self) -> Result<StatementKind, CompileError> {
+        self.advance();
+
+        let mut value = None;
+        if self.peek_token(&["expression", ";"])?.token != Token::Symbol(';') {
+            value = Some(self.parse_expression()?);
+        }
+        self.parse_specific_symbol(';')?;
+
+        Ok(StatementKind::Return(value))
+    }
+
+    fn parse_expression(&mut self) -> Result<Expression, CompileError> {
+        let term = self.parse_term()?;
+        let mut operations = vec![];
+        while let Some(operation) = self.parse_operation()? {
+            operations.push((operation, self.parse_term()?));
+        }
+        let span = term.span.to(self.previous_span);
+        Ok(Expression {
+            term,
+            operations,
+            span,
+        })
+    }
+
+    fn parse_term(&mut self) -> Result<Term, CompileError> {
+        let term_start = self.peek_token(&TERM_START)?;
+        let kind = match &term_start.token {
+            Token::IntConstant(i) => {
+                self.advance();
+                TermKind::IntConstant(*i)
+            }
+            Token::StringConstant(s) => {
+                self.advance();
+                TermKind::StringConstant(s.clone())
+            }
+            Token::Keyword(Keyword::True) => {
+                self.advance();
+                TermKind::True
+            }
+            Token::Keyword(Keyword::False) => {
+                self.advance();
+                TermKind::False
+            }
+            Token::Keyword(Keyword::Null) => {
+                self.advance();
+                TermKind::Null
+            }
+            Token::Keyword(Keyword::This) => {
+                self.advance();
+                TermKind::This
+            }
+            // (expression)
+            Token::Symbol('(') => {
+                self.parse_specific_symbol('(')?;
+                let expression = self.parse_expression()?;
+                self.parse_specific_symbol(')')?;
+                TermKind::Parenthesized(Box::new(expression))
+            }
+            // unaryOp term
+            Token::Symbol('-') => {
+                self.parse_specific_symbol('-')?;
+                let minus_end = self.previous_span.end();
+                match self.token_iterator.peek() {
+                    // -32768 is folded into one constant, because 32768 alone is out of range.
+                    // Only a minus written directly before the digits is part of the constant.
+                    Some(SpannedToken { token: Token::IntConstant(32768), span, .. }) if span.offset == minus_end => {
+                        let span = *span;
+                        self.advance();
+                        self.errors.retain(|error| error.span() != span);
+                        TermKind::IntConstant(-32768)
+                    }
+                    _ => TermKind::Unary(UnaryOperation::Negate, Box::new(self.parse_term()?)),
+                }
+            }
+            Token::Symbol('~') => {
+                self.parse_specific_symbol('~')?;
+                TermKind::Unary(UnaryOperation::Not, Box::new(self.parse_term()?))
+            }
+            // varname | varname[expression] | subroutineCall
+            Token::Identifier(_) => {
+                match self.token_iterator.peek_nth(1).map(|t| &t.token) {
+                    // varName[expression]
+                    Some(Token::Symbol('[')) => {
+                        let name = self.parse_name()?;
+                        self.parse_specific_symbol('[')?;
+                        let index = self.parse_expression()?;
+                        self.parse_specific_symbol(']')?;
+                        TermKind::ArrayAccess(name, Box::new(index))
+                    }
+                    // subroutinecall, which is var_name.function_name() or function_name()
+                    Some(Token::Symbol('.')) | Some(Token::Symbol('(')) => {
+                        TermKind::SubroutineCall(self.parse_subroutine_call()?)
+                    }
+                    // simply the var_name
+                    _ => TermKind::Variable(self.parse_name()?),
+                }
+            }
+            _ => {
+                return Err(Self::unexpected(term_start, &TERM_START))
+            }
+        };
+
+        Ok(Term {
+            kind,
+            span: term_start.span.to(self.previous_span),
+        })
+    }
+
+    fn parse_expression_list(&mut self) -> Result<Vec<Expression>, CompileError> {
+        // (
+        self.parse_specific_symbol('(')?;
+        let mut expressions = vec![];
+
+        if self.peek_token(&["expression", ")"])?.token != Token::Symbol(')') {
+            expressions.push(self.parse_expression()?);
+        }
+
+        while self.peek_token(&[",", ")"])?.token == Token::Symbol(',') {
+            self.parse_specific_symbol(',')?;
+            expressions.push(self.parse_expression()?);
+        }
+
+        // )
+        self.parse_specific_symbol(')')?;
+        Ok(expressions)
+    }
+
+    fn parse_subroutine_call(&mut self) -> Result<SubroutineCall, CompileError> {
+        let first_name = self.parse_name()?;
+        // if a dot follows, we have the case className|varName . subRoutineName, otherwise it is just subroutineName
+        let (receiver, name) = if self.peek_token(&[".", "("])?.token == Token::Symbol('.') {
+            self.parse_specific_symbol('.')?;
+            (Some(first_name), self.parse_name()?)
+        } else {
+            (None, first_name)
+        };
+        let arguments = self.parse_expression_list()?;
+        let span = receiver.as_ref().unwrap_or(&name).span.to(self.previous_span);
+
+        Ok(SubroutineCall {
+            receiver,
+            name,
+            arguments,
+            span,
+        })
+    }
+
+    fn parse_operation(&mut self) -> Result<Option<JackOperation>, CompileError> {
+        let operation = match &self.peek_token(&["operator", ";", ")", "]", ","])?.token {
+            Token::Symbol('+') => JackOperation::Add,
+            Token::Symbol('-') => JackOperation::Subtract,
+            Token::Symbol('*') => JackOperation::Multiply,
+            Token::Symbol('/') => JackOperation::Divide,
+            Token::Symbol('&') => JackOperation::And,
+            Token::Symbol('|') => JackOperation::Or,
+            Token::Symbol('<') => JackOperation::Less,
+            Token::Symbol('>') => JackOperation::Larger,
+            Token::Symbol('=') => JackOperation::Equal,
+            _ => return Ok(None),
+        };
+        self.advance();
+        Ok(Some(operation))
+    }
+
+    fn parse_type(&mut self) -> Result<(JackVariableType, Span), CompileError> {
+        let type_token = self.next_token(&TYPES)?;
+        let var_type = match &type_token.token {
+            Token::Identifier(identifier) => JackVariableType::Jclass(identifier.to_string()),
+            Token::Keyword(Keyword::Int) => JackVariableType::Jint,
+            Token::Keyword(Keyword::Char) => JackVariableType::Jchar,
+            Token::Keyword(Keyword::Boolean) => JackVariableType::Jboolean,
+            _ => {
+                return Err(Self::unexpected(type_token, &TYPES))
+            }
+        };
+        Ok((var_type, type_token.span))
+    }
+
+    fn parse_name(&mut self) -> Result<Identifier, CompileError> {
+        let name_token = self.next_token(&["identifier"])?;
+        if let Token::Identifier(id) = &name_token.token {
+            Ok(Identifier {
+                name: id.to_string(),
+                span: name_token.span,
+            })
+        } else {
+            Err(Self::unexpected(name_token, &["identifier"]))
+        }
+    }
+
+    fn parse_specific_symbol(&mut self, c: char) -> Result<(), CompileError> {
+        // a wrong token is not consumed, it may be the start of the next statement
+        let symbol_token = self.peek_token(&[&c.to_string()])?;
+        if symbol_token.token == Token::Symbol(c) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(Self::unexpected(symbol_token, &[&c.to_string()]))
+        }
+    }
+}
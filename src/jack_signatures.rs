@@ -0,0 +1,114 @@
+//! jack_signatures
+//! Signatures of the subroutines of a whole program: every class of the program and the API of
+//! the Jack OS. The compiler resolves calls against them to find calls of classes and
+//! subroutines that do not exist or that pass the wrong number of arguments.
+
+use bundled_os;
+use jack_ast::{Class, FunctionKind, JackVariableType, SubroutineDec};
+use jack_os::JackOs;
+use jack_parser::JackParser;
+use jack_tokenizer;
+
+use std::collections::{HashMap, HashSet};
+
+/// What a caller needs to know about a subroutine
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubroutineSignature {
+    /// Constructor, function or method
+    pub kind: FunctionKind,
+    /// Declared return type, `None` for `void`
+    pub return_type: Option<JackVariableType>,
+    /// Types of the parameters, without the object a method is called on
+    pub parameter_types: Vec<JackVariableType>,
+}
+
+impl<'a> From<&'a SubroutineDec> for SubroutineSignature {
+    fn from(subroutine_dec: &'a SubroutineDec) -> Self {
+        SubroutineSignature {
+            kind: subroutine_dec.kind,
+            return_type: subroutine_dec.return_type.clone(),
+            parameter_types: subroutine_dec
+                .parameters
+                .iter()
+                .map(|parameter| parameter.var_type.clone())
+                .collect(),
+        }
+    }
+}
+
+/// The subroutines of all classes of a program, by class name and subroutine name
+#[derive(Debug, Clone, Default)]
+pub struct ProgramSignatures {
+    classes: HashMap<String, HashMap<String, SubroutineSignature>>,
+    /// Classes with syntax errors, whose subroutines may be missing
+    incomplete_classes: HashSet<String>,
+}
+
+impl ProgramSignatures {
+    /// Constructor
+    pub fn new() -> Self {
+        ProgramSignatures {
+            classes: HashMap::new(),
+            incomplete_classes: HashSet::new(),
+        }
+    }
+
+    /// Adds the subroutines of the OS API, i.e. those the VM emulator and the builtin OS of
+    /// the VM interpreter provide. Subroutines the program defines itself are kept.
+    pub fn with_os_api(mut self) -> Self {
+        for (class_name, source) in bundled_os::CLASSES.iter() {
+            let tokens = jack_tokenizer::tokenize(source.to_string(), 0);
+            let class = JackParser::new(&tokens, 0)
+                .parse_class()
+                .expect("the bundled OS classes are valid Jack code");
+            let subroutines = self.classes.entry(class_name.to_string()).or_default();
+            for subroutine_dec in &class.subroutine_decs {
+                let name = &subroutine_dec.name.name;
+                if JackOs::provides(&format!("{}.{}", class_name, name)) {
+                    subroutines
+                        .entry(name.clone())
+                        .or_insert_with(|| SubroutineSignature::from(subroutine_dec));
+                }
+            }
+        }
+        self
+    }
+
+    /// Adds the subroutines of a class of the program. They replace OS subroutines of the same
+    /// name.
+    pub fn add_class(&mut self, class: &Class) {
+        let subroutines = self.classes.entry(class.name.name.clone()).or_default();
+        for subroutine_dec in &class.subroutine_decs {
+            subroutines.insert(
+                subroutine_dec.name.name.clone(),
+                SubroutineSignature::from(subroutine_dec),
+            );
+        }
+    }
+
+    /// Records that a class of the program has syntax errors. The class exists, but subroutines
+    /// that could not be parsed are missing, so calls of unknown subroutines of the class must
+    /// not be reported. `class` holds the parts that could be parsed, if any.
+    pub fn add_incomplete_class(&mut self, class_name: &str, class: Option<&Class>) {
+        self.classes.entry(class_name.to_string()).or_default();
+        if let Some(class) = class {
+            self.add_class(class);
+        }
+        self.incomplete_classes.insert(class_name.to_string());
+    }
+
+    /// Whether the program or the OS has a class with this name
+    pub fn has_class(&self, class_name: &str) -> bool {
+        self.classes.contains_key(class_name)
+    }
+
+    /// Whether the class has syntax errors, see `add_incomplete_class`
+    pub fn is_incomplete(&self, class_name: &str) -> bool {
+        self.incomplete_classes.contains(class_name)
+    }
+
+    /// The signature of the subroutine `class_name.name`, if it exists
+    pub fn subroutine(&self, class_name: &str, name: &str) -> Option<&SubroutineSignature> {
+        self.classes.get(class_name)?.get(name)
+    }
+}
@@ -1,106 +1,357 @@
 //! jack_tokenizer
+//! Splits the content of a *.jack file into tokens.
 
-extern crate regex;
-use self::regex::Regex;
+use compile_error::CompileError;
+use std::fmt;
 
-#[derive(Debug, PartialEq)]
+/// The largest integer constant, the Hack platform uses 16-bit two's complement numbers
+pub const MAX_INT_CONSTANT: i32 = 32767;
+
+/// The lexical elements of the Jack language
+#[derive(Debug, PartialEq, Clone)]
 pub enum Token {
+    /// One of the reserved words
     Keyword(Keyword),
+    /// One of `{}()[].,;+-*/&|<>=~`
     Symbol(char),
+    /// Name of a class, subroutine or variable
     Identifier(String),
+    /// Decimal integer literal. Literals above `MAX_INT_CONSTANT` carry an error, see
+    /// `SpannedToken::error`.
     IntConstant(i32),
+    /// String literal without the enclosing quotes
     StringConstant(String),
 }
 
-/// Consumes a string that is the content of a *.jack program and converts it to tokenized xml file
-pub fn tokenize(mut jack_code: String) -> Vec<Token> {
-    // remove comments that are done with //
-    let mut re = Regex::new(r"//.*\n").unwrap();
-    jack_code = re.replace_all(&jack_code, "").into_owned();
-
-    //remove comments that are done with /* ... */
-    // (?s) is a flag that changes behavior of "." in regex. "." will match new lines as well
-    re = Regex::new(r"/\*(?s).*?\*/").unwrap(); // .*? is .* but non-greedy
-    jack_code = re.replace_all(&jack_code, "").into_owned();
-
-    let mut tokens = vec![];
-    let mut current_string = "".to_string();
-    let list_containing_whitespace_and_newline_and_all_symbols = vec![
-        ' ', '\n', '\r', '\t', '{', '}', '(', ')', '[', ']', '.', ',', ';', '+', '-', '*', '/',
-        '&', '|', '<', '>', '=', '~',
-    ];
-
-    let mut inside_string_literal = false;
-
-    let mut chars = jack_code.chars();
-    while let Some(c) = chars.next() {
-        if inside_string_literal {
-            if c == '\"' {
-                // string literal ends
-                tokens.push(Token::StringConstant(current_string.clone()));
-                current_string = "".to_string();
-                inside_string_literal = false;
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Keyword(kw) => write!(f, "{}", kw),
+            Token::Symbol(c) => write!(f, "{}", c),
+            Token::Identifier(id) => write!(f, "{}", id),
+            Token::IntConstant(i) => write!(f, "{}", i),
+            Token::StringConstant(s) => write!(f, "\"{}\"", s),
+        }
+    }
+}
+
+/// Location of a token in a source file.
+/// `offset` and `len` are measured in bytes, `line` and `column` are 1-based and count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// Identifies the source file, see `diagnostics::SourceMap`
+    pub file_id: usize,
+    /// Byte offset of the first character
+    pub offset: usize,
+    /// Line of the first character
+    pub line: usize,
+    /// Column of the first character
+    pub column: usize,
+    /// Length in bytes
+    pub len: usize,
+}
+
+impl Span {
+    /// Byte offset directly after the spanned text
+    pub fn end(&self) -> usize {
+        self.offset + self.len
+    }
+
+    /// A span from the start of `self` to the end of `last`
+    pub fn to(&self, last: Span) -> Span {
+        Span {
+            len: last.end().max(self.end()) - self.offset,
+            ..*self
+        }
+    }
+}
+
+/// A token together with the place in the source file it was read from
+#[derive(Debug, PartialEq)]
+pub struct SpannedToken {
+    /// The token itself
+    pub token: Token,
+    /// Where the token was read from
+    pub span: Span,
+    /// A lexical error like an integer constant that is out of range. The token can still be
+    /// parsed, the parser reports the error when it reads the token.
+    pub error: Option<CompileError>,
+}
+
+/// Maps byte offsets of a source file to line and column numbers
+struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { source, line_starts }
+    }
+
+    fn span(&self, file_id: usize, offset: usize, len: usize) -> Span {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let column = self.source[self.line_starts[line]..offset].chars().count() + 1;
+        Span {
+            file_id,
+            offset,
+            line: line + 1,
+            column,
+            len,
+        }
+    }
+}
+
+/// The characters that form a token of their own
+const SYMBOLS: &str = "{}()[].,;+-*/&|<>=~";
+
+/// The characters that separate tokens
+const WHITESPACE: [char; 4] = [' ', '\t', '\n', '\r'];
+
+/// Consumes a string that is the content of a *.jack program and converts it to a list of tokens.
+/// Every token carries its position in the file identified by `file_id`. Lexical errors like
+/// unterminated string literals are attached to the token they occur in.
+pub fn tokenize(jack_code: String, file_id: usize) -> Vec<SpannedToken> {
+    Lexer::new(&jack_code, file_id).tokenize()
+}
+
+/// Reads the tokens of a source file in a single pass over its characters, skipping whitespace
+/// and comments
+struct Lexer<'a> {
+    source: &'a str,
+    file_id: usize,
+    line_index: LineIndex<'a>,
+    /// Byte offset of the next character to read
+    position: usize,
+    tokens: Vec<SpannedToken>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str, file_id: usize) -> Self {
+        Lexer {
+            source,
+            file_id,
+            line_index: LineIndex::new(source),
+            position: 0,
+            tokens: vec![],
+        }
+    }
+
+    /// The source text that has not been read yet
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    /// The span from `start` to the current position
+    fn span_from(&self, start: usize) -> Span {
+        self.line_index
+            .span(self.file_id, start, self.position - start)
+    }
+
+    fn push(&mut self, token: Token, span: Span, error: Option<CompileError>) {
+        self.tokens.push(SpannedToken { token, span, error });
+    }
+
+    fn tokenize(mut self) -> Vec<SpannedToken> {
+        while let Some(c) = self.rest().chars().next() {
+            if WHITESPACE.contains(&c) {
+                self.position += 1;
+            } else if self.rest().starts_with("//") {
+                self.skip_line_comment();
+            } else if self.rest().starts_with("/*") {
+                self.skip_block_comment();
+            } else if c == '"' {
+                self.read_string_constant();
+            } else if SYMBOLS.contains(c) {
+                let start = self.position;
+                self.position += 1;
+                let span = self.span_from(start);
+                self.push(Token::Symbol(c), span, None);
             } else {
-                current_string.push(c);
+                self.read_word();
             }
-        } else if c == '\"' {
-            //string literal begins
-            inside_string_literal = true;
-        } else if list_containing_whitespace_and_newline_and_all_symbols.contains(&c) {
-            // tokenize string that came before this symbol
-            if !current_string.is_empty() {
-                tokens.push(tokenize_single_string(&current_string));
-                current_string = "".to_string();
+        }
+        self.tokens
+    }
+
+    /// Skips a `//` comment up to the end of the line, or of the file if it is the last line
+    fn skip_line_comment(&mut self) {
+        self.position = match self.rest().find('\n') {
+            Some(end) => self.position + end,
+            None => self.source.len(),
+        };
+    }
+
+    /// Skips a `/* */` comment, which includes `/** */` documentation comments. An unterminated
+    /// comment takes the rest of the file, its `/` is kept as a token that carries the error.
+    fn skip_block_comment(&mut self) {
+        let start = self.position;
+        match self.rest()[2..].find("*/") {
+            Some(end) => self.position += end + 4,
+            None => {
+                self.position += 2;
+                let span = self.span_from(start);
+                let error = CompileError::UnterminatedComment { span };
+                self.push(Token::Symbol('/'), span, Some(error));
+                self.position = self.source.len();
             }
-            // tokenize current symbols
-            if c != ' ' && c != '\n' && c != '\r' && c != '\t' {
-                tokens.push(Token::Symbol(c))
+        }
+    }
+
+    /// Reads a string literal. Comment markers inside it are part of the string. A string
+    /// literal cannot span lines, so an unterminated one ends with its line and lexing goes on
+    /// with the next line.
+    fn read_string_constant(&mut self) {
+        let start = self.position;
+        let content = &self.rest()[1..];
+        match content.find(['"', '\n']) {
+            Some(end) if content[end..].starts_with('"') => {
+                self.position += end + 2;
+                let span = self.span_from(start);
+                self.push(
+                    Token::StringConstant(content[..end].to_string()),
+                    span,
+                    None,
+                );
+            }
+            end => {
+                let content = content[..end.unwrap_or(content.len())].trim_end_matches('\r');
+                self.position += content.len() + 1;
+                let span = self.span_from(start);
+                let error = CompileError::UnterminatedString { span };
+                self.push(
+                    Token::StringConstant(content.to_string()),
+                    span,
+                    Some(error),
+                );
             }
-        } else {
-            current_string.push(c);
         }
     }
 
-    tokens
+    /// Reads a keyword, identifier or integer constant, which ends at whitespace, a symbol or
+    /// a `"`. Characters that cannot be part of it are reported by `word_error`.
+    fn read_word(&mut self) {
+        let start = self.position;
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| WHITESPACE.contains(&c) || SYMBOLS.contains(c) || c == '"')
+            .unwrap_or(rest.len());
+        self.position += len;
+        let word = &rest[..len];
+        let token = tokenize_single_string(word);
+        let span = self.span_from(start);
+        let error = word_error(&token, word, span);
+        self.push(token, span, error);
+    }
 }
 
-pub fn tokenize_single_string(s: &String) -> Token {
+/// Converts a word that is neither a symbol nor a string literal into a token.
+/// A word of decimal digits is an integer constant, even if it is too large for the Hack
+/// platform; such constants become `IntConstant(i32::MAX)` if they do not fit into an `i32`.
+pub fn tokenize_single_string(s: &str) -> Token {
     if let Some(kw) = Keyword::from_string(s) {
         Token::Keyword(kw)
-    } else if let Ok(num) = s.parse::<i32>() {
-        Token::IntConstant(num)
+    } else if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        Token::IntConstant(s.parse::<i32>().unwrap_or(i32::MAX))
     } else {
-        // TODO: check if s is a sequence of letters digits and underscores not starting with a digit
+        // validated by `word_error`
         Token::Identifier(s.to_string())
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// The lexical error of a word tokenized by `tokenize_single_string`, if it has one.
+/// Identifiers consist of ASCII letters, digits and `_` and do not start with a digit. Only the
+/// first illegal character of a word is reported.
+fn word_error(token: &Token, word: &str, span: Span) -> Option<CompileError> {
+    match token {
+        Token::IntConstant(value) if *value > MAX_INT_CONSTANT => {
+            Some(CompileError::IntegerOutOfRange {
+                value: word.to_string(),
+                span,
+            })
+        }
+        Token::Identifier(_) => {
+            let illegal = word
+                .char_indices()
+                .find(|&(_, c)| !c.is_ascii_alphanumeric() && c != '_');
+            if let Some((offset, character)) = illegal {
+                // words do not contain line breaks, so the character is on the line of the word
+                Some(CompileError::IllegalCharacter {
+                    character,
+                    span: Span {
+                        offset: span.offset + offset,
+                        column: span.column + word[..offset].chars().count(),
+                        len: character.len_utf8(),
+                        ..span
+                    },
+                })
+            } else if word.starts_with(|c: char| c.is_ascii_digit()) {
+                Some(CompileError::InvalidIdentifier {
+                    name: word.to_string(),
+                    span,
+                })
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The reserved words of the Jack language
+#[derive(Debug, PartialEq, Clone)]
 pub enum Keyword {
+    /// `class`
     Class,
+    /// `constructor`
     Constructor,
+    /// `function`
     Function,
+    /// `method`
     Method,
+    /// `field`
     Field,
+    /// `static`
     Static,
+    /// `var`
     Var,
+    /// `int`
     Int,
+    /// `char`
     Char,
+    /// `boolean`
     Boolean,
+    /// `void`
     Void,
+    /// `true`
     True,
+    /// `false`
     False,
+    /// `null`
     Null,
+    /// `this`
     This,
+    /// `let`
     Let,
+    /// `do`
     Do,
+    /// `if`
     If,
+    /// `else`
     Else,
+    /// `while`
     While,
+    /// `return`
     Return,
 }
 
 impl Keyword {
+    /// Parses a keyword, returns `None` if `s` is not a reserved word
     pub fn from_string(s: &str) -> Option<Self> {
         match s {
             "class" => Some(Keyword::Class),
@@ -128,29 +379,36 @@ impl Keyword {
         }
     }
 
-    pub fn to_string(&self) -> String {
+    /// The keyword as it is written in Jack code
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Keyword::Class => "class".to_string(),
-            Keyword::Constructor => "constructor".to_string(),
-            Keyword::Function => "function".to_string(),
-            Keyword::Method => "method".to_string(),
-            Keyword::Field => "field".to_string(),
-            Keyword::Static => "static".to_string(),
-            Keyword::Var => "var".to_string(),
-            Keyword::Int => "int".to_string(),
-            Keyword::Char => "char".to_string(),
-            Keyword::Boolean => "boolean".to_string(),
-            Keyword::Void => "void".to_string(),
-            Keyword::True => "true".to_string(),
-            Keyword::False => "false".to_string(),
-            Keyword::Null => "null".to_string(),
-            Keyword::This => "this".to_string(),
-            Keyword::Let => "let".to_string(),
-            Keyword::Do => "do".to_string(),
-            Keyword::If => "if".to_string(),
-            Keyword::Else => "else".to_string(),
-            Keyword::While => "while".to_string(),
-            Keyword::Return => "return".to_string(),
+            Keyword::Class => "class",
+            Keyword::Constructor => "constructor",
+            Keyword::Function => "function",
+            Keyword::Method => "method",
+            Keyword::Field => "field",
+            Keyword::Static => "static",
+            Keyword::Var => "var",
+            Keyword::Int => "int",
+            Keyword::Char => "char",
+            Keyword::Boolean => "boolean",
+            Keyword::Void => "void",
+            Keyword::True => "true",
+            Keyword::False => "false",
+            Keyword::Null => "null",
+            Keyword::This => "this",
+            Keyword::Let => "let",
+            Keyword::Do => "do",
+            Keyword::If => "if",
+            Keyword::Else => "else",
+            Keyword::While => "while",
+            Keyword::Return => "return",
         }
     }
 }
+
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
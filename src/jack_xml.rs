@@ -0,0 +1,318 @@
+//! jack_xml
+//! Writes the token stream and the syntax tree of a class in the XML formats of nand2tetris
+//! project 10, i.e. the `*T.xml` and `*.xml` files that the course's TextComparer checks.
+
+use jack_ast::*;
+use jack_tokenizer::{SpannedToken, Token};
+
+/// Escapes the characters that have a special meaning in XML
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Tag name and text of a token, e.g. `("symbol", "&lt;")`. String constants lose their quotes.
+fn tag_and_text(token: &Token) -> (&'static str, String) {
+    match token {
+        Token::Keyword(keyword) => ("keyword", keyword.as_str().to_string()),
+        Token::Symbol(c) => ("symbol", escape(&c.to_string())),
+        Token::Identifier(name) => ("identifier", escape(name)),
+        Token::IntConstant(value) => ("integerConstant", value.to_string()),
+        Token::StringConstant(s) => ("stringConstant", escape(s)),
+    }
+}
+
+/// Renders the tokens of a file as the content of a `*T.xml` file
+pub fn tokens_to_xml(tokens: &[SpannedToken]) -> String {
+    let mut xml = String::from("<tokens>\n");
+    for spanned_token in tokens {
+        let (tag, text) = tag_and_text(&spanned_token.token);
+        xml += &format!("<{}> {} </{}>\n", tag, text, tag);
+    }
+    xml += "</tokens>\n";
+    xml
+}
+
+/// Renders a class as the content of a `*.xml` parse tree file
+pub fn class_to_xml(class: &Class) -> String {
+    let mut writer = XmlWriter {
+        xml: String::new(),
+        depth: 0,
+    };
+    writer.class(class);
+    writer.xml
+}
+
+struct XmlWriter {
+    xml: String,
+    depth: usize,
+}
+
+impl XmlWriter {
+    fn open(&mut self, tag: &str) {
+        self.xml += &format!("{}<{}>\n", "  ".repeat(self.depth), tag);
+        self.depth += 1;
+    }
+
+    fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.xml += &format!("{}</{}>\n", "  ".repeat(self.depth), tag);
+    }
+
+    fn token(&mut self, token: &Token) {
+        let (tag, text) = tag_and_text(token);
+        self.xml += &format!("{}<{}> {} </{}>\n", "  ".repeat(self.depth), tag, text, tag);
+    }
+
+    fn keyword(&mut self, keyword: &str) {
+        self.xml += &format!("{}<keyword> {} </keyword>\n", "  ".repeat(self.depth), keyword);
+    }
+
+    fn symbol(&mut self, symbol: char) {
+        self.token(&Token::Symbol(symbol));
+    }
+
+    fn identifier(&mut self, identifier: &Identifier) {
+        self.token(&Token::Identifier(identifier.name.clone()));
+    }
+
+    fn var_type(&mut self, var_type: &JackVariableType) {
+        match var_type {
+            JackVariableType::Jint => self.keyword("int"),
+            JackVariableType::Jchar => self.keyword("char"),
+            JackVariableType::Jboolean => self.keyword("boolean"),
+            JackVariableType::Jclass(name) => self.token(&Token::Identifier(name.clone())),
+        }
+    }
+
+    /// `name (, name)*`
+    fn names(&mut self, names: &[Identifier]) {
+        for (i, name) in names.iter().enumerate() {
+            if i > 0 {
+                self.symbol(',');
+            }
+            self.identifier(name);
+        }
+    }
+
+    fn class(&mut self, class: &Class) {
+        self.open("class");
+        self.keyword("class");
+        self.identifier(&class.name);
+        self.symbol('{');
+        for class_var_dec in &class.class_var_decs {
+            self.open("classVarDec");
+            self.keyword(match class_var_dec.kind {
+                VariableKind::Jfield => "field",
+                _ => "static",
+            });
+            self.var_type(&class_var_dec.var_type);
+            self.names(&class_var_dec.names);
+            self.symbol(';');
+            self.close("classVarDec");
+        }
+        for subroutine_dec in &class.subroutine_decs {
+            self.subroutine_dec(subroutine_dec);
+        }
+        self.symbol('}');
+        self.close("class");
+    }
+
+    fn subroutine_dec(&mut self, subroutine_dec: &SubroutineDec) {
+        self.open("subroutineDec");
+        self.keyword(match subroutine_dec.kind {
+            FunctionKind::Jconstructor => "constructor",
+            FunctionKind::Jfunction => "function",
+            FunctionKind::Jmethod => "method",
+        });
+        match &subroutine_dec.return_type {
+            Some(return_type) => self.var_type(return_type),
+            None => self.keyword("void"),
+        }
+        self.identifier(&subroutine_dec.name);
+        self.symbol('(');
+        self.open("parameterList");
+        for (i, parameter) in subroutine_dec.parameters.iter().enumerate() {
+            if i > 0 {
+                self.symbol(',');
+            }
+            self.var_type(&parameter.var_type);
+            self.identifier(&parameter.name);
+        }
+        self.close("parameterList");
+        self.symbol(')');
+
+        self.open("subroutineBody");
+        self.symbol('{');
+        for var_dec in &subroutine_dec.var_decs {
+            self.open("varDec");
+            self.keyword("var");
+            self.var_type(&var_dec.var_type);
+            self.names(&var_dec.names);
+            self.symbol(';');
+            self.close("varDec");
+        }
+        self.statements(&subroutine_dec.statements);
+        self.symbol('}');
+        self.close("subroutineBody");
+        self.close("subroutineDec");
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        self.open("statements");
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.close("statements");
+    }
+
+    /// `{ statements }`
+    fn block(&mut self, statements: &[Statement]) {
+        self.symbol('{');
+        self.statements(statements);
+        self.symbol('}');
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Let {
+                target,
+                index,
+                value,
+            } => {
+                self.open("letStatement");
+                self.keyword("let");
+                self.identifier(target);
+                if let Some(index) = index {
+                    self.symbol('[');
+                    self.expression(index);
+                    self.symbol(']');
+                }
+                self.symbol('=');
+                self.expression(value);
+                self.symbol(';');
+                self.close("letStatement");
+            }
+            StatementKind::If {
+                condition,
+                then_statements,
+                else_statements,
+            } => {
+                self.open("ifStatement");
+                self.keyword("if");
+                self.symbol('(');
+                self.expression(condition);
+                self.symbol(')');
+                self.block(then_statements);
+                if let Some(else_statements) = else_statements {
+                    self.keyword("else");
+                    self.block(else_statements);
+                }
+                self.close("ifStatement");
+            }
+            StatementKind::While {
+                condition,
+                statements,
+            } => {
+                self.open("whileStatement");
+                self.keyword("while");
+                self.symbol('(');
+                self.expression(condition);
+                self.symbol(')');
+                self.block(statements);
+                self.close("whileStatement");
+            }
+            StatementKind::Do(subroutine_call) => {
+                self.open("doStatement");
+                self.keyword("do");
+                self.subroutine_call(subroutine_call);
+                self.symbol(';');
+                self.close("doStatement");
+            }
+            StatementKind::Return(expression) => {
+                self.open("returnStatement");
+                self.keyword("return");
+                if let Some(expression) = expression {
+                    self.expression(expression);
+                }
+                self.symbol(';');
+                self.close("returnStatement");
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        self.open("expression");
+        self.term(&expression.term);
+        for (operation, term) in &expression.operations {
+            self.symbol(operation.symbol());
+            self.term(term);
+        }
+        self.close("expression");
+    }
+
+    fn term(&mut self, term: &Term) {
+        self.open("term");
+        match &term.kind {
+            // the folded constant -32768
+            TermKind::IntConstant(value) if *value < 0 => {
+                self.symbol('-');
+                self.open("term");
+                self.token(&Token::IntConstant(-value));
+                self.close("term");
+            }
+            TermKind::IntConstant(value) => self.token(&Token::IntConstant(*value)),
+            TermKind::StringConstant(s) => self.token(&Token::StringConstant(s.clone())),
+            TermKind::True => self.keyword("true"),
+            TermKind::False => self.keyword("false"),
+            TermKind::Null => self.keyword("null"),
+            TermKind::This => self.keyword("this"),
+            TermKind::Variable(name) => self.identifier(name),
+            TermKind::ArrayAccess(name, index) => {
+                self.identifier(name);
+                self.symbol('[');
+                self.expression(index);
+                self.symbol(']');
+            }
+            TermKind::SubroutineCall(subroutine_call) => self.subroutine_call(subroutine_call),
+            TermKind::Parenthesized(expression) => {
+                self.symbol('(');
+                self.expression(expression);
+                self.symbol(')');
+            }
+            TermKind::Unary(operation, operand) => {
+                self.symbol(operation.symbol());
+                self.term(operand);
+            }
+        }
+        self.close("term");
+    }
+
+    /// The grammar has no `subroutineCall` element, the tokens of a call are written directly
+    /// into the enclosing `doStatement` or `term`
+    fn subroutine_call(&mut self, subroutine_call: &SubroutineCall) {
+        if let Some(receiver) = &subroutine_call.receiver {
+            self.identifier(receiver);
+            self.symbol('.');
+        }
+        self.identifier(&subroutine_call.name);
+        self.symbol('(');
+        self.open("expressionList");
+        for (i, argument) in subroutine_call.arguments.iter().enumerate() {
+            if i > 0 {
+                self.symbol(',');
+            }
+            self.expression(argument);
+        }
+        self.close("expressionList");
+        self.symbol(')');
+    }
+}
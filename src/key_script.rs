@@ -0,0 +1,142 @@
+//! key_script
+//! Parses scripts of timed key events that drive interactive programs in the VM interpreter.
+//! Every line holds a step count and a key: the key is put into the keyboard register once the
+//! interpreter has executed that many commands, and stays there until the next event.
+//! A key is a Hack key code (`0` releases all keys), a name from the Hack key code table
+//! (`newline`, `backspace`, `left`, `up`, `right`, `down`, `home`, `end`, `pageup`, `pagedown`,
+//! `insert`, `delete`, `esc`, `f1` to `f12`, `space`, `none`), or a character in single quotes.
+//! `//` starts a comment.
+//!
+//! ```text
+//! // move left for a while, then quit
+//! 1000   left
+//! 50000  none
+//! 60000  'q'
+//! 61000  0
+//! ```
+
+use jack_os::{BACKSPACE, NEW_LINE};
+use std::error::Error;
+use std::fmt;
+
+/// Setting the keyboard register to a key code after a number of steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// Number of commands executed before the key code is set
+    pub step: u64,
+    /// The key code, 0 if no key is pressed
+    pub key: i16,
+}
+
+/// A line of a key script that cannot be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyScriptError {
+    /// 1-based line number
+    pub line: usize,
+    /// What is wrong with the line
+    pub message: String,
+}
+
+impl fmt::Display for KeyScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for KeyScriptError {}
+
+/// The key code of a key name of the Hack key code table
+pub fn key_code(name: &str) -> Option<i16> {
+    let code = match name.to_ascii_lowercase().as_str() {
+        "none" => 0,
+        "space" => 32,
+        "newline" | "enter" => NEW_LINE,
+        "backspace" => BACKSPACE,
+        "left" => 130,
+        "up" => 131,
+        "right" => 132,
+        "down" => 133,
+        "home" => 134,
+        "end" => 135,
+        "pageup" => 136,
+        "pagedown" => 137,
+        "insert" => 138,
+        "delete" => 139,
+        "esc" => 140,
+        name => {
+            let function_key = name.strip_prefix('f')?.parse::<i16>().ok()?;
+            if !(1..=12).contains(&function_key) {
+                return None;
+            }
+            140 + function_key
+        }
+    };
+    Some(code)
+}
+
+fn parse_key(key: &str) -> Result<i16, String> {
+    if key.starts_with(|c: char| c.is_ascii_digit()) {
+        return match key.parse::<i16>() {
+            Ok(code) if code >= 0 => Ok(code),
+            _ => Err(format!(
+                "invalid key code `{}`, the range is 0 to 32767",
+                key
+            )),
+        };
+    }
+    if let Some(character) = key
+        .strip_prefix('\'')
+        .and_then(|key| key.strip_suffix('\''))
+    {
+        let mut chars = character.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) if (' '..='~').contains(&c) => Ok(c as i16),
+            _ => Err(format!(
+                "`{}` is not a single printable ASCII character",
+                key
+            )),
+        };
+    }
+    key_code(key).ok_or_else(|| format!("unknown key `{}`", key))
+}
+
+/// Parses a key script. The step counts must not decrease from one event to the next.
+pub fn parse(script: &str) -> Result<Vec<KeyEvent>, KeyScriptError> {
+    let mut events: Vec<KeyEvent> = vec![];
+    for (i, line) in script.lines().enumerate() {
+        let error = |message: String| KeyScriptError {
+            line: i + 1,
+            message,
+        };
+        let code = match line.find("//") {
+            Some(comment_start) => &line[..comment_start],
+            None => line,
+        };
+        let parts: Vec<&str> = code.split_whitespace().collect();
+        if parts.is_empty() {
+            continue;
+        }
+        // `' '` is split into two parts
+        let key = if parts.len() == 3 && parts[1] == "'" && parts[2] == "'" {
+            "' '".to_string()
+        } else if parts.len() == 2 {
+            parts[1].to_string()
+        } else {
+            return Err(error("expected a step count and a key".to_string()));
+        };
+        let step = parts[0]
+            .parse::<u64>()
+            .map_err(|_| error(format!("invalid step count `{}`", parts[0])))?;
+        if let Some(previous) = events.last() {
+            if step < previous.step {
+                return Err(error(format!(
+                    "step {} comes before the previous event at step {}",
+                    step, previous.step
+                )));
+            }
+        }
+        let key = parse_key(&key).map_err(error)?;
+        events.push(KeyEvent { step, key });
+    }
+    Ok(events)
+}
@@ -0,0 +1,28 @@
+#![deny(missing_docs)]
+//! JackCompiler
+//! Compiles Jack code into virtual machine code. Hack is a computer specified in
+//! "The elements of Computing Systems" (a.k.a. "nand2tetris") by Nisan and Schocken.
+
+pub mod bundled_os;
+pub mod compile_error;
+pub mod diagnostics;
+pub mod hack_assembler;
+pub mod jack_ast;
+pub mod jack_compiler;
+pub mod jack_lints;
+pub mod jack_os;
+pub mod jack_parser;
+pub mod jack_signatures;
+pub mod jack_tokenizer;
+pub mod jack_xml;
+pub mod key_script;
+pub mod screen_image;
+pub mod symbol_table;
+pub mod test_script;
+pub mod type_checker;
+pub mod vm_command;
+pub mod vm_interpreter;
+pub mod vm_translator;
+pub use compile_error::CompileError;
+pub use jack_compiler::{compile, compile_in_program, JackCompiler};
+pub use jack_parser::JackParser;
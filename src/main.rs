@@ -5,18 +5,69 @@
 
 extern crate clap;
 extern crate glob;
-use clap::{App, Arg};
+extern crate jack_compiler;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use glob::glob;
-use std::error::Error;
-use std::fs::metadata;
+use std::fs::{metadata, Metadata};
 use std::fs::File;
 use std::io::prelude::*;
+use std::io::{stderr, IsTerminal};
 use std::path::Path;
 use std::path::PathBuf;
+use std::process;
 
-mod jack_compiler;
-mod jack_tokenizer;
-pub use jack_compiler::JackCompiler;
+use jack_compiler::diagnostics::{self, Diagnostic, Severity, SourceMap};
+use jack_compiler::jack_ast::Class;
+use jack_compiler::jack_tokenizer::{self, SpannedToken};
+use jack_compiler::jack_signatures::ProgramSignatures;
+use jack_compiler::jack_lints;
+use jack_compiler::jack_os;
+use jack_compiler::jack_xml;
+use jack_compiler::type_checker;
+use jack_compiler::{CompileError, JackParser};
+use jack_compiler::vm_interpreter::{Status, VmError, VmInterpreter, WatchedCall};
+use jack_compiler::key_script;
+use jack_compiler::screen_image::{self, ImageFormat};
+use jack_compiler::{bundled_os, hack_assembler, test_script, vm_command, vm_translator};
+
+/// Number of VM commands after which `run` stops a program that did not halt
+const DEFAULT_MAX_STEPS: u64 = 10_000_000;
+
+/// Prints a diagnostic to stderr, colored if stderr is a terminal
+fn report(diagnostic: &Diagnostic, source_map: &SourceMap) {
+    eprint!(
+        "{}",
+        diagnostics::render(diagnostic, source_map, stderr().is_terminal())
+    );
+}
+
+/// Reports an error and terminates the program
+fn fail(diagnostic: Diagnostic, source_map: &SourceMap) -> ! {
+    report(&diagnostic, source_map);
+    process::exit(1);
+}
+
+/// Writes `content` to the file `output_file_name`, terminating the program if that fails
+fn write_output<C: AsRef<[u8]>>(output_file_name: &str, content: C, source_map: &SourceMap) {
+    let path = Path::new(output_file_name);
+    let display = path.display();
+
+    let mut file = match File::create(path) {
+        Err(why) => fail(
+            Diagnostic::error(format!("couldn't create {}: {}", display, why)),
+            source_map,
+        ),
+        Ok(file) => file,
+    };
+
+    match file.write_all(content.as_ref()) {
+        Err(why) => fail(
+            Diagnostic::error(format!("couldn't write to {}: {}", display, why)),
+            source_map,
+        ),
+        Ok(_) => println!("Successfully wrote {}", display),
+    }
+}
 
 fn main() {
     let matches = App::new("JackCompiler")
@@ -27,61 +78,735 @@ fn main() {
                                .help("Path to the file containing Jack source code. File extension is jack. ")
                                .required(true)
                                .index(1))
+                          .arg(Arg::with_name("max_errors")
+                               .long("max-errors")
+                               .value_name("N")
+                               .help("Stop reporting errors in a file after N errors. Default is 20.")
+                               .takes_value(true))
+                          .arg(Arg::with_name("emit")
+                               .long("emit")
+                               .value_name("FORMATS")
+                               .help("Comma separated list of outputs to write: vm (VM code), xml (tokens in *T.xml and parse tree in *.xml as in project 10), asm (Hack assembly, one file for a whole directory). Default is vm.")
+                               .takes_value(true)
+                               .use_delimiter(true)
+                               .possible_values(&["vm", "xml", "asm"]))
+                          .arg(Arg::with_name("type_check")
+                               .long("type-check")
+                               .value_name("LEVEL")
+                               .help("Checks the types of expressions and statements with Jack's lenient rules and reports problems as warnings (warn) or as errors that stop the compilation (deny). Default is no checking.")
+                               .takes_value(true)
+                               .possible_values(&["warn", "deny"]))
+                          .arg(Arg::with_name("link_os")
+                               .long("link-os")
+                               .help("Compiles the bundled Jack OS classes that the program does not define itself along with the program, writing their .vm files next to the input"))
+                          .arg(Arg::with_name("implicit_return_this")
+                               .long("implicit-return-this")
+                               .help("Lets constructors that can reach their end without a return statement return this there instead of rejecting them"))
+                          .setting(AppSettings::SubcommandsNegateReqs)
+                          .subcommand(SubCommand::with_name("assemble")
+                               .about("Translates Hack assembly into a .hack file with the same base name")
+                               .arg(Arg::with_name("Asm_input")
+                                    .help("Path to the file containing Hack assembly. File extension is asm.")
+                                    .required(true)
+                                    .index(1)))
+                          .subcommand(SubCommand::with_name("test")
+                               .about("Runs a .tst test script of the VM emulator (load, output-file, compare-to, output-list, set, repeat, vmstep and output) against the VM interpreter")
+                               .arg(Arg::with_name("Tst_input")
+                                    .help("Path to the test script. File names in the script are relative to its directory.")
+                                    .required(true)
+                                    .index(1)))
+                          .subcommand(SubCommand::with_name("run")
+                               .about("Compiles a Jack program and runs it in the VM interpreter, providing the OS functions the program does not define itself. Text printed with Output is written to stdout.")
+                               .arg(Arg::with_name("Run_input")
+                                    .help("Path to a .jack file or a directory containing .jack files")
+                                    .required(true)
+                                    .index(1))
+                               .arg(Arg::with_name("max_steps")
+                                    .long("max-steps")
+                                    .value_name("N")
+                                    .help("Stop the program after N VM commands. Default is 10000000.")
+                                    .takes_value(true))
+                               .arg(Arg::with_name("link_os")
+                                    .long("link-os")
+                                    .help("Runs the program with the bundled Jack OS classes instead of the builtin OS"))
+                               .arg(Arg::with_name("implicit_return_this")
+                                    .long("implicit-return-this")
+                                    .help("Lets constructors that can reach their end without a return statement return this there instead of rejecting them"))
+                               .arg(Arg::with_name("keys")
+                                    .long("keys")
+                                    .value_name("FILE")
+                                    .help("Presses keys as listed in FILE. Every line holds a step count and a key code, a key name like left or newline, or a character in single quotes. The key is held from that step until the next line's key, 0 or none releases it.")
+                                    .takes_value(true))
+                               .arg(Arg::with_name("screen")
+                                    .long("screen")
+                                    .value_name("FILE")
+                                    .help("Writes the screen to FILE when the program ends or is stopped. The extension selects the format: pbm or png.")
+                                    .takes_value(true))
+                               .arg(Arg::with_name("screen_at")
+                                    .long("screen-at")
+                                    .value_name("STEPS")
+                                    .help("Comma separated list of step counts at which the screen is written as well, to FILE with the step count appended to the base name")
+                                    .takes_value(true)
+                                    .use_delimiter(true)
+                                    .requires("screen")))
                           .get_matches();
 
-    let input_path_string: String = matches.value_of("Jack_input").unwrap().to_string();
+    match matches.subcommand() {
+        ("assemble", Some(assemble_matches)) => assemble(assemble_matches),
+        ("test", Some(test_matches)) => test(test_matches),
+        ("run", Some(run_matches)) => run(run_matches),
+        _ => compile_jack(&matches),
+    }
+}
+
+/// Assembles a .asm file into a .hack file
+fn assemble(matches: &ArgMatches) {
+    let input_path_string = matches.value_of("Asm_input").unwrap();
+    let source_map = SourceMap::new();
+
+    let mut asm_code = String::new();
+    let read_result =
+        File::open(input_path_string).and_then(|mut file| file.read_to_string(&mut asm_code));
+    if let Err(why) = read_result {
+        fail(
+            Diagnostic::error(format!("couldn't read {}: {}", input_path_string, why)),
+            &source_map,
+        );
+    }
 
+    match hack_assembler::assemble(&asm_code) {
+        Ok(instructions) => write_output(
+            Path::new(input_path_string)
+                .with_extension("hack")
+                .to_str()
+                .unwrap(),
+            hack_assembler::to_hack_text(&instructions),
+            &source_map,
+        ),
+        Err(why) => fail(
+            Diagnostic::error(format!("couldn't assemble {}: {}", input_path_string, why)),
+            &source_map,
+        ),
+    }
+}
+
+/// Runs a .tst test script, writes its output file and compares the output
+fn test(matches: &ArgMatches) {
+    let input_path_string = matches.value_of("Tst_input").unwrap();
+    let source_map = SourceMap::new();
+    let input_path = Path::new(input_path_string);
+    let script = read_source(input_path, &source_map);
+    let directory = input_path.parent().unwrap_or_else(|| Path::new(""));
+
+    let test_run = match test_script::run(&script, directory) {
+        Ok(test_run) => test_run,
+        Err(why) => fail(
+            Diagnostic::error(format!("{}: {}", input_path_string, why)),
+            &source_map,
+        ),
+    };
+    if let Some(output_file) = &test_run.output_file {
+        write_output(output_file.to_str().unwrap(), &test_run.output, &source_map);
+    }
+    match test_run.comparison_failure {
+        Some(failure) => fail(Diagnostic::error(failure.to_string()), &source_map),
+        None if test_run.compared => println!("End of script - Comparison ended successfully"),
+        None => println!("End of script"),
+    }
+}
+
+/// Reads the metadata of the input path, terminating the program if it does not exist
+fn input_metadata(input_path_string: &str, source_map: &SourceMap) -> Metadata {
+    match metadata(input_path_string) {
+        Ok(input_metadata) => input_metadata,
+        Err(why) => fail(
+            Diagnostic::error(format!("couldn't open {}: {}", input_path_string, why)),
+            source_map,
+        ),
+    }
+}
+
+/// The .jack files to compile: the given file, or all .jack files of the given directory
+fn collect_input_files(input_path_string: &str, is_dir: bool, source_map: &SourceMap) -> Vec<PathBuf> {
     let mut input_files = vec![];
     // check of user gave directory or single file
-    if metadata(&input_path_string).unwrap().is_dir() {
+    if is_dir {
         for entry in glob(&format!("{}/*.jack", input_path_string)).unwrap() {
             match entry {
                 Ok(path) => input_files.push(path),
 
                 // if the path matched but was unreadable,
                 // thereby preventing its contents from matching
-                Err(e) => println!("{:?}", e),
+                Err(e) => report(&Diagnostic::warning(e.to_string()), source_map),
             }
         }
         // check that Sys.vm is part of input_files and also that it is the first element in the list
         input_files.retain(|x| !x.to_str().unwrap().contains("Main.jack")); //TODO: unwrap unsafe
-        let sys_vm_path: PathBuf = [&input_path_string, "Main.jack"].iter().collect();
+        let sys_vm_path: PathBuf = [input_path_string, "Main.jack"].iter().collect();
         input_files.push(sys_vm_path);
     } else {
-        input_files.push(PathBuf::from(&input_path_string));
+        input_files.push(PathBuf::from(input_path_string));
+    }
+    input_files
+}
+
+/// Reads a source file, terminating the program if that fails
+fn read_source(input_file: &Path, source_map: &SourceMap) -> String {
+    let mut content = String::new();
+    let read_result = File::open(input_file).and_then(|mut file| file.read_to_string(&mut content));
+    if let Err(why) = read_result {
+        fail(
+            Diagnostic::error(format!("couldn't read {}: {}", input_file.display(), why)),
+            source_map,
+        );
+    }
+    content
+}
+
+/// A Jack class to compile
+struct InputFile {
+    /// Path of the .jack file, which also determines the names of the output files
+    path: PathBuf,
+    /// Jack source code
+    content: String,
+    /// Whether the class is part of the bundled OS rather than a file of the user
+    bundled: bool,
+}
+
+impl InputFile {
+    /// Name of the file in diagnostics
+    fn display_name(&self) -> String {
+        if self.bundled {
+            format!("os/{}", self.path.file_name().unwrap().to_string_lossy())
+        } else {
+            self.path.display().to_string()
+        }
     }
 
-    for input_file in input_files {
-        let mut jack_source_file_content = String::new();
+    /// Name of the class, which is also the name of the .vm file
+    fn class_name(&self) -> String {
+        self.path.file_stem().unwrap().to_string_lossy().into_owned()
+    }
+}
+
+/// Reads the .jack files to compile. If `link_os` is true, the bundled OS classes that the user
+/// does not provide are added, as if they were files next to the user's files.
+fn load_input_files(
+    input_path_string: &str,
+    is_dir: bool,
+    link_os: bool,
+    source_map: &SourceMap,
+) -> Vec<InputFile> {
+    let mut input_files: Vec<InputFile> = collect_input_files(input_path_string, is_dir, source_map)
+        .into_iter()
+        .map(|path| InputFile {
+            content: read_source(&path, source_map),
+            path,
+            bundled: false,
+        })
+        .collect();
+    if link_os {
+        let directory = if is_dir {
+            PathBuf::from(input_path_string)
+        } else {
+            Path::new(input_path_string)
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default()
+        };
+        let mut os_files = vec![];
+        for (class_name, source) in bundled_os::CLASSES.iter() {
+            if input_files.iter().all(|file| file.class_name() != *class_name) {
+                os_files.push(InputFile {
+                    path: directory.join(format!("{}.jack", class_name)),
+                    content: source.to_string(),
+                    bundled: true,
+                });
+            }
+        }
+        os_files.append(&mut input_files);
+        input_files = os_files;
+    }
+    input_files
+}
+
+/// An input file with its tokens and the class parsed from them
+struct ParsedFile {
+    input_file: InputFile,
+    tokens: Vec<SpannedToken>,
+    /// `None` if parsing could not continue up to the end of the class
+    class: Option<Class>,
+    syntax_errors: Vec<CompileError>,
+}
+
+impl ParsedFile {
+    /// The class, if it was parsed without syntax errors
+    fn complete_class(&self) -> Option<&Class> {
+        match self.class {
+            Some(ref class) if self.syntax_errors.is_empty() => Some(class),
+            _ => None,
+        }
+    }
 
-        {
-            let mut file = File::open(&input_file).expect("File not found.");
-            file.read_to_string(&mut jack_source_file_content)
-                .expect("Could not read file");
+    /// Compiles the class as a part of `program`, see `jack_compiler::compile_in_program`
+    fn compile(
+        &self,
+        max_errors: usize,
+        program: &ProgramSignatures,
+        implicit_return_this: bool,
+    ) -> Result<String, Vec<CompileError>> {
+        match self.class {
+            Some(ref class) => jack_compiler::compile_in_program(
+                class,
+                &self.syntax_errors,
+                max_errors,
+                program,
+                implicit_return_this,
+            ),
+            None => Err(self.syntax_errors.clone()),
         }
+    }
+}
 
-        let tokens = jack_tokenizer::tokenize(jack_source_file_content);
-        let mut jack_compiler = JackCompiler::new(&tokens);
-        let vm_output_string = jack_compiler.compile_class().expect("Parse Error!");
+/// Registers the input files in the source map, tokenizes them and parses each of them once.
+/// The later passes all work on the parsed classes.
+fn parse_input_files(
+    input_files: Vec<InputFile>,
+    max_errors: usize,
+    source_map: &mut SourceMap,
+) -> Vec<ParsedFile> {
+    input_files
+        .into_iter()
+        .map(|input_file| {
+            let file_id = source_map.add(input_file.display_name(), input_file.content.clone());
+            let tokens = jack_tokenizer::tokenize(input_file.content.clone(), file_id);
+            let (class, syntax_errors) = JackParser::new(&tokens, file_id)
+                .with_max_errors(max_errors)
+                .parse_class_partial();
+            ParsedFile {
+                input_file,
+                tokens,
+                class,
+                syntax_errors,
+            }
+        })
+        .collect()
+}
 
-        // Write to output file
-        let mut output_file_name = str::replace(
-            &input_file.into_os_string().into_string().unwrap(),
-            ".jack",
-            ".vm",
+/// Reports the errors of a file
+fn report_errors(errors: &[CompileError], source_map: &SourceMap) {
+    for error in errors {
+        report(&Diagnostic::from(error), source_map);
+    }
+}
+
+/// The signatures of the OS API and of all classes of the program: the classes to compile and,
+/// if `directory` is given, the other .jack files in it. Classes with syntax errors are added
+/// with the subroutines that could be parsed, the errors are reported when they are compiled.
+fn program_signatures(parsed_files: &[ParsedFile], directory: Option<&Path>) -> ProgramSignatures {
+    let mut program = ProgramSignatures::new().with_os_api();
+    if let Some(directory) = directory {
+        let pattern = directory.join("*.jack");
+        for path in glob(&pattern.to_string_lossy()).unwrap().filter_map(Result::ok) {
+            let is_input_file = parsed_files
+                .iter()
+                .any(|parsed_file| parsed_file.input_file.path == path);
+            if let (false, Ok(content)) = (is_input_file, std::fs::read_to_string(&path)) {
+                let tokens = jack_tokenizer::tokenize(content, 0);
+                let (class, errors) = JackParser::new(&tokens, 0).parse_class_partial();
+                let file_stem = path.file_stem().unwrap().to_string_lossy();
+                add_class_signatures(&mut program, class.as_ref(), errors.is_empty(), &file_stem);
+            }
+        }
+    }
+    for parsed_file in parsed_files {
+        add_class_signatures(
+            &mut program,
+            parsed_file.class.as_ref(),
+            parsed_file.syntax_errors.is_empty(),
+            &parsed_file.input_file.class_name(),
         );
+    }
+    program
+}
+
+/// Adds the signatures of a parsed class. A class with syntax errors is added as incomplete,
+/// under the name of its file if even its name could not be parsed.
+fn add_class_signatures(
+    program: &mut ProgramSignatures,
+    class: Option<&Class>,
+    is_complete: bool,
+    file_stem: &str,
+) {
+    match class {
+        Some(class) if is_complete => program.add_class(class),
+        Some(class) => program.add_incomplete_class(&class.name.name, Some(class)),
+        None => program.add_incomplete_class(file_stem, None),
+    }
+}
 
-        let path = Path::new(&output_file_name);
-        let display = path.display();
+/// Compiles a .jack file or all .jack files of a directory and runs the program in the VM
+/// interpreter, with the builtin OS providing the OS functions the program does not define, or
+/// with the bundled Jack OS linked into the program and no builtin functions at all
+fn run(matches: &ArgMatches) {
+    let input_path_string = matches.value_of("Run_input").unwrap();
+    let mut source_map = SourceMap::new();
 
-        let mut file = match File::create(&path) {
-            Err(why) => panic!("couldn't create {}: {}", display, why.description()),
-            Ok(file) => file,
+    let max_steps = match matches.value_of("max_steps").map(str::parse::<u64>) {
+        None => DEFAULT_MAX_STEPS,
+        Some(Ok(max_steps)) => max_steps,
+        Some(Err(why)) => fail(
+            Diagnostic::error(format!("invalid value for --max-steps: {}", why)),
+            &source_map,
+        ),
+    };
+
+    let screen_file = matches.value_of("screen").map(|screen_file| {
+        match ImageFormat::from_path(Path::new(screen_file)) {
+            Some(format) => (Path::new(screen_file), format),
+            None => fail(
+                Diagnostic::error(format!(
+                    "unknown image format of {}, use a .pbm or .png file",
+                    screen_file
+                )),
+                &source_map,
+            ),
+        }
+    });
+    let mut screen_steps = vec![];
+    for steps in matches.values_of("screen_at").into_iter().flatten() {
+        match steps.parse::<u64>() {
+            Ok(steps) => screen_steps.push(steps),
+            Err(why) => fail(
+                Diagnostic::error(format!("invalid value for --screen-at: {}", why)),
+                &source_map,
+            ),
+        }
+    }
+    screen_steps.sort_unstable();
+    screen_steps.dedup();
+
+    let key_events = match matches.value_of("keys") {
+        None => vec![],
+        Some(key_file) => match key_script::parse(&read_source(Path::new(key_file), &source_map)) {
+            Ok(key_events) => key_events,
+            Err(why) => fail(
+                Diagnostic::error(format!("couldn't parse {}: {}", key_file, why)),
+                &source_map,
+            ),
+        },
+    };
+
+    let is_dir = input_metadata(input_path_string, &source_map).is_dir();
+    let link_os = matches.is_present("link_os");
+    let mut num_errors = 0;
+    let mut vm_files = vec![];
+    let input_files = load_input_files(input_path_string, is_dir, link_os, &source_map);
+    let max_errors = jack_compiler::compile_error::DEFAULT_MAX_ERRORS;
+    let parsed_files = parse_input_files(input_files, max_errors, &mut source_map);
+    let program = program_signatures(&parsed_files, None);
+    let implicit_return_this = matches.is_present("implicit_return_this");
+    for parsed_file in &parsed_files {
+        if let (false, Some(class)) = (parsed_file.input_file.bundled, parsed_file.complete_class()) {
+            report_lints(class, &source_map);
+        }
+        match parsed_file.compile(max_errors, &program, implicit_return_this) {
+            Ok(vm_code) => vm_files.push((parsed_file.input_file.class_name(), vm_code)),
+            Err(errors) => {
+                report_errors(&errors, &source_map);
+                num_errors += errors.len();
+            }
+        }
+    }
+    if num_errors > 0 {
+        fail(
+            Diagnostic::error(format!("aborting due to {} previous error(s)", num_errors)),
+            &source_map,
+        );
+    }
+
+    let runtime_error =
+        |why: VmError| -> ! { fail(Diagnostic::error(format!("runtime error: {}", why)), &source_map) };
+    let mut interpreter = match VmInterpreter::from_vm_code(&vm_files) {
+        Ok(interpreter) if link_os => follow_bundled_os(interpreter, &parsed_files),
+        Ok(interpreter) => interpreter.with_builtin_os(),
+        Err(why) => runtime_error(why),
+    }
+    .with_key_events(key_events);
+    let write_screen = |path: &Path, format: ImageFormat, interpreter: &VmInterpreter| {
+        let screen = screen_image::screen_memory(interpreter.ram());
+        write_output(
+            path.to_str().unwrap(),
+            screen_image::encode(screen, format),
+            &source_map,
+        );
+    };
+    let mut status = interpreter.bootstrap().map(|_| Status::Running);
+    if let Some((screen_path, format)) = screen_file {
+        for &steps in screen_steps.iter().take_while(|&&steps| steps <= max_steps) {
+            status = interpreter.run(steps - interpreter.steps());
+            if status != Ok(Status::Running) {
+                break;
+            }
+            let mut file_name = screen_path.file_stem().unwrap().to_os_string();
+            file_name.push(format!("-{}", steps));
+            let path = screen_path
+                .with_file_name(file_name)
+                .with_extension(screen_path.extension().unwrap());
+            write_screen(&path, format, &interpreter);
+        }
+    }
+    if status == Ok(Status::Running) {
+        status = interpreter.run(max_steps - interpreter.steps());
+    }
+    if let Some((screen_path, format)) = screen_file {
+        write_screen(screen_path, format, &interpreter);
+    }
+    let (output_text, sys_error) = match interpreter.os() {
+        Some(os) => (os.output_text().to_string(), os.sys_error()),
+        None => bundled_os_output(interpreter.watched_calls()),
+    };
+    if output_text.is_empty() || output_text.ends_with('\n') {
+        print!("{}", output_text);
+    } else {
+        println!("{}", output_text);
+    }
+    match status {
+        Err(why) => runtime_error(why),
+        Ok(Status::Running) => report(
+            &Diagnostic::warning(format!("stopped after {} steps", interpreter.steps())),
+            &source_map,
+        ),
+        Ok(Status::Halted) => {
+            if let Some(code) = sys_error {
+                fail(
+                    Diagnostic::error(format!("the program stopped with Sys.error({})", code)),
+                    &source_map,
+                );
+            }
+        }
+    }
+}
+
+/// Lets `run` follow the bundled OS like the builtin one: the calls of the bundled
+/// `Output.printChar`, which every printed character goes through, and of the bundled `Sys.error`
+/// are recorded, and a call of the bundled `Sys.halt`, which loops forever, halts the program.
+/// OS classes that the user wrote are not followed.
+fn follow_bundled_os(interpreter: VmInterpreter, parsed_files: &[ParsedFile]) -> VmInterpreter {
+    let is_bundled = |class_name: &str| {
+        parsed_files.iter().any(|parsed_file| {
+            parsed_file.input_file.bundled && parsed_file.input_file.class_name() == class_name
+        })
+    };
+    let mut interpreter = interpreter;
+    if is_bundled("Output") {
+        interpreter = interpreter.with_watched_function("Output.printChar");
+    }
+    if is_bundled("Sys") {
+        interpreter = interpreter
+            .with_watched_function("Sys.error")
+            .with_halt_function("Sys.halt");
+    }
+    interpreter
+}
+
+/// The text printed by the bundled OS and the code passed to its `Sys.error`, from the calls
+/// recorded by `follow_bundled_os`
+fn bundled_os_output(calls: &[WatchedCall]) -> (String, Option<i16>) {
+    let mut output_text = String::new();
+    let mut sys_error = None;
+    for call in calls {
+        let arg = call.args.first().cloned().unwrap_or(0);
+        match call.name.as_str() {
+            "Output.printChar" => jack_os::record_char(&mut output_text, arg),
+            "Sys.error" => sys_error = Some(arg),
+            _ => {}
+        }
+    }
+    (output_text, sys_error)
+}
+
+/// Runs the optional type checker on a class and reports what it finds, as errors if `deny` is
+/// true and as warnings otherwise. Returns the number of reported errors.
+fn check_types(
+    class: &Class,
+    program: &ProgramSignatures,
+    deny: bool,
+    source_map: &SourceMap,
+) -> usize {
+    let errors = type_checker::check_class(class, program);
+    for error in &errors {
+        let mut diagnostic = Diagnostic::from(error);
+        if !deny {
+            diagnostic.severity = Severity::Warning;
+        }
+        report(&diagnostic, source_map);
+    }
+    if deny {
+        errors.len()
+    } else {
+        0
+    }
+}
+
+/// Reports the warnings of `jack_lints` for a class
+fn report_lints(class: &Class, source_map: &SourceMap) {
+    for warning in &jack_lints::lint_class(class) {
+        let mut diagnostic = Diagnostic::from(warning);
+        diagnostic.severity = Severity::Warning;
+        report(&diagnostic, source_map);
+    }
+}
+
+/// Compiles a .jack file or all .jack files of a directory
+fn compile_jack(matches: &ArgMatches) {
+    let input_path_string: String = matches.value_of("Jack_input").unwrap().to_string();
+
+    let mut source_map = SourceMap::new();
+
+    let max_errors = match matches.value_of("max_errors").map(str::parse::<usize>) {
+        None => jack_compiler::compile_error::DEFAULT_MAX_ERRORS,
+        Some(Ok(max_errors)) => max_errors,
+        Some(Err(why)) => fail(
+            Diagnostic::error(format!("invalid value for --max-errors: {}", why)),
+            &source_map,
+        ),
+    };
+    let emit: Vec<&str> = match matches.values_of("emit") {
+        Some(formats) => formats.collect(),
+        None => vec!["vm"],
+    };
+    let type_check = matches.value_of("type_check");
+    let implicit_return_this = matches.is_present("implicit_return_this");
+    let mut num_errors = 0;
+    let mut vm_files = vec![];
+
+    let input_metadata = input_metadata(&input_path_string, &source_map);
+
+    let link_os = matches.is_present("link_os");
+    let input_files =
+        load_input_files(&input_path_string, input_metadata.is_dir(), link_os, &source_map);
+    let parsed_files = parse_input_files(input_files, max_errors, &mut source_map);
+    // a single file is compiled as a part of the program in its directory
+    let program = if input_metadata.is_dir() {
+        program_signatures(&parsed_files, None)
+    } else {
+        program_signatures(&parsed_files, Path::new(&input_path_string).parent())
+    };
+
+    for parsed_file in &parsed_files {
+        let input_file = &parsed_file.input_file;
+        let input_file_name = input_file.path.display().to_string();
+        // classes with syntax errors are neither linted nor type checked, the compiler reports
+        // the syntax errors
+        let complete_class = parsed_file.complete_class().filter(|_| !input_file.bundled);
+
+        if emit.contains(&"xml") && !input_file.bundled {
+            let class = match complete_class {
+                Some(class) => class,
+                None => {
+                    report_errors(&parsed_file.syntax_errors, &source_map);
+                    num_errors += parsed_file.syntax_errors.len();
+                    continue;
+                }
+            };
+            write_output(
+                &str::replace(&input_file_name, ".jack", "T.xml"),
+                jack_xml::tokens_to_xml(&parsed_file.tokens),
+                &source_map,
+            );
+            write_output(
+                &str::replace(&input_file_name, ".jack", ".xml"),
+                jack_xml::class_to_xml(class),
+                &source_map,
+            );
+        }
+
+        if let Some(class) = complete_class {
+            report_lints(class, &source_map);
+        }
+
+        let num_type_errors = match (type_check, complete_class) {
+            (Some(level), Some(class)) => check_types(class, &program, level == "deny", &source_map),
+            _ => 0,
         };
+        num_errors += num_type_errors;
+
+        if emit.contains(&"vm") || emit.contains(&"asm") {
+            let vm_output_string = match parsed_file.compile(max_errors, &program, implicit_return_this) {
+                Ok(vm_output_string) => vm_output_string,
+                Err(errors) => {
+                    report_errors(&errors, &source_map);
+                    num_errors += errors.len();
+                    continue;
+                }
+            };
+            if num_type_errors > 0 {
+                continue;
+            }
+            if emit.contains(&"vm") {
+                write_output(
+                    &str::replace(&input_file_name, ".jack", ".vm"),
+                    &vm_output_string,
+                    &source_map,
+                );
+            }
+            if emit.contains(&"asm") {
+                match vm_command::parse(&vm_output_string) {
+                    Ok(commands) => vm_files.push((input_file.class_name(), commands)),
+                    Err(why) => fail(
+                        Diagnostic::error(format!(
+                            "generated invalid VM code for {}: {}",
+                            input_file_name, why
+                        )),
+                        &source_map,
+                    ),
+                }
+            }
+        }
+    }
 
-        match file.write_all(vm_output_string.as_bytes()) {
-            Err(why) => panic!("couldn't write to {}: {}", display, why.description()),
-            Ok(_) => println!("Successfully wrote xml to {}", display),
+    // a directory or a program linked with the OS is a whole program that is started by the
+    // bootstrap code, a single file is translated as it is
+    let bootstrap = input_metadata.is_dir() || link_os;
+    if emit.contains(&"asm") && num_errors == 0 {
+        let unresolved = vm_translator::unresolved_calls(&vm_files, bootstrap);
+        if !unresolved.is_empty() {
+            let names: Vec<String> = unresolved.iter().map(|name| format!("`{}`", name)).collect();
+            let mut diagnostic = Diagnostic::error(format!(
+                "cannot write assembly, the program calls undefined functions: {}",
+                names.join(", ")
+            ));
+            if !link_os {
+                diagnostic = diagnostic.with_help("use --link-os to include the bundled Jack OS");
+            }
+            fail(diagnostic, &source_map);
         }
+        let output_file_name = if input_metadata.is_dir() {
+            let directory = Path::new(&input_path_string);
+            let program_name = match directory.canonicalize() {
+                Ok(path) => path.file_name().map(|name| name.to_string_lossy().into_owned()),
+                Err(_) => None,
+            }
+            .unwrap_or_else(|| "out".to_string());
+            directory
+                .join(format!("{}.asm", program_name))
+                .display()
+                .to_string()
+        } else {
+            str::replace(&input_path_string, ".jack", ".asm")
+        };
+        write_output(
+            &output_file_name,
+            vm_translator::translate(&vm_files, bootstrap),
+            &source_map,
+        );
+    }
+
+    if num_errors > 0 {
+        fail(
+            Diagnostic::error(format!("aborting due to {} previous error(s)", num_errors)),
+            &source_map,
+        );
     }
 }
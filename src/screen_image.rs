@@ -0,0 +1,158 @@
+//! screen_image
+//! Renders the Hack screen memory map into image files. The screen has 256 rows of 32 words,
+//! the least significant bit of a word is the leftmost of its 16 pixels and a set bit is black.
+//! Images are written as binary PBM (P4) or as 1-bit grayscale PNG.
+
+use jack_os::{SCREEN_HEIGHT, SCREEN_WIDTH, WORDS_PER_ROW};
+use std::path::Path;
+use vm_interpreter::SCREEN;
+
+/// Number of words of the screen memory map
+pub const SCREEN_WORDS: usize = SCREEN_HEIGHT * WORDS_PER_ROW;
+
+/// Supported image file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Portable bitmap, binary variant (P4)
+    Pbm,
+    /// Portable network graphics, 1-bit grayscale
+    Png,
+}
+
+impl ImageFormat {
+    /// The format belonging to the extension of `path` (`.pbm` or `.png`)
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "pbm" => Some(ImageFormat::Pbm),
+            "png" => Some(ImageFormat::Png),
+            _ => None,
+        }
+    }
+}
+
+/// The screen memory map within the RAM
+pub fn screen_memory(ram: &[i16]) -> &[i16] {
+    &ram[SCREEN..SCREEN + SCREEN_WORDS]
+}
+
+/// Encodes the screen memory map `screen` in the given format
+pub fn encode(screen: &[i16], format: ImageFormat) -> Vec<u8> {
+    match format {
+        ImageFormat::Pbm => to_pbm(screen),
+        ImageFormat::Png => to_png(screen),
+    }
+}
+
+/// Packs the pixels into rows of bytes, leftmost pixel in the most significant bit.
+/// A black pixel is a set bit if `black_is_one` is true and a cleared bit otherwise.
+fn packed_rows(screen: &[i16], black_is_one: bool) -> Vec<Vec<u8>> {
+    assert_eq!(
+        screen.len(),
+        SCREEN_WORDS,
+        "the screen has {} words",
+        SCREEN_WORDS
+    );
+    screen
+        .chunks(WORDS_PER_ROW)
+        .map(|row| {
+            let mut bytes = Vec::with_capacity(SCREEN_WIDTH / 8);
+            for &word in row {
+                let word = if black_is_one {
+                    word as u16
+                } else {
+                    !(word as u16)
+                };
+                // the Hack screen stores the leftmost pixel in bit 0
+                let word = word.reverse_bits();
+                bytes.push((word >> 8) as u8);
+                bytes.push(word as u8);
+            }
+            bytes
+        })
+        .collect()
+}
+
+/// Encodes the screen memory map as a binary PBM image
+pub fn to_pbm(screen: &[i16]) -> Vec<u8> {
+    let mut image = format!("P4\n{} {}\n", SCREEN_WIDTH, SCREEN_HEIGHT).into_bytes();
+    for row in packed_rows(screen, true) {
+        image.extend(row);
+    }
+    image
+}
+
+/// Encodes the screen memory map as a 1-bit grayscale PNG image
+pub fn to_png(screen: &[i16]) -> Vec<u8> {
+    // every scanline starts with filter type 0 (none)
+    let mut scanlines = vec![];
+    for row in packed_rows(screen, false) {
+        scanlines.push(0);
+        scanlines.extend(row);
+    }
+
+    let mut header = vec![];
+    header.extend(&(SCREEN_WIDTH as u32).to_be_bytes());
+    header.extend(&(SCREEN_HEIGHT as u32).to_be_bytes());
+    // bit depth 1, grayscale, deflate, adaptive filtering, no interlace
+    header.extend(&[1, 0, 0, 0, 0]);
+
+    let mut image = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_png_chunk(&mut image, b"IHDR", &header);
+    write_png_chunk(&mut image, b"IDAT", &zlib_stored(&scanlines));
+    write_png_chunk(&mut image, b"IEND", &[]);
+    image
+}
+
+fn write_png_chunk(image: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    image.extend(&(data.len() as u32).to_be_bytes());
+    let start = image.len();
+    image.extend(chunk_type);
+    image.extend(data);
+    let crc = crc32(&image[start..]);
+    image.extend(&crc.to_be_bytes());
+}
+
+/// Wraps `data` into a zlib stream of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK_LEN: usize = 65535;
+    let mut stream = vec![0x78, 0x01];
+    let num_blocks = ((data.len() + MAX_BLOCK_LEN - 1) / MAX_BLOCK_LEN).max(1);
+    for i in 0..num_blocks {
+        let block = &data[i * MAX_BLOCK_LEN..data.len().min((i + 1) * MAX_BLOCK_LEN)];
+        let is_final = i + 1 == num_blocks;
+        stream.push(is_final as u8);
+        let len = block.len() as u16;
+        stream.extend(&len.to_le_bytes());
+        stream.extend(&(!len).to_le_bytes());
+        stream.extend(block);
+    }
+    stream.extend(&adler32(data).to_be_bytes());
+    stream
+}
+
+/// The CRC-32 that ends every PNG chunk, the one of zlib and Ethernet
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// The Adler-32 checksum that ends a zlib stream
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
@@ -0,0 +1,96 @@
+//! symbol_table
+//! Scoped symbol table of a Jack class. The class scope holds the statics and fields, the
+//! subroutine scope the parameters and locals of the subroutine being compiled. A name is
+//! looked up in the subroutine scope first, so parameters and locals shadow statics and fields.
+
+use jack_ast::{JackVariableType, VariableKind};
+
+use std::collections::HashMap;
+
+/// A declared variable
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    /// Declared type
+    pub var_type: JackVariableType,
+    /// Static, field, local or parameter
+    pub kind: VariableKind,
+    /// Running number among the variables of the same kind in the scope
+    pub index: usize,
+}
+
+impl Symbol {
+    /// The VM memory segment holding variables of this kind
+    pub fn segment(&self) -> &'static str {
+        match self.kind {
+            VariableKind::Jstatic => "static",
+            VariableKind::Jfield => "this",
+            VariableKind::Jvar => "local",
+            VariableKind::Jarg => "argument",
+        }
+    }
+
+    /// Segment and index of the variable as used by `push` and `pop`, e.g. `local 2`
+    pub fn vm_location(&self) -> String {
+        format!("{} {}", self.segment(), self.index)
+    }
+}
+
+/// The variables visible in a subroutine of a class
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    class_scope: HashMap<String, Symbol>,
+    subroutine_scope: HashMap<String, Symbol>,
+}
+
+impl SymbolTable {
+    /// Constructor
+    pub fn new() -> Self {
+        SymbolTable {
+            class_scope: HashMap::new(),
+            subroutine_scope: HashMap::new(),
+        }
+    }
+
+    /// Forgets the parameters and locals of the previous subroutine
+    pub fn start_subroutine(&mut self) {
+        self.subroutine_scope.clear();
+    }
+
+    /// Declares a variable in the scope of its kind. Returns `false` and keeps the existing
+    /// declaration if the name is already declared in that scope.
+    pub fn define(&mut self, name: &str, var_type: JackVariableType, kind: VariableKind) -> bool {
+        let index = self.count(kind);
+        let scope = match kind {
+            VariableKind::Jstatic | VariableKind::Jfield => &mut self.class_scope,
+            VariableKind::Jvar | VariableKind::Jarg => &mut self.subroutine_scope,
+        };
+        if scope.contains_key(name) {
+            return false;
+        }
+        scope.insert(
+            name.to_string(),
+            Symbol {
+                var_type,
+                kind,
+                index,
+            },
+        );
+        true
+    }
+
+    /// The variable a name refers to, `None` if it is not declared
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.subroutine_scope
+            .get(name)
+            .or_else(|| self.class_scope.get(name))
+    }
+
+    /// Number of variables of a kind, e.g. the number of fields an object needs memory for
+    pub fn count(&self, kind: VariableKind) -> usize {
+        let scope = match kind {
+            VariableKind::Jstatic | VariableKind::Jfield => &self.class_scope,
+            VariableKind::Jvar | VariableKind::Jarg => &self.subroutine_scope,
+        };
+        scope.values().filter(|symbol| symbol.kind == kind).count()
+    }
+}
@@ -0,0 +1,597 @@
+//! test_script
+//! Runs the `.tst` test scripts of the course's VM emulator against the VM interpreter. The
+//! supported commands are `load`, `output-file`, `compare-to`, `output-list`, `set`, `repeat`,
+//! `vmstep` and `output`. Every line written by `output-list` and `output` is compared to the
+//! same line of the compare file, and the script stops at the first line that differs.
+//!
+//! Like the VM emulator, `load` starts the program at `Sys.init` if it exists and at the first
+//! command otherwise, without setting up the stack: the script does that with `set`.
+//! OS functions that the program does not define are provided by the builtin OS.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use vm_command::Segment;
+use vm_interpreter::{VmInterpreter, ARG, LCL, SP, TEMP_BASE, THAT, THIS};
+
+/// A command of a test script that is invalid or fails
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestScriptError {
+    /// 1-based line number in the test script
+    pub line: usize,
+    /// What is wrong with the command
+    pub message: String,
+}
+
+impl fmt::Display for TestScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for TestScriptError {}
+
+/// An output line that differs from the compare file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonFailure {
+    /// 1-based line number in the output and compare files
+    pub line: usize,
+    /// The line of the compare file, empty if the compare file is shorter
+    pub expected: String,
+    /// The line written by the script
+    pub actual: String,
+}
+
+impl fmt::Display for ComparisonFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "comparison failure at line {}: expected `{}`, found `{}`",
+            self.line, self.expected, self.actual
+        )
+    }
+}
+
+/// The result of a test script that ran to its end or to the first comparison failure
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestRun {
+    /// The file set with `output-file`
+    pub output_file: Option<PathBuf>,
+    /// The lines written by `output-list` and `output`
+    pub output: String,
+    /// Whether a compare file was set with `compare-to`
+    pub compared: bool,
+    /// The first line that differs from the compare file
+    pub comparison_failure: Option<ComparisonFailure>,
+}
+
+/// A value of the VM state that can be set and written to the output
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Variable {
+    /// `sp`, `local`, `argument`, `this` and `that` are the pointers in RAM[0..5]
+    Ram(u16),
+    /// `local[i]`, `argument[i]`, `this[i]`, `that[i]` and `temp[i]`
+    SegmentCell(Segment, u16),
+}
+
+/// Column of the output table, `name%Fl.w.r` in `output-list`
+#[derive(Debug, Clone, PartialEq)]
+struct OutputColumn {
+    name: String,
+    variable: Variable,
+    /// `D` (decimal), `X` (hexadecimal), `B` (binary) or `S` (string)
+    format: char,
+    pad_left: usize,
+    width: usize,
+    pad_right: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CommandKind {
+    Load(Option<String>),
+    OutputFile(String),
+    CompareTo(String),
+    OutputList(Vec<OutputColumn>),
+    Set(Variable, i16),
+    Repeat(u32, Vec<Command>),
+    VmStep,
+    Output,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Command {
+    line: usize,
+    kind: CommandKind,
+}
+
+/// Splits a script into words, `{`, `}` and the command terminators `,`, `;` and `!`, skipping
+/// `//` and `/* */` comments
+fn tokenize(script: &str) -> Vec<(usize, String)> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut chars = script.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            ',' | ';' | '!' | '{' | '}' => tokens.push((line, c.to_string())),
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || ",;!{}".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push((line, word));
+            }
+        }
+    }
+    tokens
+}
+
+fn parse_variable(name: &str) -> Result<Variable, String> {
+    let pointer = |name: &str| match name {
+        "sp" => Some(SP),
+        "local" => Some(LCL),
+        "argument" => Some(ARG),
+        "this" => Some(THIS),
+        "that" => Some(THAT),
+        _ => None,
+    };
+    if let Some(address) = pointer(name) {
+        return Ok(Variable::Ram(address as u16));
+    }
+    let unknown = || format!("unknown variable `{}`", name);
+    let open = name.find('[').ok_or_else(unknown)?;
+    let index = name[open + 1..]
+        .strip_suffix(']')
+        .and_then(|index| index.parse::<u16>().ok())
+        .ok_or_else(unknown)?;
+    let segment = match &name[..open] {
+        "RAM" if index < 32768 => return Ok(Variable::Ram(index)),
+        "local" => Segment::Local,
+        "argument" => Segment::Argument,
+        "this" => Segment::This,
+        "that" => Segment::That,
+        "temp" if index < 8 => Segment::Temp,
+        _ => return Err(unknown()),
+    };
+    Ok(Variable::SegmentCell(segment, index))
+}
+
+/// Parses a value of `set`: a decimal number or `%D`, `%X` or `%B` followed by a number
+fn parse_value(value: &str) -> Result<i16, String> {
+    let (radix, digits) = match value.get(..2) {
+        Some("%D") => (10, &value[2..]),
+        Some("%X") => (16, &value[2..]),
+        Some("%B") => (2, &value[2..]),
+        _ => (10, value),
+    };
+    match i32::from_str_radix(digits, radix) {
+        Ok(number) if radix == 10 && (-32768..=32767).contains(&number) => Ok(number as i16),
+        // hexadecimal and binary values are 16 bit patterns
+        Ok(number) if radix != 10 && (0..=0xffff).contains(&number) => Ok(number as u16 as i16),
+        _ => Err(format!("invalid value `{}`", value)),
+    }
+}
+
+/// Parses an `output-list` entry like `RAM[0]%D2.6.2`. Without a format, `%D1.6.1` is used.
+fn parse_output_column(entry: &str) -> Result<OutputColumn, String> {
+    let (name, format) = match entry.find('%') {
+        Some(percent) => (&entry[..percent], &entry[percent + 1..]),
+        None => (entry, "D1.6.1"),
+    };
+    let variable = parse_variable(name)?;
+    let invalid = || format!("invalid output format `{}`", entry);
+    let mut format_chars = format.chars();
+    let format_char = match format_chars.next() {
+        Some(c) if "DXBS".contains(c) => c,
+        _ => return Err(invalid()),
+    };
+    let sizes: Vec<usize> = format_chars
+        .as_str()
+        .split('.')
+        .map(|size| size.parse::<usize>().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    if sizes.len() != 3 {
+        return Err(invalid());
+    }
+    Ok(OutputColumn {
+        name: name.to_string(),
+        variable,
+        format: format_char,
+        pad_left: sizes[0],
+        width: sizes[1],
+        pad_right: sizes[2],
+    })
+}
+
+struct Parser {
+    tokens: Vec<(usize, String)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens
+            .get(self.position)
+            .map(|(_, token)| token.as_str())
+    }
+
+    fn line(&self) -> usize {
+        match self
+            .tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+        {
+            Some((line, _)) => *line,
+            None => 1,
+        }
+    }
+
+    fn error(&self, message: String) -> TestScriptError {
+        TestScriptError {
+            line: self.line(),
+            message,
+        }
+    }
+
+    /// The arguments of a command up to its terminator
+    fn arguments(&mut self) -> Result<Vec<String>, TestScriptError> {
+        let mut arguments = vec![];
+        loop {
+            match self.peek() {
+                Some(",") | Some(";") | Some("!") => {
+                    self.position += 1;
+                    return Ok(arguments);
+                }
+                Some("{") | Some("}") | None => {
+                    return Err(self.error("expected `,`, `;` or `!` after the command".to_string()))
+                }
+                Some(argument) => {
+                    arguments.push(argument.to_string());
+                    self.position += 1;
+                }
+            }
+        }
+    }
+
+    /// Parses commands until the end of the script or until a `}`
+    fn commands(&mut self) -> Result<Vec<Command>, TestScriptError> {
+        let mut commands = vec![];
+        while let Some(word) = self.peek() {
+            if word == "}" {
+                break;
+            }
+            let line = self.line();
+            let word = word.to_string();
+            self.position += 1;
+            let kind = if word == "repeat" {
+                self.repeat()?
+            } else {
+                let arguments = self.arguments()?;
+                let error = |message: String| TestScriptError { line, message };
+                let single_argument = || match arguments.as_slice() {
+                    [argument] => Ok(argument.clone()),
+                    _ => Err(error(format!("`{}` expects one argument", word))),
+                };
+                match word.as_str() {
+                    "load" => match arguments.as_slice() {
+                        [] => CommandKind::Load(None),
+                        [file] => CommandKind::Load(Some(file.clone())),
+                        _ => return Err(error("`load` expects at most one argument".to_string())),
+                    },
+                    "output-file" => CommandKind::OutputFile(single_argument()?),
+                    "compare-to" => CommandKind::CompareTo(single_argument()?),
+                    "output-list" => CommandKind::OutputList(
+                        arguments
+                            .iter()
+                            .map(|entry| parse_output_column(entry))
+                            .collect::<Result<_, _>>()
+                            .map_err(error)?,
+                    ),
+                    "set" => match arguments.as_slice() {
+                        [variable, value] => CommandKind::Set(
+                            parse_variable(variable).map_err(error)?,
+                            parse_value(value).map_err(error)?,
+                        ),
+                        _ => return Err(error("`set` expects a variable and a value".to_string())),
+                    },
+                    "vmstep" | "output" if !arguments.is_empty() => {
+                        return Err(error(format!("`{}` expects no arguments", word)))
+                    }
+                    "vmstep" => CommandKind::VmStep,
+                    "output" => CommandKind::Output,
+                    _ => return Err(error(format!("unsupported command `{}`", word))),
+                }
+            };
+            commands.push(Command { line, kind });
+        }
+        Ok(commands)
+    }
+
+    /// `repeat n { commands }`, the `repeat` is already consumed
+    fn repeat(&mut self) -> Result<CommandKind, TestScriptError> {
+        let count = match self.peek().map(str::parse::<u32>) {
+            Some(Ok(count)) => count,
+            _ => return Err(self.error("expected the number of repetitions".to_string())),
+        };
+        self.position += 1;
+        if self.peek() != Some("{") {
+            return Err(self.error("expected `{` after the number of repetitions".to_string()));
+        }
+        self.position += 1;
+        let body = self.commands()?;
+        if self.peek() != Some("}") {
+            return Err(self.error("expected `}` at the end of `repeat`".to_string()));
+        }
+        self.position += 1;
+        Ok(CommandKind::Repeat(count, body))
+    }
+}
+
+/// Parses a test script
+fn parse(script: &str) -> Result<Vec<Command>, TestScriptError> {
+    let mut parser = Parser {
+        tokens: tokenize(script),
+        position: 0,
+    };
+    let commands = parser.commands()?;
+    match parser.peek() {
+        Some(_) => Err(parser.error("unexpected `}`".to_string())),
+        None => Ok(commands),
+    }
+}
+
+/// Formats a value right-aligned in `width` characters, cutting off characters on the left
+fn fit(text: String, width: usize) -> String {
+    let start = text.len().saturating_sub(width);
+    format!("{:>width$}", &text[start..], width = width)
+}
+
+impl OutputColumn {
+    fn header(&self) -> String {
+        let width = self.pad_left + self.width + self.pad_right;
+        let name: String = self.name.chars().take(width).collect();
+        let left = (width - name.len()) / 2;
+        format!(
+            "{}{}{}",
+            " ".repeat(left),
+            name,
+            " ".repeat(width - left - name.len())
+        )
+    }
+
+    fn cell(&self, value: i16) -> String {
+        let text = match self.format {
+            'X' => fit(format!("{:04X}", value as u16), self.width),
+            'B' => fit(format!("{:016b}", value as u16), self.width),
+            'S' => format!("{:<width$}", value, width = self.width),
+            _ => fit(value.to_string(), self.width),
+        };
+        format!(
+            "{}{}{}",
+            " ".repeat(self.pad_left),
+            text,
+            " ".repeat(self.pad_right)
+        )
+    }
+}
+
+struct Runner {
+    directory: PathBuf,
+    interpreter: Option<VmInterpreter>,
+    output_list: Vec<OutputColumn>,
+    compare_lines: Option<Vec<String>>,
+    num_output_lines: usize,
+    run: TestRun,
+}
+
+impl Runner {
+    fn interpreter(&mut self) -> Result<&mut VmInterpreter, String> {
+        self.interpreter
+            .as_mut()
+            .ok_or_else(|| "no program is loaded".to_string())
+    }
+
+    fn address(&mut self, variable: Variable) -> Result<usize, String> {
+        let ram = self.interpreter()?.ram();
+        let address = match variable {
+            Variable::Ram(address) => i32::from(address),
+            Variable::SegmentCell(Segment::Temp, index) => TEMP_BASE as i32 + i32::from(index),
+            Variable::SegmentCell(segment, index) => {
+                let base = match segment {
+                    Segment::Local => LCL,
+                    Segment::Argument => ARG,
+                    Segment::This => THIS,
+                    _ => THAT,
+                };
+                i32::from(ram[base]) + i32::from(index)
+            }
+        };
+        if address < 0 || address as usize >= ram.len() {
+            return Err(format!("address {} is outside of the RAM", address));
+        }
+        Ok(address as usize)
+    }
+
+    fn load(&mut self, file: &Option<String>) -> Result<(), String> {
+        let path = match file {
+            Some(file) => self.directory.join(file),
+            None => self.directory.clone(),
+        };
+        let mut vm_paths = vec![];
+        if path.is_dir() {
+            let entries = fs::read_dir(&path)
+                .map_err(|why| format!("couldn't read {}: {}", path.display(), why))?;
+            for entry in entries {
+                let entry_path = entry.map_err(|why| why.to_string())?.path();
+                if entry_path
+                    .extension()
+                    .is_some_and(|extension| extension == "vm")
+                {
+                    vm_paths.push(entry_path);
+                }
+            }
+            vm_paths.sort();
+        } else {
+            vm_paths.push(path);
+        }
+        let mut files = vec![];
+        for vm_path in vm_paths {
+            let vm_code = fs::read_to_string(&vm_path)
+                .map_err(|why| format!("couldn't read {}: {}", vm_path.display(), why))?;
+            let file_name = vm_path.file_stem().unwrap().to_string_lossy().into_owned();
+            files.push((file_name, vm_code));
+        }
+        let mut interpreter = VmInterpreter::from_vm_code(&files)
+            .map_err(|why| why.to_string())?
+            .with_builtin_os();
+        if interpreter.has_function("Sys.init") {
+            interpreter
+                .start_at("Sys.init")
+                .map_err(|why| why.to_string())?;
+        }
+        self.interpreter = Some(interpreter);
+        Ok(())
+    }
+
+    /// Writes a line to the output and compares it, returns false on a comparison failure
+    fn output_line(&mut self, line: String) -> bool {
+        self.num_output_lines += 1;
+        let line_number = self.num_output_lines;
+        self.run.output += &line;
+        self.run.output += "\n";
+        if let Some(compare_lines) = &self.compare_lines {
+            let expected = compare_lines
+                .get(line_number - 1)
+                .cloned()
+                .unwrap_or_default();
+            if expected != line {
+                self.run.comparison_failure = Some(ComparisonFailure {
+                    line: line_number,
+                    expected,
+                    actual: line,
+                });
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Executes commands, returns false if the script stops at a comparison failure
+    fn execute(&mut self, commands: &[Command]) -> Result<bool, TestScriptError> {
+        for command in commands {
+            let error = |message: String| TestScriptError {
+                line: command.line,
+                message,
+            };
+            match &command.kind {
+                CommandKind::Load(file) => self.load(file).map_err(error)?,
+                CommandKind::OutputFile(file) => {
+                    self.run.output_file = Some(self.directory.join(file));
+                }
+                CommandKind::CompareTo(file) => {
+                    let path = self.directory.join(file);
+                    let compare_file = fs::read_to_string(&path).map_err(|why| {
+                        error(format!("couldn't read {}: {}", path.display(), why))
+                    })?;
+                    self.compare_lines = Some(
+                        compare_file
+                            .lines()
+                            .map(|line| line.trim_end().to_string())
+                            .collect(),
+                    );
+                    self.run.compared = true;
+                }
+                CommandKind::OutputList(columns) => {
+                    self.output_list = columns.clone();
+                    let header: String =
+                        columns.iter().map(|column| column.header() + "|").collect();
+                    if !self.output_line(format!("|{}", header)) {
+                        return Ok(false);
+                    }
+                }
+                CommandKind::Set(variable, value) => {
+                    let address = self.address(*variable).map_err(error)?;
+                    self.interpreter().map_err(error)?.ram_mut()[address] = *value;
+                }
+                CommandKind::Repeat(count, body) => {
+                    for _ in 0..*count {
+                        if !self.execute(body)? {
+                            return Ok(false);
+                        }
+                    }
+                }
+                CommandKind::VmStep => {
+                    self.interpreter()
+                        .map_err(error)?
+                        .step()
+                        .map_err(|why| error(format!("runtime error: {}", why)))?;
+                }
+                CommandKind::Output => {
+                    let mut cells = String::new();
+                    for column in self.output_list.clone() {
+                        let address = self.address(column.variable).map_err(error)?;
+                        let value = self.interpreter().map_err(error)?.ram()[address];
+                        cells += &column.cell(value);
+                        cells += "|";
+                    }
+                    if !self.output_line(format!("|{}", cells)) {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Runs a test script. File names in the script are relative to `directory`, usually the
+/// directory of the script. The output is not written to the output file, see
+/// `TestRun::output_file`.
+pub fn run(script: &str, directory: &Path) -> Result<TestRun, TestScriptError> {
+    let commands = parse(script)?;
+    let directory = if directory.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        directory
+    };
+    let mut runner = Runner {
+        directory: directory.to_path_buf(),
+        interpreter: None,
+        output_list: vec![],
+        compare_lines: None,
+        num_output_lines: 0,
+        run: TestRun {
+            output_file: None,
+            output: String::new(),
+            compared: false,
+            comparison_failure: None,
+        },
+    };
+    runner.execute(&commands)?;
+    Ok(runner.run)
+}
@@ -0,0 +1,395 @@
+//! type_checker
+//! Optional pass that checks the types of expressions and statements of a Jack class. The rules
+//! are as lenient as Jack itself: `int` and `char` are interchangeable, `null` can be assigned to
+//! any object, and `Array` is untyped, i.e. its elements have no type and an `Array` can be used
+//! like an `int` holding an address and vice versa. Values whose type cannot be known, like an
+//! array element or the result of a subroutine without a known signature, are accepted
+//! everywhere.
+
+use compile_error::CompileError;
+use jack_ast::*;
+use jack_signatures::ProgramSignatures;
+use jack_tokenizer::Span;
+use symbol_table::SymbolTable;
+
+use std::fmt;
+
+/// The type of an expression
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Int,
+    Char,
+    Boolean,
+    Object(String),
+    /// `null`, which can be assigned to every object
+    Null,
+    /// The result of a `void` subroutine
+    Void,
+    /// A value whose type is not known, e.g. an array element
+    Unknown,
+}
+
+impl<'a> From<&'a JackVariableType> for Type {
+    fn from(var_type: &'a JackVariableType) -> Self {
+        match var_type {
+            JackVariableType::Jint => Type::Int,
+            JackVariableType::Jchar => Type::Char,
+            JackVariableType::Jboolean => Type::Boolean,
+            JackVariableType::Jclass(name) => Type::Object(name.clone()),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Char => write!(f, "char"),
+            Type::Boolean => write!(f, "boolean"),
+            Type::Object(name) => write!(f, "{}", name),
+            Type::Null => write!(f, "null"),
+            Type::Void => write!(f, "void"),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl Type {
+    fn is_array(&self) -> bool {
+        *self == Type::Object("Array".to_string())
+    }
+
+    /// Whether the value can be used in arithmetic, which includes addresses in an `Array`
+    fn is_numeric(&self) -> bool {
+        match self {
+            Type::Int | Type::Char | Type::Unknown => true,
+            _ => self.is_array(),
+        }
+    }
+
+    /// Whether a value of this type can be assigned to a variable of type `target`
+    fn is_assignable_to(&self, target: &Type) -> bool {
+        match (self, target) {
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (_, Type::Int) | (_, Type::Char) => self.is_numeric(),
+            (_, Type::Boolean) => *self == Type::Boolean,
+            (Type::Boolean, _) | (Type::Void, _) => false,
+            (_, Type::Object(_)) if target.is_array() => true,
+            (Type::Null, Type::Object(_)) => true,
+            (_, Type::Object(_)) => self == target || self.is_array(),
+            (_, Type::Null) | (_, Type::Void) => false,
+        }
+    }
+}
+
+/// Checks the types in a class, see the module documentation for the rules. The signatures of
+/// the subroutines that are called are looked up in `program`, which should contain the class
+/// itself. Calls of subroutines that `program` does not know are not checked.
+pub fn check_class(class: &Class, program: &ProgramSignatures) -> Vec<CompileError> {
+    let mut checker = TypeChecker {
+        class,
+        program,
+        symbol_table: SymbolTable::new(),
+        return_type: Type::Void,
+        errors: vec![],
+    };
+    checker.check_class();
+    checker.errors.sort_by_key(|error| error.span().offset);
+    checker.errors
+}
+
+struct TypeChecker<'a> {
+    class: &'a Class,
+    program: &'a ProgramSignatures,
+    symbol_table: SymbolTable,
+    return_type: Type,
+    errors: Vec<CompileError>,
+}
+
+impl<'a> TypeChecker<'a> {
+    fn mismatch(&mut self, expected: &Type, found: &Type, span: Span) {
+        self.errors.push(CompileError::TypeMismatch {
+            expected: expected.to_string(),
+            found: found.to_string(),
+            span,
+        });
+    }
+
+    fn invalid_operand(&mut self, operator: &str, found: &Type, span: Span) {
+        self.errors.push(CompileError::InvalidOperand {
+            operator: operator.to_string(),
+            found: found.to_string(),
+            span,
+        });
+    }
+
+    /// The type of a variable, `None` if it is not declared. Undeclared variables are reported
+    /// by the code generator.
+    fn variable_type(&self, name: &str) -> Option<Type> {
+        self.symbol_table
+            .lookup(name)
+            .map(|symbol| Type::from(&symbol.var_type))
+    }
+
+    fn check_class(&mut self) {
+        let class = self.class;
+        for class_var_dec in &class.class_var_decs {
+            for name in &class_var_dec.names {
+                self.symbol_table.define(
+                    &name.name,
+                    class_var_dec.var_type.clone(),
+                    class_var_dec.kind,
+                );
+            }
+        }
+        for subroutine_dec in &class.subroutine_decs {
+            self.symbol_table.start_subroutine();
+            for parameter in &subroutine_dec.parameters {
+                self.symbol_table.define(
+                    &parameter.name.name,
+                    parameter.var_type.clone(),
+                    VariableKind::Jarg,
+                );
+            }
+            for var_dec in &subroutine_dec.var_decs {
+                for name in &var_dec.names {
+                    self.symbol_table.define(
+                        &name.name,
+                        var_dec.var_type.clone(),
+                        VariableKind::Jvar,
+                    );
+                }
+            }
+            self.return_type = match &subroutine_dec.return_type {
+                Some(return_type) => Type::from(return_type),
+                None => Type::Void,
+            };
+            self.check_statements(&subroutine_dec.statements);
+        }
+    }
+
+    fn check_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Let {
+                target,
+                index,
+                value,
+            } => {
+                let value_type = self.expression_type(value);
+                let target_type = match self.variable_type(&target.name) {
+                    Some(target_type) => target_type,
+                    None => return,
+                };
+                if let Some(index) = index {
+                    // array elements are untyped
+                    self.check_indexing(&target_type, index, target.span);
+                } else if !value_type.is_assignable_to(&target_type) {
+                    self.mismatch(&target_type, &value_type, value.span);
+                }
+            }
+            StatementKind::If {
+                condition,
+                then_statements,
+                else_statements,
+            } => {
+                self.check_condition(condition);
+                self.check_statements(then_statements);
+                if let Some(else_statements) = else_statements {
+                    self.check_statements(else_statements);
+                }
+            }
+            StatementKind::While {
+                condition,
+                statements,
+            } => {
+                self.check_condition(condition);
+                self.check_statements(statements);
+            }
+            StatementKind::Do(call) => {
+                self.call_type(call);
+            }
+            StatementKind::Return(value) => {
+                // returns that do not match `void` are reported by the compiler
+                let return_type = self.return_type.clone();
+                if let Some(value) = value {
+                    let value_type = self.expression_type(value);
+                    if return_type != Type::Void && !value_type.is_assignable_to(&return_type) {
+                        self.mismatch(&return_type, &value_type, value.span);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Expression) {
+        let condition_type = self.expression_type(condition);
+        if !condition_type.is_assignable_to(&Type::Boolean) {
+            self.mismatch(&Type::Boolean, &condition_type, condition.span);
+        }
+    }
+
+    /// Checks `name[index]` where `name` has the type `array_type`
+    fn check_indexing(&mut self, array_type: &Type, index: &Expression, name_span: Span) {
+        let index_type = self.expression_type(index);
+        if !index_type.is_numeric() {
+            self.mismatch(&Type::Int, &index_type, index.span);
+        }
+        if !array_type.is_numeric() {
+            self.invalid_operand("[]", array_type, name_span);
+        }
+    }
+
+    fn expression_type(&mut self, expression: &Expression) -> Type {
+        let mut left = (self.term_type(&expression.term), expression.term.span);
+        for (operation, term) in &expression.operations {
+            let right = (self.term_type(term), term.span);
+            let left_span = left.1;
+            left = (
+                self.operation_type(*operation, left, right),
+                left_span.to(term.span),
+            );
+        }
+        left.0
+    }
+
+    /// The type of `left operation right`, the operands are given with their locations
+    fn operation_type(
+        &mut self,
+        operation: JackOperation,
+        (left, left_span): (Type, Span),
+        (right, right_span): (Type, Span),
+    ) -> Type {
+        let symbol = operation.symbol().to_string();
+        match operation {
+            JackOperation::Add
+            | JackOperation::Subtract
+            | JackOperation::Multiply
+            | JackOperation::Divide => {
+                for (operand, span) in &[(&left, left_span), (&right, right_span)] {
+                    if !operand.is_numeric() {
+                        self.invalid_operand(&symbol, operand, *span);
+                    }
+                }
+                Type::Int
+            }
+            JackOperation::Less | JackOperation::Larger => {
+                for (operand, span) in &[(&left, left_span), (&right, right_span)] {
+                    if !operand.is_numeric() {
+                        self.invalid_operand(&symbol, operand, *span);
+                    }
+                }
+                Type::Boolean
+            }
+            // bitwise on numbers, logical on booleans
+            JackOperation::And | JackOperation::Or => match (&left, &right) {
+                (Type::Boolean, Type::Boolean) => Type::Boolean,
+                // could be either
+                (Type::Unknown, Type::Unknown) => Type::Unknown,
+                (Type::Unknown, operand) | (operand, Type::Unknown) => {
+                    if *operand == Type::Boolean {
+                        Type::Boolean
+                    } else {
+                        Type::Int
+                    }
+                }
+                (Type::Boolean, _) | (_, Type::Boolean) => {
+                    self.mismatch(&left, &right, right_span);
+                    Type::Unknown
+                }
+                _ => {
+                    for (operand, span) in &[(&left, left_span), (&right, right_span)] {
+                        if !operand.is_numeric() {
+                            self.invalid_operand(&symbol, operand, *span);
+                        }
+                    }
+                    Type::Int
+                }
+            },
+            JackOperation::Equal => {
+                if !right.is_assignable_to(&left) && !left.is_assignable_to(&right) {
+                    self.mismatch(&left, &right, right_span);
+                }
+                Type::Boolean
+            }
+        }
+    }
+
+    fn term_type(&mut self, term: &Term) -> Type {
+        match &term.kind {
+            TermKind::IntConstant(_) => Type::Int,
+            TermKind::StringConstant(_) => Type::Object("String".to_string()),
+            TermKind::True | TermKind::False => Type::Boolean,
+            TermKind::Null => Type::Null,
+            TermKind::This => Type::Object(self.class.name.name.clone()),
+            TermKind::Variable(name) => self.variable_type(&name.name).unwrap_or(Type::Unknown),
+            TermKind::ArrayAccess(name, index) => {
+                match self.variable_type(&name.name) {
+                    Some(array_type) => self.check_indexing(&array_type, index, name.span),
+                    None => {
+                        self.expression_type(index);
+                    }
+                }
+                Type::Unknown
+            }
+            TermKind::SubroutineCall(call) => self.call_type(call),
+            TermKind::Parenthesized(expression) => self.expression_type(expression),
+            TermKind::Unary(operation, operand) => {
+                let operand_type = self.term_type(operand);
+                match operation {
+                    UnaryOperation::Not if operand_type == Type::Boolean => Type::Boolean,
+                    UnaryOperation::Not if operand_type == Type::Unknown => Type::Unknown,
+                    _ => {
+                        if !operand_type.is_numeric() {
+                            self.invalid_operand(
+                                &operation.symbol().to_string(),
+                                &operand_type,
+                                operand.span,
+                            );
+                        }
+                        Type::Int
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the arguments of a call and returns the type of its result
+    fn call_type(&mut self, call: &SubroutineCall) -> Type {
+        let argument_types: Vec<(Type, Span)> = call
+            .arguments
+            .iter()
+            .map(|argument| (self.expression_type(argument), argument.span))
+            .collect();
+        let class_name = match &call.receiver {
+            None => self.class.name.name.clone(),
+            Some(receiver) => match self.variable_type(&receiver.name) {
+                Some(Type::Object(class_name)) => class_name,
+                // reported by the compiler
+                Some(_) => return Type::Unknown,
+                None => receiver.name.clone(),
+            },
+        };
+        let signature = match self.program.subroutine(&class_name, &call.name.name) {
+            Some(signature) => signature,
+            None => return Type::Unknown,
+        };
+        for ((argument_type, span), parameter_type) in
+            argument_types.iter().zip(&signature.parameter_types)
+        {
+            let parameter_type = Type::from(parameter_type);
+            if !argument_type.is_assignable_to(&parameter_type) {
+                self.mismatch(&parameter_type, argument_type, *span);
+            }
+        }
+        match &signature.return_type {
+            Some(return_type) => Type::from(return_type),
+            None => Type::Void,
+        }
+    }
+}
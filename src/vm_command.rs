@@ -0,0 +1,262 @@
+//! vm_command
+//! The commands of the Hack virtual machine and a parser for their textual form, as it is
+//! produced by `jack_compiler::JackCompiler`.
+
+use std::error::Error;
+use std::fmt;
+
+/// The virtual memory segments of the VM
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    /// Arguments of the current function
+    Argument,
+    /// Local variables of the current function
+    Local,
+    /// Static variables of the current file
+    Static,
+    /// Pseudo segment that holds the constants 0..32767
+    Constant,
+    /// The object that `pointer 0` points to
+    This,
+    /// The array that `pointer 1` points to
+    That,
+    /// The two cells holding the base addresses of `this` and `that`
+    Pointer,
+    /// Eight general purpose cells
+    Temp,
+}
+
+impl Segment {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "argument" => Some(Segment::Argument),
+            "local" => Some(Segment::Local),
+            "static" => Some(Segment::Static),
+            "constant" => Some(Segment::Constant),
+            "this" => Some(Segment::This),
+            "that" => Some(Segment::That),
+            "pointer" => Some(Segment::Pointer),
+            "temp" => Some(Segment::Temp),
+            _ => None,
+        }
+    }
+
+    /// The segment as it is written in VM code
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Segment::Argument => "argument",
+            Segment::Local => "local",
+            Segment::Static => "static",
+            Segment::Constant => "constant",
+            Segment::This => "this",
+            Segment::That => "that",
+            Segment::Pointer => "pointer",
+            Segment::Temp => "temp",
+        }
+    }
+}
+
+/// Arithmetic and logical commands, which operate on the top of the stack
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArithmeticCommand {
+    /// `x + y`
+    Add,
+    /// `x - y`
+    Sub,
+    /// `-y`
+    Neg,
+    /// `x == y`
+    Eq,
+    /// `x > y`
+    Gt,
+    /// `x < y`
+    Lt,
+    /// Bitwise `x & y`
+    And,
+    /// Bitwise `x | y`
+    Or,
+    /// Bitwise `!y`
+    Not,
+}
+
+impl ArithmeticCommand {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "add" => Some(ArithmeticCommand::Add),
+            "sub" => Some(ArithmeticCommand::Sub),
+            "neg" => Some(ArithmeticCommand::Neg),
+            "eq" => Some(ArithmeticCommand::Eq),
+            "gt" => Some(ArithmeticCommand::Gt),
+            "lt" => Some(ArithmeticCommand::Lt),
+            "and" => Some(ArithmeticCommand::And),
+            "or" => Some(ArithmeticCommand::Or),
+            "not" => Some(ArithmeticCommand::Not),
+            _ => None,
+        }
+    }
+
+    /// The command as it is written in VM code
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ArithmeticCommand::Add => "add",
+            ArithmeticCommand::Sub => "sub",
+            ArithmeticCommand::Neg => "neg",
+            ArithmeticCommand::Eq => "eq",
+            ArithmeticCommand::Gt => "gt",
+            ArithmeticCommand::Lt => "lt",
+            ArithmeticCommand::And => "and",
+            ArithmeticCommand::Or => "or",
+            ArithmeticCommand::Not => "not",
+        }
+    }
+}
+
+/// A single VM command
+#[derive(Debug, Clone, PartialEq)]
+pub enum VmCommand {
+    /// `push segment index`
+    Push(Segment, u16),
+    /// `pop segment index`
+    Pop(Segment, u16),
+    /// `add`, `sub`, `neg`, `eq`, `gt`, `lt`, `and`, `or` or `not`
+    Arithmetic(ArithmeticCommand),
+    /// `label name`
+    Label(String),
+    /// `goto name`
+    Goto(String),
+    /// `if-goto name`
+    IfGoto(String),
+    /// `function name nLocals`
+    Function(String, u16),
+    /// `call name nArgs`
+    Call(String, u16),
+    /// `return`
+    Return,
+}
+
+impl fmt::Display for VmCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmCommand::Push(segment, index) => write!(f, "push {} {}", segment.as_str(), index),
+            VmCommand::Pop(segment, index) => write!(f, "pop {} {}", segment.as_str(), index),
+            VmCommand::Arithmetic(command) => write!(f, "{}", command.as_str()),
+            VmCommand::Label(label) => write!(f, "label {}", label),
+            VmCommand::Goto(label) => write!(f, "goto {}", label),
+            VmCommand::IfGoto(label) => write!(f, "if-goto {}", label),
+            VmCommand::Function(name, n_locals) => write!(f, "function {} {}", name, n_locals),
+            VmCommand::Call(name, n_args) => write!(f, "call {} {}", name, n_args),
+            VmCommand::Return => write!(f, "return"),
+        }
+    }
+}
+
+/// A line of VM code that is not a valid command
+#[derive(Debug, Clone, PartialEq)]
+pub struct VmParseError {
+    /// 1-based line number
+    pub line: usize,
+    /// What is wrong with the line
+    pub message: String,
+}
+
+impl fmt::Display for VmParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for VmParseError {}
+
+/// Parses VM code. Comments starting with `//` and empty lines are ignored.
+pub fn parse(vm_code: &str) -> Result<Vec<VmCommand>, VmParseError> {
+    let mut commands = vec![];
+    for (line_index, line) in vm_code.lines().enumerate() {
+        let code = match line.find("//") {
+            Some(comment_start) => &line[..comment_start],
+            None => line,
+        };
+        let words: Vec<&str> = code.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        let command = parse_command(&words).map_err(|message| VmParseError {
+            line: line_index + 1,
+            message,
+        })?;
+        commands.push(command);
+    }
+    Ok(commands)
+}
+
+fn parse_command(words: &[&str]) -> Result<VmCommand, String> {
+    let expect_args = |n: usize| {
+        if words.len() == n + 1 {
+            Ok(())
+        } else {
+            Err(format!(
+                "`{}` takes {} argument(s), found {}",
+                words[0],
+                n,
+                words.len() - 1
+            ))
+        }
+    };
+    let number = |word: &str| {
+        word.parse::<u16>()
+            .map_err(|_| format!("expected a number, found `{}`", word))
+    };
+
+    if let Some(command) = ArithmeticCommand::from_str(words[0]) {
+        expect_args(0)?;
+        return Ok(VmCommand::Arithmetic(command));
+    }
+    match words[0] {
+        "push" | "pop" => {
+            expect_args(2)?;
+            let segment = Segment::from_str(words[1])
+                .ok_or_else(|| format!("unknown segment `{}`", words[1]))?;
+            let index = number(words[2])?;
+            let limit = match segment {
+                Segment::Pointer => 2,
+                Segment::Temp => 8,
+                Segment::Constant => 32768,
+                _ => u16::MAX as u32 + 1,
+            };
+            if u32::from(index) >= limit {
+                return Err(format!("index {} is out of range for segment `{}`", index, words[1]));
+            }
+            if words[0] == "push" {
+                Ok(VmCommand::Push(segment, index))
+            } else if segment == Segment::Constant {
+                Err("cannot pop to segment `constant`".to_string())
+            } else {
+                Ok(VmCommand::Pop(segment, index))
+            }
+        }
+        "label" => {
+            expect_args(1)?;
+            Ok(VmCommand::Label(words[1].to_string()))
+        }
+        "goto" => {
+            expect_args(1)?;
+            Ok(VmCommand::Goto(words[1].to_string()))
+        }
+        "if-goto" => {
+            expect_args(1)?;
+            Ok(VmCommand::IfGoto(words[1].to_string()))
+        }
+        "function" => {
+            expect_args(2)?;
+            Ok(VmCommand::Function(words[1].to_string(), number(words[2])?))
+        }
+        "call" => {
+            expect_args(2)?;
+            Ok(VmCommand::Call(words[1].to_string(), number(words[2])?))
+        }
+        "return" => {
+            expect_args(0)?;
+            Ok(VmCommand::Return)
+        }
+        other => Err(format!("unknown command `{}`", other)),
+    }
+}
@@ -0,0 +1,581 @@
+//! vm_interpreter
+//! Executes VM code directly, without translating it to Hack assembly first. The interpreter uses
+//! the standard memory map of the Hack platform: the pointers SP, LCL, ARG, THIS and THAT in
+//! RAM[0..5], temp in RAM[5..13], statics from RAM[16], the stack from RAM[256], the heap from
+//! RAM[2048], the screen from RAM[16384] and the keyboard in RAM[24576].
+
+use jack_os::{BadAddress, BuiltinResult, JackOs};
+use key_script::KeyEvent;
+use vm_command::{self, ArithmeticCommand, Segment, VmCommand, VmParseError};
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+/// Number of words of RAM
+pub const RAM_SIZE: usize = 32768;
+/// Address of the stack pointer
+pub const SP: usize = 0;
+/// Address of the base pointer of the `local` segment
+pub const LCL: usize = 1;
+/// Address of the base pointer of the `argument` segment
+pub const ARG: usize = 2;
+/// Address of the base pointer of the `this` segment
+pub const THIS: usize = 3;
+/// Address of the base pointer of the `that` segment
+pub const THAT: usize = 4;
+/// First address of the `temp` segment
+pub const TEMP_BASE: usize = 5;
+/// First address of the static variables
+pub const STATIC_BASE: usize = 16;
+/// Initial value of SP
+pub const STACK_BASE: usize = 256;
+/// First address of the heap
+pub const HEAP_BASE: usize = 2048;
+/// First address of the screen memory map
+pub const SCREEN: usize = 16384;
+/// Address of the keyboard register
+pub const KBD: usize = 24576;
+
+/// Everything that can go wrong while loading or running VM code
+#[derive(Debug, Clone, PartialEq)]
+pub enum VmError {
+    /// A file contains a line that is not a VM command
+    Parse {
+        /// Name of the file
+        file_name: String,
+        /// The parse error
+        error: VmParseError,
+    },
+    /// A function is defined more than once
+    DuplicateFunction {
+        /// Name of the function
+        name: String,
+    },
+    /// A `goto` or `if-goto` whose label does not exist in the function
+    UndefinedLabel {
+        /// The function containing the jump
+        function: String,
+        /// The label
+        label: String,
+    },
+    /// A call of a function that is not defined
+    UndefinedFunction {
+        /// Name of the called function
+        name: String,
+    },
+    /// The static variables of all files do not fit into RAM[16..256]
+    TooManyStatics,
+    /// The program has more commands than a return address on the stack can refer to
+    ProgramTooLarge,
+    /// A memory access outside of the RAM
+    AddressOutOfRange {
+        /// The accessed address
+        address: i32,
+    },
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmError::Parse { file_name, error } => write!(f, "{}: {}", file_name, error),
+            VmError::DuplicateFunction { name } => {
+                write!(f, "the function `{}` is defined more than once", name)
+            }
+            VmError::UndefinedLabel { function, label } => {
+                write!(f, "cannot find label `{}` in function `{}`", label, function)
+            }
+            VmError::UndefinedFunction { name } => write!(f, "cannot find function `{}`", name),
+            VmError::TooManyStatics => write!(f, "the static variables do not fit into RAM[16..256]"),
+            VmError::ProgramTooLarge => write!(f, "the program has more than 32767 commands"),
+            VmError::AddressOutOfRange { address } => {
+                write!(f, "address {} is outside of the RAM", address)
+            }
+        }
+    }
+}
+
+impl Error for VmError {}
+
+/// Whether the program can continue
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    /// There are more commands to execute
+    Running,
+    /// The program counter left the program, e.g. because `Sys.init` returned, or the program
+    /// called `Sys.halt` or `Sys.error` with the builtin OS enabled, or the halt function set with
+    /// `VmInterpreter::with_halt_function`
+    Halted,
+}
+
+/// A call of a function that is watched, see `VmInterpreter::with_watched_function`
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchedCall {
+    /// Name of the called function
+    pub name: String,
+    /// The arguments of the call
+    pub args: Vec<i16>,
+}
+
+/// A VM command with resolved jump targets and static addresses
+#[derive(Debug, Clone)]
+enum Instruction {
+    /// `static` indices are replaced by absolute addresses
+    Push(Segment, u16),
+    Pop(Segment, u16),
+    Arithmetic(ArithmeticCommand),
+    Label,
+    Goto(usize),
+    IfGoto(usize),
+    Function(u16),
+    Call {
+        name: String,
+        target: Option<usize>,
+        n_args: u16,
+    },
+    Return,
+}
+
+/// VmInterpreter struct
+pub struct VmInterpreter {
+    program: Vec<Instruction>,
+    functions: HashMap<String, usize>,
+    ram: Vec<i16>,
+    pc: usize,
+    steps: u64,
+    os: Option<JackOs>,
+    halted: bool,
+    key_events: Vec<KeyEvent>,
+    next_key_event: usize,
+    /// Addresses of the functions whose calls are recorded
+    watched_functions: HashSet<usize>,
+    watched_calls: Vec<WatchedCall>,
+    /// Address of the function whose call halts the program
+    halt_function: Option<usize>,
+}
+
+impl From<BadAddress> for VmError {
+    fn from(BadAddress(address): BadAddress) -> Self {
+        VmError::AddressOutOfRange { address }
+    }
+}
+
+impl VmInterpreter {
+    /// Loads the VM files of a program. `files` holds the file name without extension and the
+    /// VM code of each file.
+    pub fn from_vm_code(files: &[(String, String)]) -> Result<Self, VmError> {
+        let mut parsed_files = vec![];
+        for (file_name, vm_code) in files {
+            let commands = vm_command::parse(vm_code).map_err(|error| VmError::Parse {
+                file_name: file_name.clone(),
+                error,
+            })?;
+            parsed_files.push((file_name.clone(), commands));
+        }
+        VmInterpreter::new(&parsed_files)
+    }
+
+    /// Loads the VM files of a program. `files` holds the file name without extension and the
+    /// commands of each file. Execution starts at the first command of the first file, call
+    /// `bootstrap` to start with `Sys.init` instead.
+    pub fn new(files: &[(String, Vec<VmCommand>)]) -> Result<Self, VmError> {
+        // first pass: functions, labels and static variables
+        let mut functions = HashMap::new();
+        let mut labels = HashMap::new();
+        let mut static_bases = vec![];
+        let mut next_static = STATIC_BASE;
+        let mut address = 0;
+        for (file_name, commands) in files {
+            let mut current_function = file_name.clone();
+            let mut num_statics = 0;
+            for command in commands {
+                match command {
+                    VmCommand::Function(name, _) => {
+                        if functions.insert(name.clone(), address).is_some() {
+                            return Err(VmError::DuplicateFunction { name: name.clone() });
+                        }
+                        current_function = name.clone();
+                    }
+                    VmCommand::Label(label) => {
+                        labels.insert((current_function.clone(), label.clone()), address);
+                    }
+                    VmCommand::Push(Segment::Static, index) | VmCommand::Pop(Segment::Static, index) => {
+                        num_statics = num_statics.max(*index as usize + 1);
+                    }
+                    _ => {}
+                }
+                address += 1;
+            }
+            static_bases.push(next_static);
+            next_static += num_statics;
+        }
+        if next_static > STACK_BASE {
+            return Err(VmError::TooManyStatics);
+        }
+        if address > i16::MAX as usize {
+            return Err(VmError::ProgramTooLarge);
+        }
+
+        // second pass: resolve jumps, calls and static addresses
+        let mut program = vec![];
+        for ((file_name, commands), static_base) in files.iter().zip(static_bases) {
+            let mut current_function = file_name.clone();
+            for command in commands {
+                let resolve_label = |label: &String| {
+                    labels
+                        .get(&(current_function.clone(), label.clone()))
+                        .cloned()
+                        .ok_or_else(|| VmError::UndefinedLabel {
+                            function: current_function.clone(),
+                            label: label.clone(),
+                        })
+                };
+                let instruction = match command {
+                    VmCommand::Push(Segment::Static, index) => {
+                        Instruction::Push(Segment::Static, (static_base + *index as usize) as u16)
+                    }
+                    VmCommand::Pop(Segment::Static, index) => {
+                        Instruction::Pop(Segment::Static, (static_base + *index as usize) as u16)
+                    }
+                    VmCommand::Push(segment, index) => Instruction::Push(*segment, *index),
+                    VmCommand::Pop(segment, index) => Instruction::Pop(*segment, *index),
+                    VmCommand::Arithmetic(command) => Instruction::Arithmetic(*command),
+                    VmCommand::Label(_) => Instruction::Label,
+                    VmCommand::Goto(label) => Instruction::Goto(resolve_label(label)?),
+                    VmCommand::IfGoto(label) => Instruction::IfGoto(resolve_label(label)?),
+                    VmCommand::Function(name, n_locals) => {
+                        current_function = name.clone();
+                        Instruction::Function(*n_locals)
+                    }
+                    VmCommand::Call(name, n_args) => Instruction::Call {
+                        name: name.clone(),
+                        target: functions.get(name).cloned(),
+                        n_args: *n_args,
+                    },
+                    VmCommand::Return => Instruction::Return,
+                };
+                program.push(instruction);
+            }
+        }
+
+        Ok(VmInterpreter {
+            program,
+            functions,
+            ram: vec![0; RAM_SIZE],
+            pc: 0,
+            steps: 0,
+            os: None,
+            halted: false,
+            key_events: vec![],
+            next_key_event: 0,
+            watched_functions: HashSet::new(),
+            watched_calls: vec![],
+            halt_function: None,
+        })
+    }
+
+    /// Provides the functions of the Jack OS that the program does not define itself, see
+    /// `jack_os`
+    pub fn with_builtin_os(mut self) -> Self {
+        self.os = Some(JackOs::new());
+        self
+    }
+
+    /// Records every call of the function `name` with its arguments, see `watched_calls`.
+    /// Functions that the program does not define are ignored.
+    pub fn with_watched_function(mut self, name: &str) -> Self {
+        if let Some(target) = self.functions.get(name) {
+            self.watched_functions.insert(*target);
+        }
+        self
+    }
+
+    /// Halts the program when it calls the function `name` instead of calling it, e.g. for a
+    /// `Sys.halt` that loops forever. Functions that the program does not define are ignored.
+    pub fn with_halt_function(mut self, name: &str) -> Self {
+        self.halt_function = self.functions.get(name).cloned();
+        self
+    }
+
+    /// Sets the keyboard register according to `key_events` (see `key_script`) while the
+    /// program runs. The events must be sorted by step.
+    pub fn with_key_events(mut self, key_events: Vec<KeyEvent>) -> Self {
+        self.key_events = key_events;
+        self.next_key_event = 0;
+        self
+    }
+
+    /// State of the builtin OS, if it is enabled
+    pub fn os(&self) -> Option<&JackOs> {
+        self.os.as_ref()
+    }
+
+    /// The calls of the watched functions so far, in the order they were made
+    pub fn watched_calls(&self) -> &[WatchedCall] {
+        &self.watched_calls
+    }
+
+    /// Sets SP to 256 and calls `Sys.init`, like the bootstrap code of the VM translator.
+    /// The program halts when `Sys.init` returns. If the program has no `Sys.init` but the
+    /// builtin OS is enabled, `Main.main` is called instead, which is all the OS's `Sys.init`
+    /// would do.
+    pub fn bootstrap(&mut self) -> Result<(), VmError> {
+        self.ram[SP] = STACK_BASE as i16;
+        if self.os.is_some() && !self.has_function("Sys.init") {
+            self.call("Main.main", 0, -1)
+        } else {
+            self.call("Sys.init", 0, -1)
+        }
+    }
+
+    /// Continues execution at the first command of the function `name` without setting up a
+    /// call frame, like the VM emulator does with `Sys.init` when it loads a program
+    pub fn start_at(&mut self, name: &str) -> Result<(), VmError> {
+        match self.functions.get(name) {
+            Some(target) => {
+                self.pc = *target;
+                Ok(())
+            }
+            None => Err(VmError::UndefinedFunction { name: name.to_string() }),
+        }
+    }
+
+    /// The RAM
+    pub fn ram(&self) -> &[i16] {
+        &self.ram
+    }
+
+    /// The RAM, e.g. to set up the stack or to simulate a key press
+    pub fn ram_mut(&mut self) -> &mut [i16] {
+        &mut self.ram
+    }
+
+    /// Index of the next command to execute
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Number of commands executed so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Whether a function with the given name is defined
+    pub fn has_function(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    fn status(&self) -> Status {
+        if !self.halted && self.pc < self.program.len() {
+            Status::Running
+        } else {
+            Status::Halted
+        }
+    }
+
+    fn read(&self, address: i32) -> Result<i16, VmError> {
+        if address < 0 || address as usize >= RAM_SIZE {
+            return Err(VmError::AddressOutOfRange { address });
+        }
+        Ok(self.ram[address as usize])
+    }
+
+    fn write(&mut self, address: i32, value: i16) -> Result<(), VmError> {
+        if address < 0 || address as usize >= RAM_SIZE {
+            return Err(VmError::AddressOutOfRange { address });
+        }
+        self.ram[address as usize] = value;
+        Ok(())
+    }
+
+    fn push(&mut self, value: i16) -> Result<(), VmError> {
+        let sp = i32::from(self.ram[SP]);
+        self.write(sp, value)?;
+        self.ram[SP] = self.ram[SP].wrapping_add(1);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<i16, VmError> {
+        self.ram[SP] = self.ram[SP].wrapping_sub(1);
+        let sp = i32::from(self.ram[SP]);
+        self.read(sp)
+    }
+
+    /// The RAM address of a cell of a segment. `static` indices are already absolute.
+    fn address(&self, segment: Segment, index: u16) -> i32 {
+        let index = i32::from(index);
+        match segment {
+            Segment::Local => i32::from(self.ram[LCL]) + index,
+            Segment::Argument => i32::from(self.ram[ARG]) + index,
+            Segment::This => i32::from(self.ram[THIS]) + index,
+            Segment::That => i32::from(self.ram[THAT]) + index,
+            Segment::Pointer => THIS as i32 + index,
+            Segment::Temp => TEMP_BASE as i32 + index,
+            Segment::Static => index,
+            Segment::Constant => unreachable!("the constant segment has no address"),
+        }
+    }
+
+    /// Saves the frame of the caller and jumps to a function
+    fn call(&mut self, name: &str, n_args: u16, return_address: i16) -> Result<(), VmError> {
+        let target = match self.functions.get(name) {
+            Some(target) => *target,
+            None => return Err(VmError::UndefinedFunction { name: name.to_string() }),
+        };
+        self.push(return_address)?;
+        for pointer in &[LCL, ARG, THIS, THAT] {
+            let value = self.ram[*pointer];
+            self.push(value)?;
+        }
+        self.ram[ARG] = self.ram[SP].wrapping_sub(5 + n_args as i16);
+        self.ram[LCL] = self.ram[SP];
+        self.pc = target;
+        Ok(())
+    }
+
+    fn return_from_call(&mut self) -> Result<(), VmError> {
+        let frame = i32::from(self.ram[LCL]);
+        let return_address = self.read(frame - 5)?;
+        let value = self.pop()?;
+        let arg = i32::from(self.ram[ARG]);
+        self.write(arg, value)?;
+        self.ram[SP] = self.ram[ARG].wrapping_add(1);
+        self.ram[THAT] = self.read(frame - 1)?;
+        self.ram[THIS] = self.read(frame - 2)?;
+        self.ram[ARG] = self.read(frame - 3)?;
+        self.ram[LCL] = self.read(frame - 4)?;
+        // a negative return address cannot be part of the program and halts it
+        self.pc = if return_address < 0 {
+            self.program.len()
+        } else {
+            return_address as usize
+        };
+        Ok(())
+    }
+
+    /// Executes a single command. Labels are not counted as commands, like in the VM emulator
+    /// of the course, they are skipped on the way to the next command.
+    pub fn step(&mut self) -> Result<Status, VmError> {
+        while let Some(Instruction::Label) = self.program.get(self.pc) {
+            self.pc += 1;
+        }
+        if self.status() == Status::Halted {
+            return Ok(Status::Halted);
+        }
+        while let Some(event) = self.key_events.get(self.next_key_event) {
+            if event.step > self.steps {
+                break;
+            }
+            self.ram[KBD] = event.key;
+            self.next_key_event += 1;
+        }
+        let instruction = self.program[self.pc].clone();
+        self.pc += 1;
+        self.steps += 1;
+        match instruction {
+            Instruction::Push(Segment::Constant, value) => self.push(value as i16)?,
+            Instruction::Push(segment, index) => {
+                let value = self.read(self.address(segment, index))?;
+                self.push(value)?;
+            }
+            Instruction::Pop(segment, index) => {
+                let value = self.pop()?;
+                self.write(self.address(segment, index), value)?;
+            }
+            Instruction::Arithmetic(command) => self.arithmetic(command)?,
+            Instruction::Label => {}
+            Instruction::Goto(target) => self.pc = target,
+            Instruction::IfGoto(target) => {
+                if self.pop()? != 0 {
+                    self.pc = target;
+                }
+            }
+            Instruction::Function(n_locals) => {
+                for _ in 0..n_locals {
+                    self.push(0)?;
+                }
+            }
+            Instruction::Call { name, target, n_args } => match target {
+                Some(target) if self.halt_function == Some(target) => self.halted = true,
+                Some(target) => {
+                    if self.watched_functions.contains(&target) {
+                        let args = self.args(n_args)?.to_vec();
+                        self.watched_calls.push(WatchedCall { name: name.clone(), args });
+                    }
+                    let return_address = self.pc as i16;
+                    self.call(&name, n_args, return_address)?;
+                }
+                None => self.call_builtin(&name, n_args)?,
+            },
+            Instruction::Return => self.return_from_call()?,
+        }
+        Ok(self.status())
+    }
+
+    /// The `n_args` arguments of a call on top of the stack
+    fn args(&self, n_args: u16) -> Result<&[i16], VmError> {
+        let sp = i32::from(self.ram[SP]);
+        let first_arg = sp - i32::from(n_args);
+        if first_arg < 0 || sp as usize > RAM_SIZE {
+            return Err(VmError::AddressOutOfRange { address: first_arg });
+        }
+        Ok(&self.ram[first_arg as usize..sp as usize])
+    }
+
+    /// Calls a function of the builtin OS with the arguments on top of the stack
+    fn call_builtin(&mut self, name: &str, n_args: u16) -> Result<(), VmError> {
+        let os = match self.os.as_mut() {
+            Some(os) if JackOs::provides(name) => os,
+            _ => return Err(VmError::UndefinedFunction { name: name.to_string() }),
+        };
+        let sp = i32::from(self.ram[SP]);
+        let first_arg = sp - i32::from(n_args);
+        if first_arg < 0 || sp as usize > RAM_SIZE {
+            return Err(VmError::AddressOutOfRange { address: first_arg });
+        }
+        let args = self.ram[first_arg as usize..sp as usize].to_vec();
+        match os.call(name, &args, &mut self.ram, self.steps)? {
+            BuiltinResult::Return(value) => {
+                self.ram[SP] = first_arg as i16;
+                self.push(value)?;
+            }
+            // execute the call again in the next step
+            BuiltinResult::Block => self.pc -= 1,
+            BuiltinResult::Halt => self.halted = true,
+        }
+        Ok(())
+    }
+
+    fn arithmetic(&mut self, command: ArithmeticCommand) -> Result<(), VmError> {
+        let truth = |b: bool| if b { -1 } else { 0 };
+        let result = match command {
+            ArithmeticCommand::Neg => self.pop()?.wrapping_neg(),
+            ArithmeticCommand::Not => !self.pop()?,
+            _ => {
+                let y = self.pop()?;
+                let x = self.pop()?;
+                match command {
+                    ArithmeticCommand::Add => x.wrapping_add(y),
+                    ArithmeticCommand::Sub => x.wrapping_sub(y),
+                    ArithmeticCommand::And => x & y,
+                    ArithmeticCommand::Or => x | y,
+                    ArithmeticCommand::Eq => truth(x == y),
+                    ArithmeticCommand::Gt => truth(x > y),
+                    ArithmeticCommand::Lt => truth(x < y),
+                    ArithmeticCommand::Neg | ArithmeticCommand::Not => unreachable!(),
+                }
+            }
+        };
+        self.push(result)
+    }
+
+    /// Executes commands until the program halts or `max_steps` commands have been executed
+    pub fn run(&mut self, max_steps: u64) -> Result<Status, VmError> {
+        for _ in 0..max_steps {
+            if self.step()? == Status::Halted {
+                return Ok(Status::Halted);
+            }
+        }
+        Ok(self.status())
+    }
+}
@@ -0,0 +1,249 @@
+//! vm_translator
+//! Translates VM commands into Hack assembly. The VM files of a program are translated one after
+//! another into a single assembly program, optionally preceded by bootstrap code that sets up the
+//! stack and calls `Sys.init`.
+
+use vm_command::{ArithmeticCommand, Segment, VmCommand};
+
+use std::collections::{BTreeSet, HashSet};
+
+/// Translates the VM files of a program into one Hack assembly program.
+/// `files` holds the file name without extension (used to name static variables) and the
+/// commands of each file.
+pub fn translate(files: &[(String, Vec<VmCommand>)], bootstrap: bool) -> String {
+    let mut translator = VmTranslator::new();
+    if bootstrap {
+        translator.write_bootstrap();
+    }
+    for (file_name, commands) in files {
+        translator.translate_file(file_name, commands);
+    }
+    translator.asm_output
+}
+
+/// The functions that the program calls but does not define, sorted by name. The bootstrap
+/// code calls `Sys.init`. Calls of these functions would jump to addresses the assembler
+/// allocates as variables.
+pub fn unresolved_calls(files: &[(String, Vec<VmCommand>)], bootstrap: bool) -> Vec<String> {
+    let commands = || files.iter().flat_map(|(_, commands)| commands);
+    let defined: HashSet<&str> = commands()
+        .filter_map(|command| match command {
+            VmCommand::Function(name, _) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    let mut called: BTreeSet<&str> = commands()
+        .filter_map(|command| match command {
+            VmCommand::Call(name, _) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    if bootstrap {
+        called.insert("Sys.init");
+    }
+    called
+        .into_iter()
+        .filter(|name| !defined.contains(name))
+        .map(str::to_string)
+        .collect()
+}
+
+/// VmTranslator struct
+pub struct VmTranslator {
+    asm_output: String,
+    file_name: String,
+    current_function: String,
+    label_num: usize,
+}
+
+impl Default for VmTranslator {
+    fn default() -> Self {
+        VmTranslator::new()
+    }
+}
+
+impl VmTranslator {
+    /// Constructor
+    pub fn new() -> Self {
+        VmTranslator {
+            asm_output: String::new(),
+            file_name: String::new(),
+            current_function: String::new(),
+            label_num: 0,
+        }
+    }
+
+    /// The assembly code written so far
+    pub fn asm_output(&self) -> &str {
+        &self.asm_output
+    }
+
+    /// Sets SP to 256 and calls `Sys.init`
+    pub fn write_bootstrap(&mut self) {
+        self.asm_output += "// bootstrap\n@256\nD=A\n@SP\nM=D\n";
+        self.current_function = "bootstrap".to_string();
+        self.write_call("Sys.init", 0);
+    }
+
+    /// Translates the commands of the file `file_name` (without extension)
+    pub fn translate_file(&mut self, file_name: &str, commands: &[VmCommand]) {
+        self.file_name = file_name.to_string();
+        self.current_function = file_name.to_string();
+        for command in commands {
+            self.asm_output += &format!("// {}\n", command);
+            self.translate_command(command);
+        }
+    }
+
+    fn translate_command(&mut self, command: &VmCommand) {
+        match command {
+            VmCommand::Push(segment, index) => self.write_push(*segment, *index),
+            VmCommand::Pop(segment, index) => self.write_pop(*segment, *index),
+            VmCommand::Arithmetic(command) => self.write_arithmetic(*command),
+            VmCommand::Label(label) => {
+                self.asm_output += &format!("({}${})\n", self.current_function, label);
+            }
+            VmCommand::Goto(label) => {
+                self.asm_output += &format!("@{}${}\n0;JMP\n", self.current_function, label);
+            }
+            VmCommand::IfGoto(label) => {
+                self.asm_output += &format!(
+                    "@SP\nAM=M-1\nD=M\n@{}${}\nD;JNE\n",
+                    self.current_function, label
+                );
+            }
+            VmCommand::Function(name, n_locals) => {
+                self.current_function = name.clone();
+                self.asm_output += &format!("({})\n", name);
+                for _ in 0..*n_locals {
+                    self.asm_output += "@SP\nAM=M+1\nA=A-1\nM=0\n";
+                }
+            }
+            VmCommand::Call(name, n_args) => self.write_call(name, *n_args),
+            VmCommand::Return => self.write_return(),
+        }
+    }
+
+    /// Pushes the D register onto the stack
+    fn push_d(&mut self) {
+        self.asm_output += "@SP\nAM=M+1\nA=A-1\nM=D\n";
+    }
+
+    /// The register holding the base address of a segment, for segments that can move
+    fn base_pointer(segment: Segment) -> Option<&'static str> {
+        match segment {
+            Segment::Local => Some("LCL"),
+            Segment::Argument => Some("ARG"),
+            Segment::This => Some("THIS"),
+            Segment::That => Some("THAT"),
+            _ => None,
+        }
+    }
+
+    /// The address of a cell in the `static`, `pointer` or `temp` segment
+    fn fixed_address(&self, segment: Segment, index: u16) -> String {
+        match segment {
+            Segment::Static => format!("{}.{}", self.file_name, index),
+            Segment::Pointer => (3 + index).to_string(),
+            Segment::Temp => (5 + index).to_string(),
+            _ => unreachable!("segment {} has no fixed address", segment.as_str()),
+        }
+    }
+
+    fn write_push(&mut self, segment: Segment, index: u16) {
+        if segment == Segment::Constant {
+            self.asm_output += &format!("@{}\nD=A\n", index);
+        } else if let Some(base) = VmTranslator::base_pointer(segment) {
+            self.asm_output += &format!("@{}\nD=A\n@{}\nA=D+M\nD=M\n", index, base);
+        } else {
+            let address = self.fixed_address(segment, index);
+            self.asm_output += &format!("@{}\nD=M\n", address);
+        }
+        self.push_d();
+    }
+
+    fn write_pop(&mut self, segment: Segment, index: u16) {
+        if let Some(base) = VmTranslator::base_pointer(segment) {
+            self.asm_output += &format!(
+                "@{}\nD=A\n@{}\nD=D+M\n@R13\nM=D\n@SP\nAM=M-1\nD=M\n@R13\nA=M\nM=D\n",
+                index, base
+            );
+        } else {
+            let address = self.fixed_address(segment, index);
+            self.asm_output += &format!("@SP\nAM=M-1\nD=M\n@{}\nM=D\n", address);
+        }
+    }
+
+    fn write_arithmetic(&mut self, command: ArithmeticCommand) {
+        let binary = |operation: &str| format!("@SP\nAM=M-1\nD=M\nA=A-1\n{}\n", operation);
+        // D = x - y, M = -1, and M = 0 unless D fits `jump`
+        let set_result = |jump: &str, num: usize| {
+            format!(
+                "M=-1\n@CMP_TRUE.{num}\nD;{jump}\n@SP\nA=M-1\nM=0\n(CMP_TRUE.{num})\n",
+                num = num,
+                jump = jump
+            )
+        };
+        let equality =
+            |num: usize| format!("@SP\nAM=M-1\nD=M\nA=A-1\nD=M-D\n{}", set_result("JEQ", num));
+        // x - y overflows if x and y have different signs, so it is only computed for equal
+        // signs. Otherwise the sign of x decides and D is set to 1 or -1.
+        let ordering = |jump: &str, num: usize| {
+            format!(
+                "@SP\nAM=M-1\nD=M\n@R13\nM=D\n@SP\nA=M-1\nD=M\n@CMP_X_NEG.{num}\nD;JLT\n\
+                 @R13\nD=M\n@CMP_SAME_SIGN.{num}\nD;JGE\nD=1\n@CMP_DONE.{num}\n0;JMP\n\
+                 (CMP_X_NEG.{num})\n@R13\nD=M\n@CMP_SAME_SIGN.{num}\nD;JLT\nD=-1\n@CMP_DONE.{num}\n0;JMP\n\
+                 (CMP_SAME_SIGN.{num})\n@R13\nD=M\n@SP\nA=M-1\nD=M-D\n\
+                 (CMP_DONE.{num})\n@SP\nA=M-1\n{result}",
+                num = num,
+                result = set_result(jump, num)
+            )
+        };
+        let code = match command {
+            ArithmeticCommand::Add => binary("M=D+M"),
+            ArithmeticCommand::Sub => binary("M=M-D"),
+            ArithmeticCommand::And => binary("M=D&M"),
+            ArithmeticCommand::Or => binary("M=D|M"),
+            ArithmeticCommand::Neg => "@SP\nA=M-1\nM=-M\n".to_string(),
+            ArithmeticCommand::Not => "@SP\nA=M-1\nM=!M\n".to_string(),
+            ArithmeticCommand::Eq => equality(self.next_label_num()),
+            ArithmeticCommand::Gt => ordering("JGT", self.next_label_num()),
+            ArithmeticCommand::Lt => ordering("JLT", self.next_label_num()),
+        };
+        self.asm_output += &code;
+    }
+
+    fn next_label_num(&mut self) -> usize {
+        self.label_num += 1;
+        self.label_num
+    }
+
+    fn write_call(&mut self, name: &str, n_args: u16) {
+        let label_num = self.next_label_num();
+        let return_label = format!("{}$ret.{}", self.current_function, label_num);
+        self.asm_output += &format!("@{}\nD=A\n", return_label);
+        self.push_d();
+        for pointer in &["LCL", "ARG", "THIS", "THAT"] {
+            self.asm_output += &format!("@{}\nD=M\n", pointer);
+            self.push_d();
+        }
+        // ARG = SP - 5 - nArgs, LCL = SP
+        self.asm_output += &format!(
+            "@SP\nD=M\n@{}\nD=D-A\n@ARG\nM=D\n@SP\nD=M\n@LCL\nM=D\n@{}\n0;JMP\n({})\n",
+            u32::from(n_args) + 5,
+            name,
+            return_label
+        );
+    }
+
+    fn write_return(&mut self) {
+        // R13 = frame, R14 = return address
+        self.asm_output += "@LCL\nD=M\n@R13\nM=D\n@5\nA=D-A\nD=M\n@R14\nM=D\n";
+        // *ARG = pop(), SP = ARG + 1
+        self.asm_output += "@SP\nAM=M-1\nD=M\n@ARG\nA=M\nM=D\n@ARG\nD=M+1\n@SP\nM=D\n";
+        for pointer in &["THAT", "THIS", "ARG", "LCL"] {
+            self.asm_output += &format!("@R13\nAM=M-1\nD=M\n@{}\nM=D\n", pointer);
+        }
+        self.asm_output += "@R14\nA=M\n0;JMP\n";
+    }
+}
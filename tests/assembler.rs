@@ -0,0 +1,99 @@
+//! The Hack assembler: instructions, symbols and the errors for invalid lines.
+
+extern crate jack_compiler;
+
+use jack_compiler::hack_assembler::{assemble, to_hack_text, AsmError};
+
+/// The `Add.asm` program of project 6
+const ADD: &str = "
+// Computes R0 = 2 + 3  (R0 refers to RAM[0])
+@2
+D=A
+@3
+D=D+A
+@0
+M=D
+";
+
+/// A number in binary, the groups of digits are separated by spaces for readability
+fn binary(digits: &str) -> u16 {
+    u16::from_str_radix(&digits.replace(' ', ""), 2).unwrap()
+}
+
+#[test]
+fn add_program_of_project_6() {
+    let hack = to_hack_text(&assemble(ADD).unwrap());
+    assert_eq!(
+        hack,
+        "0000000000000010\n\
+         1110110000010000\n\
+         0000000000000011\n\
+         1110000010010000\n\
+         0000000000000000\n\
+         1110001100001000\n"
+    );
+}
+
+#[test]
+fn computations_destinations_and_jumps() {
+    let instructions = assemble("AMD=M+1;JMP\nMD=D|M\n0;JEQ\nA=!A;JNE\nDM=1+D").unwrap();
+    assert_eq!(
+        instructions,
+        [
+            binary("111 1 110111 111 111"),
+            binary("111 1 010101 011 000"),
+            binary("111 0 101010 000 010"),
+            binary("111 0 110001 100 101"),
+            binary("111 0 011111 011 000"),
+        ]
+    );
+}
+
+#[test]
+fn labels_and_variables() {
+    let code = "
+        @i      // first variable, 16
+        M=1
+    (LOOP)
+        @i
+        D=M
+        @END
+        D;JGT
+        @sum    // second variable, 17
+        @LOOP
+        0;JMP
+    (END)
+        @R13
+        @SCREEN
+        @KBD
+        @THAT
+    ";
+    let instructions = assemble(code).unwrap();
+    assert_eq!(instructions[0], 16);
+    assert_eq!(instructions[2], 16);
+    assert_eq!(instructions[4], 9);
+    assert_eq!(instructions[6], 17);
+    assert_eq!(instructions[7], 2);
+    assert_eq!(instructions[9..], [13, 16384, 24576, 4]);
+}
+
+#[test]
+fn invalid_lines() {
+    let error = |code: &str| -> AsmError { assemble(code).unwrap_err() };
+    assert_eq!(error("@1\nD=X").line, 2);
+    assert_eq!(error("D=X").message, "invalid computation `X`");
+    assert_eq!(error("DD=A").message, "invalid destination `DD`");
+    assert_eq!(error("0;JUMP").message, "invalid jump `JUMP`");
+    assert_eq!(
+        error("@32768").message,
+        "constant 32768 is larger than 32767"
+    );
+    assert_eq!(error("@1x").message, "invalid symbol `1x`");
+    assert_eq!(
+        error("(A)\n(A)"),
+        AsmError {
+            line: 2,
+            message: "the symbol `A` is defined more than once".to_string()
+        }
+    );
+}
@@ -0,0 +1,86 @@
+//! Calls without a class or object, like `draw()`, are resolved against the subroutines of the
+//! class: methods get the current object as first argument, functions and constructors do not.
+
+extern crate jack_compiler;
+
+mod common;
+
+use common::{class, compile_class};
+use jack_compiler::CompileError;
+
+/// A method, a function and a constructor of `Main`
+const SUBROUTINES: &str = "
+    method void draw(int n) {
+        return;
+    }
+    function int helper(int n) {
+        return n;
+    }
+    constructor Main new() {
+        return this;
+    }";
+
+/// `Main` with a field, the given subroutines and `SUBROUTINES`
+fn class_with(subroutines: &str) -> String {
+    class(
+        "Main",
+        &format!("    field int x;\n    {}{}", subroutines, SUBROUTINES),
+    )
+}
+
+#[test]
+fn methods_get_the_current_object() {
+    let code = class_with("method void run() {\n        do draw(2);\n        return;\n    }");
+    let vm_code = compile_class(&code).unwrap();
+    assert!(
+        vm_code.contains("push pointer 0\npush constant 2\ncall Main.draw 2\n"),
+        "{}",
+        vm_code
+    );
+}
+
+#[test]
+fn functions_and_constructors_do_not() {
+    let code = class_with(
+        "method void run() {
+        var Main m;
+        do helper(3);
+        let m = new();
+        return;
+    }
+    function void main() {
+        do helper(4);
+        return;
+    }",
+    );
+    let vm_code = compile_class(&code).unwrap();
+    for expected in &[
+        "pop pointer 0\npush constant 3\ncall Main.helper 1\n",
+        "pop temp 0\ncall Main.new 0\npop local 0\n",
+        "function Main.main 0\npush constant 4\ncall Main.helper 1\n",
+    ] {
+        assert!(vm_code.contains(expected), "{}: {}", expected, vm_code);
+    }
+}
+
+#[test]
+fn methods_cannot_be_called_from_functions() {
+    let code = class_with("function void main() {\n        do draw(1);\n        return;\n    }");
+    match compile_class(&code).unwrap_err().as_slice() {
+        [CompileError::MethodCallInFunction { name, function, .. }] => {
+            assert_eq!((name.as_str(), function.as_str()), ("draw", "main"));
+        }
+        other => panic!("expected a method call in a function, got {:?}", other),
+    }
+}
+
+#[test]
+fn unknown_subroutines() {
+    let code = class_with("function void main() {\n        do erase();\n        return;\n    }");
+    match compile_class(&code).unwrap_err().as_slice() {
+        [CompileError::UndefinedSubroutine {
+            class_name, name, ..
+        }] => assert_eq!((class_name.as_str(), name.as_str()), ("Main", "erase")),
+        other => panic!("expected an undefined subroutine, got {:?}", other),
+    }
+}
@@ -0,0 +1,153 @@
+//! The builtin OS of the VM interpreter, driven by compiled Jack programs: what they print,
+//! draw and allocate, and the error codes of `Sys.error`.
+
+extern crate jack_compiler;
+
+mod common;
+
+use common::{class, compile_class};
+use jack_compiler::key_script::KeyEvent;
+use jack_compiler::vm_interpreter::{Status, VmInterpreter, HEAP_BASE, SCREEN, STATIC_BASE};
+
+/// Runs `Main.main` with the given body and key events until it halts
+fn run_main(body: &str, key_events: Vec<KeyEvent>) -> VmInterpreter {
+    let declarations = format!(
+        "    static int result;\n    function void main() {{\n{}\n        return;\n    }}",
+        body
+    );
+    let vm_code = compile_class(&class("Main", &declarations)).unwrap();
+    let mut interpreter = VmInterpreter::from_vm_code(&[("Main".to_string(), vm_code)])
+        .unwrap()
+        .with_builtin_os()
+        .with_key_events(key_events);
+    interpreter.bootstrap().unwrap();
+    assert_eq!(interpreter.run(1_000_000), Ok(Status::Halted));
+    interpreter
+}
+
+fn printed(body: &str) -> String {
+    run_main(body, vec![])
+        .os()
+        .unwrap()
+        .output_text()
+        .to_string()
+}
+
+fn sys_error(body: &str) -> Option<i16> {
+    run_main(body, vec![]).os().unwrap().sys_error()
+}
+
+#[test]
+fn math() {
+    assert_eq!(
+        printed(
+            "do Output.printInt(Math.multiply(-7, 6));
+             do Output.printChar(32);
+             do Output.printInt(Math.divide(-43, 5));
+             do Output.printChar(32);
+             do Output.printInt(Math.sqrt(99));
+             do Output.printChar(32);
+             do Output.printInt(Math.abs(-3) + Math.min(2, 9) + Math.max(2, 9));"
+        ),
+        "-42 -8 9 14"
+    );
+    assert_eq!(sys_error("do Math.divide(1, 0);"), Some(3));
+    assert_eq!(sys_error("do Math.sqrt(-1);"), Some(4));
+}
+
+#[test]
+fn strings() {
+    assert_eq!(
+        printed(
+            "var String s;
+             let s = String.new(10);
+             do s.setInt(-305);
+             do s.appendChar(65);
+             do s.eraseLastChar();
+             do s.appendChar(String.doubleQuote());
+             do Output.printString(s);
+             do Output.printInt(s.length());
+             do s.setCharAt(0, 48);
+             do Output.printInt(s.intValue());"
+        ),
+        "-305\"5305"
+    );
+    assert_eq!(
+        sys_error(
+            "var String s;
+             let s = String.new(1);
+             do s.appendChar(65);
+             do s.appendChar(66);"
+        ),
+        Some(17)
+    );
+}
+
+#[test]
+fn output_lines_and_backspace() {
+    assert_eq!(
+        printed(
+            "do Output.printString(\"ab\");
+             do Output.backSpace();
+             do Output.println();
+             do Output.printChar(String.newLine());
+             do Output.printString(\"c\");"
+        ),
+        "a\n\nc"
+    );
+}
+
+#[test]
+fn memory_and_arrays() {
+    let interpreter = run_main(
+        "var Array a, b;
+         let a = Array.new(3);
+         let b = Array.new(2);
+         let a[2] = 7;
+         do Memory.poke(20000, Memory.peek(a + 2) + 1);
+         do Memory.poke(20001, a);
+         do Memory.poke(20002, b);
+         do a.dispose();
+         let a = Array.new(3);
+         do Memory.poke(20003, a);",
+        vec![],
+    );
+    let ram = interpreter.ram();
+    assert_eq!(ram[20000], 8);
+    assert!(ram[20001] as usize >= HEAP_BASE);
+    assert!(ram[20002] >= ram[20001] + 3);
+    // first fit reuses the disposed block
+    assert_eq!(ram[20003], ram[20001]);
+}
+
+#[test]
+fn screen() {
+    let interpreter = run_main(
+        "do Screen.drawPixel(17, 1);
+         do Screen.drawLine(0, 10, 31, 10);
+         do Screen.setColor(false);
+         do Screen.drawPixel(5, 10);",
+        vec![],
+    );
+    let ram = interpreter.ram();
+    // 32 words per row, bit 0 is the leftmost pixel
+    assert_eq!(ram[SCREEN + 32 + 1], 1 << 1);
+    assert_eq!(ram[SCREEN + 10 * 32] as u16, !(1u16 << 5));
+    assert_eq!(ram[SCREEN + 10 * 32 + 1], -1);
+    assert_eq!(sys_error("do Screen.drawPixel(512, 0);"), Some(7));
+}
+
+#[test]
+fn keyboard() {
+    let key_events = vec![
+        KeyEvent { step: 0, key: 0 },
+        KeyEvent { step: 50, key: 75 },
+        KeyEvent { step: 100, key: 0 },
+    ];
+    let interpreter = run_main(
+        "while (Keyboard.keyPressed() = 0) {}
+         let result = Keyboard.keyPressed();",
+        key_events,
+    );
+    assert_eq!(interpreter.ram()[STATIC_BASE], 75);
+}
@@ -0,0 +1,79 @@
+//! Helpers shared by the integration tests: Jack classes written as source text, compiled with
+//! the default error limit, and temporary directories of input files for the compiler binary.
+
+#![allow(dead_code)]
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use jack_compiler::compile_error::DEFAULT_MAX_ERRORS;
+use jack_compiler::jack_tokenizer::tokenize;
+use jack_compiler::{compile, CompileError};
+
+/// The source of a class with the given variable and subroutine declarations
+pub fn class(name: &str, declarations: &str) -> String {
+    format!("class {} {{\n{}\n}}\n", name, declarations)
+}
+
+/// Compiles the source of a single class
+pub fn compile_class(code: &str) -> Result<String, Vec<CompileError>> {
+    compile(&tokenize(code.to_string(), 0), 0, DEFAULT_MAX_ERRORS)
+}
+
+/// A directory of input files below the temp directory, removed again when it is dropped
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Creates the directory `jack_compiler_<name>_<process id>` with the given files
+    pub fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("jack_compiler_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        for (file_name, contents) in files {
+            fs::write(path.join(file_name), contents).unwrap();
+        }
+        TempDir { path }
+    }
+
+    /// The directory itself
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A file in the directory
+    pub fn join(&self, file_name: &str) -> PathBuf {
+        self.path.join(file_name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Runs the compiler binary with the given arguments
+pub fn jack_compiler<I, S>(arguments: I) -> Output
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    Command::new(env!("CARGO_BIN_EXE_jack_compiler"))
+        .args(arguments)
+        .output()
+        .unwrap()
+}
+
+/// Whether the process succeeded, its stdout and its stderr
+pub fn outcome(output: &Output) -> (bool, String, String) {
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
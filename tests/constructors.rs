@@ -0,0 +1,171 @@
+//! Constructors must end with `return this;`. With `--implicit-return-this` the compiler inserts
+//! it instead, and the lints warn about constructors returning other values and about functions
+//! and methods named `new`.
+
+extern crate jack_compiler;
+
+mod common;
+
+use common::{class, compile_class, jack_compiler, TempDir};
+use jack_compiler::jack_ast::FunctionKind;
+use jack_compiler::jack_lints::lint_class;
+use jack_compiler::jack_tokenizer::tokenize;
+use jack_compiler::{CompileError, JackCompiler, JackParser};
+
+/// A class `Point` with a field and a constructor `new` with the given body
+fn point_with_constructor(body: &str) -> String {
+    let constructor = format!(
+        "    field int x;\n    constructor Point new(int ax) {{\n{}\n    }}",
+        body
+    );
+    class("Point", &constructor)
+}
+
+fn lints(code: &str) -> Vec<CompileError> {
+    let tokens = tokenize(code.to_string(), 0);
+    let class = JackParser::new(&tokens, 0).parse_class().unwrap();
+    lint_class(&class)
+}
+
+#[test]
+fn constructors_must_return_this() {
+    let code = point_with_constructor("let x = ax;\nif (ax) { return this; }");
+    match compile_class(&code).unwrap_err().as_slice() {
+        [CompileError::MissingReturnThis { constructor, .. }] => assert_eq!(constructor, "new"),
+        other => panic!("expected a missing return this, got {:?}", other),
+    }
+
+    let code = point_with_constructor("let x = ;");
+    let errors = compile_class(&code).unwrap_err();
+    assert!(
+        matches!(errors.as_slice(), [CompileError::UnexpectedToken { .. }]),
+        "{:?}",
+        errors
+    );
+}
+
+#[test]
+fn constructors_must_return_their_class() {
+    for (return_type, found) in &[("void", "void"), ("int", "int"), ("Line", "Line")] {
+        let code = class(
+            "Point",
+            &format!("    constructor {} new() {{\n        return;\n    }}", return_type),
+        );
+        match compile_class(&code).unwrap_err().as_slice() {
+            [CompileError::BadConstructorReturnType {
+                class_name,
+                found: found_type,
+                span,
+            }] => {
+                assert_eq!(class_name, "Point");
+                assert_eq!(found_type, found);
+                assert_eq!(span.len, return_type.len());
+            }
+            other => panic!("expected a bad constructor return type, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn constructors_cannot_return_without_a_value() {
+    let code = point_with_constructor("if (ax) { return; }\nreturn this;");
+    match compile_class(&code).unwrap_err().as_slice() {
+        [CompileError::MissingReturnThis { constructor, span }] => {
+            assert_eq!(constructor, "new");
+            assert_eq!(&code[span.offset..span.offset + span.len], "return;");
+        }
+        other => panic!("expected a missing return this, got {:?}", other),
+    }
+
+    // not even when `return this` is inserted at the end
+    let tokens = tokenize(point_with_constructor("if (ax) { return; }"), 0);
+    let class = JackParser::new(&tokens, 0).parse_class().unwrap();
+    let errors = JackCompiler::new(&class)
+        .with_implicit_return_this(true)
+        .compile_class()
+        .unwrap_err();
+    assert!(
+        matches!(errors.as_slice(), [CompileError::MissingReturnThis { .. }]),
+        "{:?}",
+        errors
+    );
+}
+
+#[test]
+fn implicit_return_this_is_inserted_at_the_end() {
+    let tokens = tokenize(point_with_constructor("let x = ax;"), 0);
+    let class = JackParser::new(&tokens, 0).parse_class().unwrap();
+    let vm_code = JackCompiler::new(&class)
+        .with_implicit_return_this(true)
+        .compile_class()
+        .unwrap();
+    assert!(
+        vm_code.ends_with("push argument 0\npop this 0\npush pointer 0\nreturn\n"),
+        "{}",
+        vm_code
+    );
+
+    // constructors that return on every path are left alone
+    let tokens = tokenize(point_with_constructor("return this;"), 0);
+    let class = JackParser::new(&tokens, 0).parse_class().unwrap();
+    let vm_code = JackCompiler::new(&class)
+        .with_implicit_return_this(true)
+        .compile_class()
+        .unwrap();
+    assert_eq!(vm_code.matches("return\n").count(), 1, "{}", vm_code);
+}
+
+#[test]
+fn implicit_return_this_flag() {
+    let directory = TempDir::new(
+        "constructors",
+        &[("Point.jack", &point_with_constructor("let x = ax;"))],
+    );
+    let input = directory.join("Point.jack");
+    let output = jack_compiler([&input]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("the constructor `new` does not end every path with `return this;`"));
+    let output = jack_compiler([input.as_os_str(), "--implicit-return-this".as_ref()]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn constructors_returning_other_values() {
+    let code = point_with_constructor(
+        "if (ax) { return (this); }\nwhile (ax) { return null; }\nreturn Point.new(1);",
+    );
+    let warnings = lints(&code);
+    assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    assert!(warnings.iter().all(|warning| matches!(
+        warning,
+        CompileError::ConstructorReturnsOther { constructor, .. } if constructor == "new"
+    )));
+    assert!(warnings[0].span().offset < warnings[1].span().offset);
+
+    let code = point_with_constructor("if (ax) { return; }\nreturn this;");
+    match lints(&code).as_slice() {
+        [CompileError::ConstructorReturnsOther { span, .. }] => {
+            assert_eq!(&code[span.offset..span.offset + span.len], "return;")
+        }
+        other => panic!("expected a constructor returning something else, got {:?}", other),
+    }
+}
+
+#[test]
+fn functions_and_methods_named_new() {
+    let code = "class Stack {\n    function Stack new() {\n        return null;\n    }\n}\n";
+    match lints(code).as_slice() {
+        [CompileError::NewIsNotAConstructor {
+            class_name, kind, ..
+        }] => {
+            assert_eq!(class_name, "Stack");
+            assert_eq!(*kind, FunctionKind::Jfunction);
+        }
+        other => panic!("expected a new that is not a constructor, got {:?}", other),
+    }
+    // the OS declares `Array.new` as a function
+    let code =
+        "class Array {\n    function Array new(int size) {\n        return null;\n    }\n}\n";
+    assert_eq!(lints(code), []);
+}
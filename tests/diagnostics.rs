@@ -0,0 +1,116 @@
+//! Diagnostics are rendered like rustc renders them: the gutter is as wide as the line number,
+//! the caret is under the span, and spans at the end of a file still get a caret.
+
+extern crate jack_compiler;
+
+mod common;
+
+use common::compile_class;
+use jack_compiler::diagnostics::{render, Diagnostic, SourceMap};
+use jack_compiler::jack_tokenizer::Span;
+
+/// Renders the first error of compiling `code` as the file `Main.jack`, without colors
+fn render_first_error(code: &str) -> String {
+    let mut source_map = SourceMap::new();
+    source_map.add("Main.jack".to_string(), code.to_string());
+    let errors = compile_class(code).unwrap_err();
+    render(&Diagnostic::from(&errors[0]), &source_map, false)
+}
+
+#[test]
+fn caret_under_the_span() {
+    let code =
+        "class Main {\n    function void main() {\n        let x = 1;\n        return;\n    }\n}\n";
+    assert_eq!(
+        render_first_error(code),
+        "error: cannot find variable `x` in this scope
+ --> Main.jack:3:13
+  |
+3 |         let x = 1;
+  |             ^
+  = help: declare it with `var`, as a parameter, or as a `field` or `static` of the class
+"
+    );
+}
+
+#[test]
+fn gutter_is_as_wide_as_the_line_number() {
+    let code = format!(
+        "class Main {{{}\n    function void main() {{\n        do Output.printInt(abc);\n        return;\n    }}\n}}\n",
+        "\n".repeat(97)
+    );
+    assert_eq!(
+        render_first_error(&code),
+        "error: cannot find variable `abc` in this scope
+   --> Main.jack:100:28
+    |
+100 |         do Output.printInt(abc);
+    |                            ^^^
+    = help: declare it with `var`, as a parameter, or as a `field` or `static` of the class
+"
+    );
+}
+
+#[test]
+fn multi_digit_line_numbers() {
+    let mut source_map = SourceMap::new();
+    let content: String = (1..=12).map(|line| format!("line {}\n", line)).collect();
+    source_map.add("Lines.jack".to_string(), content);
+    let span = |line: usize| Span {
+        file_id: 0,
+        offset: 0,
+        line,
+        column: 6,
+        len: line.to_string().len(),
+    };
+    let diagnostic = Diagnostic::warning("nine").with_span(span(9));
+    assert_eq!(
+        render(&diagnostic, &source_map, false),
+        "warning: nine
+ --> Lines.jack:9:6
+  |
+9 | line 9
+  |      ^
+"
+    );
+    let diagnostic = Diagnostic::warning("twelve")
+        .with_span(span(12))
+        .with_note("the note is indented like the gutter");
+    assert_eq!(
+        render(&diagnostic, &source_map, false),
+        "warning: twelve
+  --> Lines.jack:12:6
+   |
+12 | line 12
+   |      ^^
+   = note: the note is indented like the gutter
+"
+    );
+}
+
+#[test]
+fn span_at_the_end_of_the_file() {
+    // the file ends without a new line, the caret is behind the last character
+    assert_eq!(
+        render_first_error("class Main {\n    function void main() {\n        return;"),
+        "error: unexpected end of file, expected `let`, `if`, `while`, `do`, `return` or `}`
+ --> Main.jack:3:16
+  |
+3 |         return;
+  |                ^
+"
+    );
+    assert_eq!(
+        render_first_error(""),
+        "error: unexpected end of file, expected `class`\n --> Main.jack:1:1\n  |\n1 | \n  | ^\n"
+    );
+}
+
+#[test]
+fn diagnostics_without_a_span() {
+    let diagnostic = Diagnostic::error("no input files").with_help("pass a .jack file");
+    assert_eq!(
+        render(&diagnostic, &SourceMap::new(), false),
+        "error: no input files\n = help: pass a .jack file\n"
+    );
+}
@@ -0,0 +1,95 @@
+//! Identifiers consist of letters, digits and `_` and do not start with a digit. Other
+//! characters are only allowed in strings and comments.
+
+extern crate jack_compiler;
+
+use jack_compiler::compile_error::DEFAULT_MAX_ERRORS;
+use jack_compiler::jack_tokenizer::{tokenize, SpannedToken, Token};
+use jack_compiler::{compile, CompileError};
+
+fn errors(spanned_tokens: &[SpannedToken]) -> Vec<&CompileError> {
+    spanned_tokens
+        .iter()
+        .filter_map(|spanned_token| spanned_token.error.as_ref())
+        .collect()
+}
+
+fn compile_errors(code: &str) -> Vec<CompileError> {
+    compile(&tokenize(code.to_string(), 0), 0, DEFAULT_MAX_ERRORS).unwrap_err()
+}
+
+const CLASS: &str = "class Main {\n    function void main() {\n        return;\n    }\n}\n";
+
+#[test]
+fn valid_identifiers() {
+    let spanned_tokens = tokenize("_ok a1 _1 classy CamelCase".to_string(), 0);
+    assert!(errors(&spanned_tokens).is_empty());
+    assert_eq!(
+        spanned_tokens[3].token,
+        Token::Identifier("classy".to_string())
+    );
+}
+
+#[test]
+fn malformed_words() {
+    let spanned_tokens = tokenize("1abc foo$bar _ok".to_string(), 0);
+    match errors(&spanned_tokens).as_slice() {
+        [CompileError::InvalidIdentifier { name, .. }, CompileError::IllegalCharacter {
+            character: '$',
+            span,
+        }] => {
+            assert_eq!(name, "1abc");
+            assert_eq!((span.offset, span.len), (8, 1));
+        }
+        other => panic!("expected two lexical errors, got {:?}", other),
+    }
+}
+
+#[test]
+fn non_ascii_is_allowed_in_strings_and_comments_only() {
+    let spanned_tokens = tokenize("\"café\" // café\n/* café */".to_string(), 0);
+    assert_eq!(spanned_tokens.len(), 1);
+    assert_eq!(
+        spanned_tokens[0].token,
+        Token::StringConstant("café".to_string())
+    );
+    assert!(errors(&spanned_tokens).is_empty());
+
+    let spanned_tokens = tokenize("let café = 1;".to_string(), 0);
+    match errors(&spanned_tokens).as_slice() {
+        [CompileError::IllegalCharacter { character, span }] => {
+            assert_eq!(*character, 'é');
+            assert_eq!((span.offset, span.column, span.len), (7, 8, 2));
+        }
+        other => panic!("expected an illegal character, got {:?}", other),
+    }
+}
+
+#[test]
+fn illegal_characters_are_reported_once() {
+    let code = CLASS.replace("return;", "var int a$;\n        return;");
+    match compile_errors(&code).as_slice() {
+        [CompileError::IllegalCharacter { character: '$', .. }] => {}
+        other => panic!("expected an illegal character, got {:?}", other),
+    }
+}
+
+#[test]
+fn lexical_errors_after_the_class_are_reported() {
+    match compile_errors(&format!("{}$", CLASS)).as_slice() {
+        [CompileError::IllegalCharacter {
+            character: '$',
+            span,
+        }] => assert_eq!(span.line, 6),
+        other => panic!("expected an illegal character, got {:?}", other),
+    }
+    match compile_errors(&format!("{}2x", CLASS)).as_slice() {
+        [CompileError::InvalidIdentifier { name, .. }] => assert_eq!(name, "2x"),
+        other => panic!("expected an invalid identifier, got {:?}", other),
+    }
+    match compile_errors(&format!("{}x 40000 #", CLASS)).as_slice() {
+        [CompileError::UnexpectedToken { .. }, CompileError::IntegerOutOfRange { .. }, CompileError::IllegalCharacter { character: '#', .. }] =>
+            {}
+        other => panic!("expected junk after the class, got {:?}", other),
+    }
+}
@@ -0,0 +1,96 @@
+//! Integer constants range from 0 to 32767. `-32768` is the one negative constant, it is folded
+//! into a single constant because `32768` alone is out of range, but only when the minus is written
+//! directly before the digits.
+
+extern crate jack_compiler;
+
+mod common;
+
+use common::{class, compile_class};
+use jack_compiler::jack_tokenizer::{tokenize, Token};
+use jack_compiler::jack_xml::class_to_xml;
+use jack_compiler::{CompileError, JackParser};
+
+/// A class whose function `main` returns `expression`
+fn class_returning(expression: &str) -> String {
+    let function = format!(
+        "    function int main() {{\n        return {};\n    }}",
+        expression
+    );
+    class("Main", &function)
+}
+
+fn out_of_range_values(code: &str) -> Vec<String> {
+    match compile_class(code) {
+        Err(errors) => errors
+            .iter()
+            .map(|error| match error {
+                CompileError::IntegerOutOfRange { value, .. } => value.clone(),
+                other => panic!("expected an integer out of range, got {:?}", other),
+            })
+            .collect(),
+        Ok(_) => panic!("{} must not compile", code),
+    }
+}
+
+#[test]
+fn largest_constant() {
+    let tokens = tokenize("0 32767".to_string(), 0);
+    assert_eq!(tokens[1].token, Token::IntConstant(32767));
+    assert!(tokens.iter().all(|token| token.error.is_none()));
+}
+
+#[test]
+fn constants_out_of_range() {
+    let tokens = tokenize("32768 99999999999999999999".to_string(), 0);
+    match (&tokens[0].error, &tokens[1].error) {
+        (
+            Some(CompileError::IntegerOutOfRange { value: first, span }),
+            Some(CompileError::IntegerOutOfRange { value: second, .. }),
+        ) => {
+            assert_eq!(first, "32768");
+            assert_eq!((span.offset, span.len), (0, 5));
+            assert_eq!(second, "99999999999999999999");
+        }
+        other => panic!("expected two integers out of range, got {:?}", other),
+    }
+
+    assert_eq!(out_of_range_values(&class_returning("40000")), ["40000"]);
+    assert_eq!(
+        out_of_range_values(&class_returning("1 - 32768")),
+        ["32768"]
+    );
+    assert_eq!(out_of_range_values(&class_returning("-(32768)")), ["32768"]);
+    // only a minus directly before the digits is folded
+    assert_eq!(out_of_range_values(&class_returning("- 32768")), ["32768"]);
+    assert_eq!(out_of_range_values(&class_returning("-/**/32768")), ["32768"]);
+}
+
+#[test]
+fn smallest_constant_is_folded() {
+    let code = class_returning("-32768");
+    let vm_code = compile_class(&code).unwrap();
+    assert!(
+        vm_code.contains("push constant 32767\nnot\nreturn\n"),
+        "{}",
+        vm_code
+    );
+
+    let code = class_returning("-32767");
+    let vm_code = compile_class(&code).unwrap();
+    assert!(
+        vm_code.contains("push constant 32767\nneg\nreturn\n"),
+        "{}",
+        vm_code
+    );
+}
+
+#[test]
+fn folded_constant_keeps_its_parse_tree() {
+    let tokens = tokenize(class_returning("-32768"), 0);
+    let class = JackParser::new(&tokens, 0).parse_class().unwrap();
+    let xml = class_to_xml(&class);
+    let words: Vec<&str> = xml.split_whitespace().collect();
+    let expected = "<symbol> - </symbol> <term> <integerConstant> 32768 </integerConstant> </term>";
+    assert!(words.join(" ").contains(expected), "{}", xml);
+}
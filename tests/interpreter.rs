@@ -0,0 +1,172 @@
+//! The VM interpreter: arithmetic, memory segments, branching, calls and the errors of
+//! programs that cannot be loaded or run.
+
+extern crate jack_compiler;
+
+use jack_compiler::vm_interpreter::{Status, VmError, VmInterpreter, ARG, LCL, SP, STATIC_BASE};
+
+fn interpreter(files: &[(&str, &str)]) -> Result<VmInterpreter, VmError> {
+    let files: Vec<(String, String)> = files
+        .iter()
+        .map(|(name, code)| (name.to_string(), code.to_string()))
+        .collect();
+    VmInterpreter::from_vm_code(&files)
+}
+
+/// Runs the code of a single file from its first command with SP at 256 and returns the RAM
+fn run_file(code: &str) -> Vec<i16> {
+    let mut interpreter = interpreter(&[("Test", code)]).unwrap();
+    interpreter.ram_mut()[SP] = 256;
+    assert_eq!(interpreter.run(10_000), Ok(Status::Halted));
+    interpreter.ram().to_vec()
+}
+
+#[test]
+fn arithmetic_wraps_around() {
+    let ram = run_file(
+        "push constant 32767\npush constant 1\nadd\n\
+         push constant 5\nneg\npush constant 3\nsub\n\
+         push constant 12\npush constant 10\nand\n\
+         push constant 12\npush constant 10\nor\n\
+         push constant 0\nnot\n",
+    );
+    assert_eq!(ram[SP], 261);
+    assert_eq!(ram[256..261], [-32768, -8, 8, 14, -1]);
+}
+
+#[test]
+fn comparisons() {
+    let ram = run_file(
+        "push constant 1\nneg\npush constant 1\nlt\n\
+         push constant 1\npush constant 1\neq\n\
+         push constant 2\npush constant 3\ngt\n",
+    );
+    assert_eq!(ram[256..259], [-1, -1, 0]);
+}
+
+#[test]
+fn segments() {
+    let ram = run_file(
+        "push constant 3000\npop pointer 0\npush constant 4000\npop pointer 1\n\
+         push constant 7\npop this 2\npush constant 8\npop that 3\n\
+         push constant 9\npop temp 7\npush constant 10\npop static 1\n\
+         push this 2\npush that 3\nadd\npush temp 7\nadd\npush static 1\nadd\n",
+    );
+    assert_eq!((ram[3], ram[4]), (3000, 4000));
+    assert_eq!((ram[3002], ram[4003], ram[12]), (7, 8, 9));
+    assert_eq!(ram[STATIC_BASE + 1], 10);
+    assert_eq!(ram[256], 34);
+}
+
+#[test]
+fn loops_with_labels() {
+    // sums 1 to 10 in temp 0, counting down temp 1
+    let ram = run_file(
+        "push constant 10\npop temp 1\n\
+         label LOOP\n\
+         push temp 0\npush temp 1\nadd\npop temp 0\n\
+         push temp 1\npush constant 1\nsub\npop temp 1\n\
+         push temp 1\nif-goto LOOP\n\
+         push temp 0\n",
+    );
+    assert_eq!(ram[256], 55);
+}
+
+#[test]
+fn calls_and_returns_between_files() {
+    let mut interpreter = interpreter(&[
+        (
+            "Sys",
+            "function Sys.init 0\npush constant 6\npush constant 7\ncall Math.mul 2\n\
+             pop static 0\nlabel HALT\ngoto HALT\n",
+        ),
+        (
+            "Math",
+            "function Math.mul 1\n\
+             label LOOP\n\
+             push argument 1\nif-goto BODY\npush local 0\nreturn\n\
+             label BODY\n\
+             push local 0\npush argument 0\nadd\npop local 0\n\
+             push argument 1\npush constant 1\nsub\npop argument 1\ngoto LOOP\n",
+        ),
+    ])
+    .unwrap();
+    interpreter.bootstrap().unwrap();
+    assert_eq!(interpreter.run(10_000), Ok(Status::Running));
+    let ram = interpreter.ram();
+    assert_eq!(ram[STATIC_BASE], 42);
+    // only the frame of Sys.init is left
+    assert_eq!((ram[SP], ram[LCL], ram[ARG]), (261, 261, 256));
+}
+
+#[test]
+fn statics_are_per_file() {
+    let mut interpreter = interpreter(&[
+        (
+            "A",
+            "push constant 1\npop static 0\npush constant 2\npop static 1\n",
+        ),
+        ("B", "push constant 3\npop static 0\n"),
+    ])
+    .unwrap();
+    interpreter.ram_mut()[SP] = 256;
+    interpreter.run(100).unwrap();
+    assert_eq!(interpreter.ram()[STATIC_BASE..STATIC_BASE + 3], [1, 2, 3]);
+}
+
+#[test]
+fn labels_are_local_to_their_function() {
+    let result = interpreter(&[(
+        "Main",
+        "function Main.f 0\nlabel L\nreturn\nfunction Main.g 0\ngoto L\n",
+    )]);
+    assert_eq!(
+        result.err(),
+        Some(VmError::UndefinedLabel {
+            function: "Main.g".to_string(),
+            label: "L".to_string()
+        })
+    );
+}
+
+#[test]
+fn programs_that_cannot_run() {
+    let duplicate = interpreter(&[("A", "function A.f 0\nreturn\nfunction A.f 0\nreturn\n")]);
+    assert_eq!(
+        duplicate.err(),
+        Some(VmError::DuplicateFunction {
+            name: "A.f".to_string()
+        })
+    );
+
+    let mut undefined = interpreter(&[("A", "call B.g 0\n")]).unwrap();
+    assert_eq!(
+        undefined.run(10),
+        Err(VmError::UndefinedFunction {
+            name: "B.g".to_string()
+        })
+    );
+
+    let mut out_of_range = interpreter(&[("A", "push this 0\n")]).unwrap();
+    out_of_range.ram_mut()[SP] = 256;
+    out_of_range.ram_mut()[3] = -1;
+    assert_eq!(
+        out_of_range.run(10),
+        Err(VmError::AddressOutOfRange { address: -1 })
+    );
+
+    assert!(matches!(
+        interpreter(&[("A", "push nowhere 1\n")]),
+        Err(VmError::Parse { .. })
+    ));
+}
+
+#[test]
+fn steps_are_counted_without_labels() {
+    let mut interpreter =
+        interpreter(&[("A", "label X\npush constant 1\nlabel Y\npush constant 2\n")]).unwrap();
+    interpreter.ram_mut()[SP] = 256;
+    assert_eq!(interpreter.run(1), Ok(Status::Running));
+    assert_eq!(interpreter.run(5), Ok(Status::Halted));
+    assert_eq!(interpreter.steps(), 2);
+}
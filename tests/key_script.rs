@@ -0,0 +1,100 @@
+//! Key scripts: one step count and key per line, keys as codes, names or quoted characters,
+//! comments after `//`, and errors that name the line.
+
+extern crate jack_compiler;
+
+use jack_compiler::key_script::{key_code, parse, KeyScriptError};
+
+fn events(script: &str) -> Vec<(u64, i16)> {
+    parse(script)
+        .unwrap()
+        .iter()
+        .map(|event| (event.step, event.key))
+        .collect()
+}
+
+fn error(script: &str) -> KeyScriptError {
+    parse(script).unwrap_err()
+}
+
+#[test]
+fn keys_as_codes_names_and_characters() {
+    let script = "0 0\n10 75\n20 left\n30 F12\n40 'q'\n50 ' '\n60 newline\n70 none\n";
+    assert_eq!(
+        events(script),
+        [
+            (0, 0),
+            (10, 75),
+            (20, 130),
+            (30, 152),
+            (40, 113),
+            (50, 32),
+            (60, 128),
+            (70, 0)
+        ]
+    );
+    assert_eq!(key_code("PageDown"), Some(137));
+    assert_eq!(key_code("f0"), None);
+    assert_eq!(key_code("f13"), None);
+}
+
+#[test]
+fn comments_and_blank_lines() {
+    let script =
+        "// press and release\n\n   \n100 up // the up arrow\n200 '/' // a slash\n// 300 'x'\n";
+    assert_eq!(events(script), [(100, 131), (200, 47)]);
+    assert_eq!(events(""), []);
+}
+
+#[test]
+fn step_counts_must_not_decrease() {
+    // equal steps are allowed, the last event wins
+    assert_eq!(
+        events("10 'a'\n10 'b'\n20 0"),
+        [(10, 97), (10, 98), (20, 0)]
+    );
+    assert_eq!(
+        error("10 'a'\n// comment\n5 'b'\n"),
+        KeyScriptError {
+            line: 3,
+            message: "step 5 comes before the previous event at step 10".to_string(),
+        }
+    );
+}
+
+#[test]
+fn malformed_lines() {
+    let message = |script: &str| {
+        let error = error(script);
+        assert_eq!(error.line, 2, "{}", script);
+        error.message
+    };
+    assert_eq!(message("0 0\n100"), "expected a step count and a key");
+    assert_eq!(
+        message("0 0\n100 left right"),
+        "expected a step count and a key"
+    );
+    assert_eq!(message("0 0\nsoon 'a'"), "invalid step count `soon`");
+    assert_eq!(message("0 0\n-1 'a'"), "invalid step count `-1`");
+    assert_eq!(
+        message("0 0\n100 40000"),
+        "invalid key code `40000`, the range is 0 to 32767"
+    );
+    assert_eq!(
+        message("0 0\n100 'ab'"),
+        "`'ab'` is not a single printable ASCII character"
+    );
+    assert_eq!(
+        message("0 0\n100 'é'"),
+        "`'é'` is not a single printable ASCII character"
+    );
+}
+
+#[test]
+fn unknown_key_names() {
+    let error = error("100 leftarrow");
+    assert_eq!(error.line, 1);
+    assert_eq!(error.message, "unknown key `leftarrow`");
+    assert_eq!(error.to_string(), "line 1: unknown key `leftarrow`");
+    assert_eq!(parse("100 q").unwrap_err().message, "unknown key `q`");
+}
@@ -0,0 +1,217 @@
+//! Regression tests for the lexer: comments, string literals and lexical errors, in particular
+//! comment markers inside strings and comments at the end of a file.
+
+extern crate jack_compiler;
+
+use jack_compiler::compile_error::DEFAULT_MAX_ERRORS;
+use jack_compiler::jack_tokenizer::{tokenize, Keyword, SpannedToken, Token};
+use jack_compiler::{compile, CompileError};
+
+fn tokens(code: &str) -> Vec<Token> {
+    tokenize(code.to_string(), 0)
+        .into_iter()
+        .map(|spanned_token| spanned_token.token)
+        .collect()
+}
+
+fn errors(spanned_tokens: &[SpannedToken]) -> Vec<&CompileError> {
+    spanned_tokens
+        .iter()
+        .filter_map(|spanned_token| spanned_token.error.as_ref())
+        .collect()
+}
+
+fn string(s: &str) -> Token {
+    Token::StringConstant(s.to_string())
+}
+
+fn identifier(name: &str) -> Token {
+    Token::Identifier(name.to_string())
+}
+
+#[test]
+fn line_comment_marker_inside_string() {
+    assert_eq!(
+        tokens(r#"do f("http://example.com");"#),
+        vec![
+            Token::Keyword(Keyword::Do),
+            identifier("f"),
+            Token::Symbol('('),
+            string("http://example.com"),
+            Token::Symbol(')'),
+            Token::Symbol(';'),
+        ]
+    );
+}
+
+#[test]
+fn block_comment_markers_inside_string() {
+    assert_eq!(
+        tokens(r#"let s = "a/*b*/c"; let t = "/*"; x"#),
+        vec![
+            Token::Keyword(Keyword::Let),
+            identifier("s"),
+            Token::Symbol('='),
+            string("a/*b*/c"),
+            Token::Symbol(';'),
+            Token::Keyword(Keyword::Let),
+            identifier("t"),
+            Token::Symbol('='),
+            string("/*"),
+            Token::Symbol(';'),
+            identifier("x"),
+        ]
+    );
+}
+
+#[test]
+fn line_comment_on_last_line_without_newline() {
+    assert_eq!(tokens("x // the end"), vec![identifier("x")]);
+    assert_eq!(tokens("x //"), vec![identifier("x")]);
+}
+
+#[test]
+fn string_delimiter_inside_comments() {
+    assert_eq!(
+        tokens("a // \"b\n/* \" */ c"),
+        vec![identifier("a"), identifier("c")]
+    );
+}
+
+#[test]
+fn block_comments() {
+    assert_eq!(
+        tokens("a /** doc\n * comment */ b /**/ c /* // */ d /*/ e */ f"),
+        vec![
+            identifier("a"),
+            identifier("b"),
+            identifier("c"),
+            identifier("d"),
+            identifier("f"),
+        ]
+    );
+}
+
+#[test]
+fn comments_next_to_tokens_and_division() {
+    assert_eq!(
+        tokens("a/b//c\nd/*e*/f"),
+        vec![
+            identifier("a"),
+            Token::Symbol('/'),
+            identifier("b"),
+            identifier("d"),
+            identifier("f"),
+        ]
+    );
+}
+
+#[test]
+fn spans_after_comments() {
+    let spanned_tokens = tokenize("/* one\ntwo */ x // three\n  \"y\"".to_string(), 7);
+    assert_eq!(spanned_tokens.len(), 2);
+    let x = &spanned_tokens[0].span;
+    assert_eq!(
+        (x.file_id, x.offset, x.line, x.column, x.len),
+        (7, 14, 2, 8, 1)
+    );
+    let y = &spanned_tokens[1].span;
+    assert_eq!((y.offset, y.line, y.column, y.len), (27, 3, 3, 3));
+}
+
+#[test]
+fn unterminated_string_ends_with_its_line() {
+    let spanned_tokens = tokenize("let s = \"abc;\r\nlet t = 1;".to_string(), 0);
+    let found: Vec<&Token> = spanned_tokens.iter().map(|t| &t.token).collect();
+    assert_eq!(found[3], &string("abc;"));
+    assert_eq!(
+        found[4..],
+        [
+            &Token::Keyword(Keyword::Let),
+            &identifier("t"),
+            &Token::Symbol('='),
+            &Token::IntConstant(1),
+            &Token::Symbol(';')
+        ]
+    );
+    match errors(&spanned_tokens).as_slice() {
+        [CompileError::UnterminatedString { span }] => {
+            assert_eq!((span.offset, span.len, span.line), (8, 5, 1));
+        }
+        other => panic!("expected an unterminated string, got {:?}", other),
+    }
+}
+
+#[test]
+fn unterminated_string_at_end_of_file() {
+    let spanned_tokens = tokenize("x \"abc".to_string(), 0);
+    assert_eq!(spanned_tokens[1].token, string("abc"));
+    match errors(&spanned_tokens).as_slice() {
+        [CompileError::UnterminatedString { span }] => assert_eq!((span.offset, span.len), (2, 4)),
+        other => panic!("expected an unterminated string, got {:?}", other),
+    }
+}
+
+#[test]
+fn unterminated_comment_takes_the_rest_of_the_file() {
+    let spanned_tokens = tokenize("x /* y \"z\"\n w".to_string(), 0);
+    assert_eq!(spanned_tokens.len(), 2);
+    match errors(&spanned_tokens).as_slice() {
+        [CompileError::UnterminatedComment { span }] => assert_eq!((span.offset, span.len), (2, 2)),
+        other => panic!("expected an unterminated comment, got {:?}", other),
+    }
+}
+
+#[test]
+fn strings_with_comment_markers_compile_unchanged() {
+    let code = r#"
+class Main {
+    function void main() {
+        do Output.printString("http://example.com"); /* block */
+        do Output.printString("a/*b*/c");
+        return;
+    }
+} // comment on the last line without a newline"#;
+    let vm_code = compile(&tokenize(code.to_string(), 0), 0, DEFAULT_MAX_ERRORS).unwrap();
+    assert!(vm_code.contains("push constant 18\ncall String.new 1\n"));
+    assert!(vm_code.contains("push constant 7\ncall String.new 1\n"));
+}
+
+#[test]
+fn lexical_errors_are_reported_by_the_compiler() {
+    let code =
+        "class Main {\n    function void main() {\n        do f(\"abc);\n        return;\n    }\n}";
+    match compile(&tokenize(code.to_string(), 0), 0, DEFAULT_MAX_ERRORS) {
+        Err(errors) => assert!(errors
+            .iter()
+            .any(|error| matches!(error, CompileError::UnterminatedString { .. }))),
+        Ok(_) => panic!("an unterminated string must not compile"),
+    }
+}
+
+#[test]
+fn tokens_after_the_class_are_reported() {
+    let class = "class Main {\n    function void main() {\n        return;\n    }\n}\n";
+    let code = format!("{}/* not closed", class);
+    match compile(&tokenize(code, 0), 0, DEFAULT_MAX_ERRORS) {
+        Err(errors) => match errors.as_slice() {
+            [CompileError::UnterminatedComment { span }] => assert_eq!(span.line, 6),
+            other => panic!("expected an unterminated comment, got {:?}", other),
+        },
+        Ok(_) => panic!("an unterminated comment after the class must not compile"),
+    }
+
+    let code = format!("{}class Other {{}} \"abc", class);
+    match compile(&tokenize(code, 0), 0, DEFAULT_MAX_ERRORS) {
+        Err(errors) => match errors.as_slice() {
+            [CompileError::UnexpectedToken {
+                found, expected, ..
+            }, CompileError::UnterminatedString { .. }] => {
+                assert_eq!(found, &Token::Keyword(Keyword::Class));
+                assert_eq!(expected, &["end of file"]);
+            }
+            other => panic!("expected junk after the class, got {:?}", other),
+        },
+        Ok(_) => panic!("tokens after the class must not compile"),
+    }
+}
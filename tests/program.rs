@@ -0,0 +1,119 @@
+//! Compiles whole programs with the compiler binary: calls are resolved across the files of a
+//! directory, also when some of the files have syntax errors.
+
+extern crate jack_compiler;
+
+mod common;
+
+use common::{jack_compiler, outcome, TempDir};
+
+/// Compiles a directory of .jack files and returns whether that succeeded and the diagnostics
+fn compile(name: &str, files: &[(&str, &str)]) -> (bool, String) {
+    let directory = TempDir::new(&format!("program_{}", name), files);
+    let (success, _, stderr) = outcome(&jack_compiler([directory.path()]));
+    (success, stderr)
+}
+
+const MAIN: &str = "
+class Main {
+    function void main() {
+        var Point p;
+        let p = Point.new(1, 2);
+        do p.print();
+        do Point.count();
+        return;
+    }
+}
+";
+
+const POINT: &str = "
+class Point {
+    field int x, y;
+    constructor Point new(int ax, int ay) {
+        let x = ax;
+        let y = ay;
+        return this;
+    }
+    method void print() {
+        do Output.printInt(x);
+        return;
+    }
+    function int count() { return 0; }
+}
+";
+
+#[test]
+fn calls_are_resolved_across_files() {
+    assert_eq!(
+        compile("resolved", &[("Main.jack", MAIN), ("Point.jack", POINT)]),
+        (true, String::new())
+    );
+}
+
+#[test]
+fn wrong_calls_of_other_classes_are_reported() {
+    let main = MAIN
+        .replace("Point.new(1, 2)", "Point.new(1)")
+        .replace("Point.count()", "Point.size()");
+    let (success, stderr) = compile("wrong", &[("Main.jack", &main), ("Point.jack", POINT)]);
+    assert!(!success);
+    assert!(
+        stderr.contains("`Point.new` takes 2 arguments but 1 argument"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("cannot find subroutine `size` in class `Point`"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn classes_with_syntax_errors_are_known() {
+    // `print` keeps its signature, `count` is lost with its syntax error
+    let point = POINT
+        .replace("printInt(x)", "printInt(x +)")
+        .replace("return 0;", "return 0");
+    let main = MAIN.replace("Point.new(1, 2)", "Point.new(1)");
+    let (success, stderr) = compile("syntax", &[("Main.jack", &main), ("Point.jack", &point)]);
+    assert!(!success);
+    assert!(!stderr.contains("cannot find class"), "{}", stderr);
+    assert!(
+        !stderr.contains("cannot find subroutine `count`"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("`Point.new` takes 2 arguments"),
+        "{}",
+        stderr
+    );
+    assert_eq!(stderr.matches("error: expected").count(), 2, "{}", stderr);
+}
+
+#[test]
+fn classes_whose_name_cannot_be_parsed_are_known_by_their_file() {
+    let (success, stderr) = compile("unnamed", &[("Main.jack", MAIN), ("Point.jack", "class {")]);
+    assert!(!success);
+    assert!(!stderr.contains("cannot find"), "{}", stderr);
+}
+
+#[test]
+fn empty_files_are_reported_at_their_start() {
+    let (success, stderr) = compile(
+        "empty",
+        &[
+            ("Main.jack", MAIN),
+            ("Point.jack", POINT),
+            ("Empty.jack", "// nothing\n"),
+        ],
+    );
+    assert!(!success);
+    assert!(
+        stderr.contains("unexpected end of file, expected `class`"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("Empty.jack:1:1"), "{}", stderr);
+}
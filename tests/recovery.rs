@@ -0,0 +1,68 @@
+//! After a syntax error, the compiler continues with the parts of the class that could be
+//! parsed. The errors it finds there must not be caused by the parts that were left out.
+
+extern crate jack_compiler;
+
+use jack_compiler::compile_error::DEFAULT_MAX_ERRORS;
+use jack_compiler::jack_signatures::ProgramSignatures;
+use jack_compiler::jack_tokenizer::tokenize;
+use jack_compiler::{compile, compile_in_program, CompileError, JackParser};
+
+fn compile_errors(code: &str) -> Vec<CompileError> {
+    compile(&tokenize(code.to_string(), 0), 0, DEFAULT_MAX_ERRORS).unwrap_err()
+}
+
+fn is_syntax_error(error: &CompileError) -> bool {
+    matches!(error, CompileError::UnexpectedToken { .. })
+}
+
+const LOST_SUBROUTINES: &str = "
+class Main {
+    function void main() {
+        do helper(1);
+        do Main.helper(2);
+        do Output.printInt(1);
+        return;
+    }
+    function void helper(int x {
+        return;
+    }
+    method void print() {
+        do Output.printInt(x +);
+        return;
+    }
+}
+";
+
+#[test]
+fn calls_of_subroutines_with_syntax_errors_are_not_reported() {
+    let errors = compile_errors(LOST_SUBROUTINES);
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+    assert!(errors.iter().all(is_syntax_error), "{:?}", errors);
+
+    let tokens = tokenize(LOST_SUBROUTINES.to_string(), 0);
+    let (class, syntax_errors) = JackParser::new(&tokens, 0).parse_class_partial();
+    let program = ProgramSignatures::new().with_os_api();
+    let errors = compile_in_program(
+        &class.unwrap(),
+        &syntax_errors,
+        DEFAULT_MAX_ERRORS,
+        &program,
+        false,
+    )
+    .unwrap_err();
+    assert!(errors.iter().all(is_syntax_error), "{:?}", errors);
+}
+
+#[test]
+fn calls_of_undefined_subroutines_are_reported_without_syntax_errors() {
+    let code = LOST_SUBROUTINES
+        .replace("int x {", "int x) {")
+        .replace("x +", "1")
+        .replace("do helper(1)", "do helpr(1)");
+    let errors = compile_errors(&code);
+    match errors.as_slice() {
+        [CompileError::UndefinedSubroutine { name, .. }] => assert_eq!(name, "helpr"),
+        other => panic!("expected an undefined subroutine, got {:?}", other),
+    }
+}
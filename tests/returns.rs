@@ -0,0 +1,88 @@
+//! Definite-return analysis: a subroutine with a return type must return a value on every path,
+//! a void subroutine must not return one.
+
+extern crate jack_compiler;
+
+mod common;
+
+use common::{class, compile_class};
+use jack_compiler::CompileError;
+
+/// Compiles `function int f(boolean a)` with the given body
+fn compile_f(body: &str) -> Result<String, Vec<CompileError>> {
+    let function = format!("    function int f(boolean a) {{\n{}\n    }}", body);
+    compile_class(&class("Main", &function))
+}
+
+fn is_missing_return(errors: &[CompileError]) -> bool {
+    matches!(errors, [CompileError::MissingReturn { function, .. }] if function == "f")
+}
+
+#[test]
+fn returns_on_every_path() {
+    let bodies = [
+        "return 1;",
+        "if (a) { return 1; } else { return 2; }",
+        "if (a) { if (a) { return 1; } else { return 2; } } else { return 3; }",
+        "while (a) { let a = false; } return 1;",
+        "if (a) { let a = false; } return 1;",
+        "return 1; let a = false;",
+    ];
+    for body in &bodies {
+        assert!(compile_f(body).is_ok(), "{}", body);
+    }
+}
+
+#[test]
+fn can_reach_the_end() {
+    let bodies = [
+        "let a = false;",
+        "if (a) { return 1; }",
+        "if (a) { return 1; } else { let a = false; }",
+        "if (a) { if (a) { return 1; } } else { return 2; }",
+        "while (a) { return 1; }",
+        "while (true) { if (a) { return 1; } else { return 2; } }",
+    ];
+    for body in &bodies {
+        match compile_f(body) {
+            Err(errors) => assert!(is_missing_return(&errors), "{}: {:?}", body, errors),
+            Ok(_) => panic!("{} must not compile", body),
+        }
+    }
+}
+
+#[test]
+fn return_values_must_match_the_return_type() {
+    match compile_f("return;").unwrap_err().as_slice() {
+        [CompileError::MissingReturnValue { function, .. }] => assert_eq!(function, "f"),
+        other => panic!("expected a missing return value, got {:?}", other),
+    }
+    let code = class("Main", "    function void g() {\n        return 1;\n    }");
+    match compile_class(&code).unwrap_err().as_slice() {
+        [CompileError::ReturnValueInVoid { function, .. }] => assert_eq!(function, "g"),
+        other => panic!(
+            "expected a return value in a void function, got {:?}",
+            other
+        ),
+    }
+}
+
+#[test]
+fn syntax_errors_do_not_cause_missing_returns() {
+    let bodies = [
+        "return a + ;",
+        "if (a) { return 1; } else { return 2 + ; }",
+        "while (a) { let a = ; } return 1",
+    ];
+    for body in &bodies {
+        let errors = compile_f(body).unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .all(|error| matches!(error, CompileError::UnexpectedToken { .. })),
+            "{}: {:?}",
+            body,
+            errors
+        );
+    }
+}
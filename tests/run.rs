@@ -0,0 +1,138 @@
+//! Runs programs with the `run` subcommand, with the builtin OS and with the bundled Jack OS.
+//! Both must print the same text and end when the program halts.
+
+extern crate jack_compiler;
+
+mod common;
+
+use std::ffi::OsStr;
+
+use common::{jack_compiler, outcome, TempDir};
+
+/// Writes `Main.jack` to a new directory, runs it and returns whether the run succeeded,
+/// stdout and stderr
+fn run(name: &str, main: &str, arguments: &[&str]) -> (bool, String, String) {
+    let directory = TempDir::new(&format!("run_{}", name), &[("Main.jack", main)]);
+    let input = directory.join("Main.jack");
+    let mut command_line = vec![OsStr::new("run"), input.as_os_str()];
+    command_line.extend(arguments.iter().map(OsStr::new));
+    outcome(&jack_compiler(command_line))
+}
+
+const HELLO: &str = r#"
+class Main {
+    function void main() {
+        do Output.printString("Hello");
+        do Output.printChar(String.backSpace());
+        do Output.println();
+        do Output.printInt(-1234);
+        return;
+    }
+}
+"#;
+
+#[test]
+fn prints_text_and_halts() {
+    for arguments in &[&[][..], &["--link-os"][..]] {
+        let (success, stdout, stderr) = run("hello", HELLO, arguments);
+        assert!(success, "{:?}: {}", arguments, stderr);
+        assert_eq!(stdout, "Hell\n-1234\n", "{:?}", arguments);
+        assert!(
+            !stderr.contains("stopped after"),
+            "{:?}: {}",
+            arguments,
+            stderr
+        );
+    }
+}
+
+#[test]
+fn sys_error_stops_the_program() {
+    let main = HELLO.replace("return;", "do Sys.error(3);\n        return;");
+    for arguments in &[&[][..], &["--link-os"][..]] {
+        let (success, stdout, stderr) = run("error", &main, arguments);
+        assert!(!success, "{:?}", arguments);
+        assert_eq!(stdout, "Hell\n-1234ERR3\n", "{:?}", arguments);
+        assert!(
+            stderr.contains("the program stopped with Sys.error(3)"),
+            "{:?}: {}",
+            arguments,
+            stderr
+        );
+    }
+}
+
+#[test]
+fn programs_that_do_not_halt_are_stopped() {
+    let main = HELLO.replace("return;", "while (true) {}\n        return;");
+    let (success, stdout, stderr) = run("loop", &main, &["--max-steps", "100000"]);
+    assert!(success);
+    assert_eq!(stdout, "Hell\n-1234\n");
+    assert!(stderr.contains("stopped after 100000 steps"), "{}", stderr);
+}
+
+#[test]
+fn freed_blocks_are_merged() {
+    // the three blocks are freed in an order that merges with the following block, the
+    // preceding block and both, until the heap is one free block again
+    let main = r#"
+class Main {
+    function void main() {
+        var Array a, b, c;
+        let a = Array.new(3000);
+        let b = Array.new(3000);
+        let c = Array.new(3000);
+        do b.dispose();
+        do a.dispose();
+        do c.dispose();
+        let a = Array.new(10000);
+        do Output.printString("done");
+        return;
+    }
+}
+"#;
+    for arguments in &[&[][..], &["--link-os"][..]] {
+        let (success, stdout, stderr) = run("heap", main, arguments);
+        assert!(success, "{:?}: {}", arguments, stderr);
+        assert_eq!(stdout, "done\n", "{:?}", arguments);
+    }
+}
+
+#[test]
+fn os_classes_of_the_user_are_not_replaced() {
+    let sys = r#"
+class Sys {
+    function void init() {
+        do Memory.init();
+        do Math.init();
+        do Output.init();
+        do Main.main();
+        do Sys.halt();
+        return;
+    }
+
+    function void error(int code) {
+        do Output.printInt(code);
+        do Sys.halt();
+        return;
+    }
+
+    function void halt() {
+        do Output.printString("halt");
+        while (true) {}
+        return;
+    }
+}
+"#;
+    let directory = TempDir::new("run_user_sys", &[("Main.jack", HELLO), ("Sys.jack", sys)]);
+    let (success, stdout, stderr) = outcome(&jack_compiler([
+        OsStr::new("run"),
+        directory.path().as_os_str(),
+        OsStr::new("--link-os"),
+        OsStr::new("--max-steps"),
+        OsStr::new("1000000"),
+    ]));
+    assert!(success, "{}", stderr);
+    assert_eq!(stdout, "Hell\n-1234halt\n");
+    assert!(stderr.contains("stopped after 1000000 steps"), "{}", stderr);
+}
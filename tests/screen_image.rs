@@ -0,0 +1,177 @@
+//! Screen images: the checksums of the PNG encoder, the layout of PBM and PNG files of the
+//! 512x256 screen, and the `--screen` and `--screen-at` options of `run`.
+
+extern crate jack_compiler;
+
+mod common;
+
+use std::ffi::OsStr;
+use std::fs;
+
+use common::{jack_compiler, outcome, TempDir};
+use jack_compiler::screen_image::{adler32, crc32, to_pbm, to_png, SCREEN_WORDS};
+
+const PBM_HEADER: &[u8] = b"P4\n512 256\n";
+
+/// A screen with only the pixel in the top left corner set
+fn top_left_pixel() -> Vec<i16> {
+    let mut screen = vec![0; SCREEN_WORDS];
+    screen[0] = 1;
+    screen
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[test]
+fn checksums_of_known_vectors() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    assert_eq!(adler32(b""), 1);
+    assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    assert_eq!(adler32(&[0xff; 70000]), 0x2a28_6e81);
+}
+
+#[test]
+fn pbm_header_and_size() {
+    let image = to_pbm(&top_left_pixel());
+    assert!(image.starts_with(PBM_HEADER));
+    assert_eq!(image.len(), PBM_HEADER.len() + 512 / 8 * 256);
+    // a set bit is black, the leftmost pixel is the most significant bit
+    assert_eq!(image[PBM_HEADER.len()], 0x80);
+    assert!(image[PBM_HEADER.len() + 1..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn png_chunk_layout() {
+    let image = to_png(&top_left_pixel());
+    assert!(image.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    // length, type, data and CRC of type and data
+    let mut chunks = vec![];
+    let mut rest = &image[8..];
+    while !rest.is_empty() {
+        let len = be_u32(rest) as usize;
+        let (chunk, next) = rest.split_at(12 + len);
+        assert_eq!(be_u32(&chunk[8 + len..]), crc32(&chunk[4..8 + len]));
+        chunks.push((chunk[4..8].to_vec(), chunk[8..8 + len].to_vec()));
+        rest = next;
+    }
+    let types: Vec<&[u8]> = chunks
+        .iter()
+        .map(|(chunk_type, _)| &chunk_type[..])
+        .collect();
+    assert_eq!(types, [&b"IHDR"[..], b"IDAT", b"IEND"]);
+
+    // 512x256, bit depth 1, grayscale, deflate, adaptive filtering, no interlace
+    assert_eq!(chunks[0].1, [0, 0, 2, 0, 0, 0, 1, 0, 1, 0, 0, 0, 0]);
+    assert!(chunks[2].1.is_empty());
+
+    // a zlib stream of stored blocks holding 256 scanlines of filter type 0 and 64 bytes
+    let stream = &chunks[1].1;
+    assert_eq!(stream[..2], [0x78, 0x01]);
+    let mut scanlines = vec![];
+    let mut position = 2;
+    loop {
+        let is_final = stream[position] == 1;
+        let len = usize::from(u16::from_le_bytes([
+            stream[position + 1],
+            stream[position + 2],
+        ]));
+        let nlen = u16::from_le_bytes([stream[position + 3], stream[position + 4]]);
+        assert_eq!(nlen, !(len as u16));
+        scanlines.extend(&stream[position + 5..position + 5 + len]);
+        position += 5 + len;
+        if is_final {
+            break;
+        }
+    }
+    assert_eq!(be_u32(&stream[position..]), adler32(&scanlines));
+    assert_eq!(position + 4, stream.len());
+    assert_eq!(scanlines.len(), 256 * (1 + 64));
+    // a cleared bit is black
+    assert_eq!(scanlines[..2], [0, 0x7f]);
+    assert!(scanlines.chunks(65).all(|scanline| scanline[0] == 0));
+    assert!(scanlines[2..]
+        .iter()
+        .enumerate()
+        .all(|(i, &byte)| (i + 2) % 65 == 0 || byte == 0xff));
+}
+
+const DRAW_AND_LOOP: &str = "
+class Main {
+    function void main() {
+        do Screen.drawPixel(0, 0);
+        while (true) {}
+        return;
+    }
+}
+";
+
+#[test]
+fn screen_options() {
+    let directory = TempDir::new("screen_options", &[("Main.jack", DRAW_AND_LOOP)]);
+    let input = directory.join("Main.jack");
+    let screen = directory.join("screen.pbm");
+    let (success, _, stderr) = outcome(&jack_compiler([
+        OsStr::new("run"),
+        input.as_os_str(),
+        OsStr::new("--max-steps"),
+        OsStr::new("2000"),
+        OsStr::new("--screen"),
+        screen.as_os_str(),
+        OsStr::new("--screen-at"),
+        OsStr::new("1000,1,5000"),
+    ]));
+    assert!(success, "{}", stderr);
+    // before the pixel is drawn, after it, and when the program is stopped
+    let blank = to_pbm(&vec![0; SCREEN_WORDS]);
+    let drawn = to_pbm(&top_left_pixel());
+    assert_eq!(fs::read(directory.join("screen-1.pbm")).unwrap(), blank);
+    assert_eq!(fs::read(directory.join("screen-1000.pbm")).unwrap(), drawn);
+    assert_eq!(fs::read(&screen).unwrap(), drawn);
+    // steps after the end of the run are not reached
+    assert!(!directory.join("screen-5000.pbm").exists());
+
+    let screen = directory.join("screen.png");
+    let output = jack_compiler([
+        OsStr::new("run"),
+        input.as_os_str(),
+        OsStr::new("--max-steps"),
+        OsStr::new("2000"),
+        OsStr::new("--screen"),
+        screen.as_os_str(),
+    ]);
+    assert!(output.status.success());
+    assert_eq!(fs::read(&screen).unwrap(), to_png(&top_left_pixel()));
+}
+
+#[test]
+fn bad_screen_options() {
+    let directory = TempDir::new("bad_screen_options", &[("Main.jack", DRAW_AND_LOOP)]);
+    let input = directory.join("Main.jack");
+    let run = |arguments: &[&str]| {
+        let mut command_line = vec![OsStr::new("run"), input.as_os_str()];
+        command_line.extend(arguments.iter().map(OsStr::new));
+        outcome(&jack_compiler(command_line))
+    };
+    let (success, _, stderr) = run(&["--screen", "screen.gif"]);
+    assert!(!success);
+    assert!(
+        stderr.contains("unknown image format of screen.gif"),
+        "{}",
+        stderr
+    );
+    let (success, _, stderr) = run(&["--screen", "screen.pbm", "--screen-at", "10,ten"]);
+    assert!(!success);
+    assert!(
+        stderr.contains("invalid value for --screen-at"),
+        "{}",
+        stderr
+    );
+    // --screen-at needs a file to write to
+    let (success, _, _) = run(&["--screen-at", "10"]);
+    assert!(!success);
+}
@@ -0,0 +1,73 @@
+//! Functions have no object: using a field or `this` in a function is an error, in methods and
+//! constructors it is not.
+
+extern crate jack_compiler;
+
+mod common;
+
+use common::{class, compile_class};
+use jack_compiler::CompileError;
+
+/// A class with the fields `x` and `next`, the static `s` and a subroutine `f` of the given kind
+fn compile_subroutine(kind: &str, body: &str) -> Result<String, Vec<CompileError>> {
+    let declarations = format!(
+        "    field int x;
+    field Main next;
+    static int s;
+    {} Main f() {{
+        {}
+        return null;
+    }}",
+        kind, body
+    );
+    compile_class(&class("Main", &declarations))
+}
+
+fn field_in_function(body: &str) -> String {
+    match compile_subroutine("function", body).unwrap_err().as_slice() {
+        [CompileError::FieldInFunction { name, function, .. }] => {
+            assert_eq!(function, "f");
+            name.clone()
+        }
+        other => panic!("expected a field in a function, got {:?}", other),
+    }
+}
+
+#[test]
+fn fields_in_functions() {
+    assert_eq!(field_in_function("let s = x;"), "x");
+    assert_eq!(field_in_function("let x = 1;"), "x");
+    assert_eq!(field_in_function("do next.f();"), "next");
+    assert!(compile_subroutine("function", "let s = s + 1;").is_ok());
+}
+
+#[test]
+fn this_in_functions() {
+    match compile_subroutine("function", "do Main.g(this);")
+        .unwrap_err()
+        .as_slice()
+    {
+        [CompileError::ThisInFunction { function, span }] => {
+            assert_eq!(function, "f");
+            assert_eq!(span.len, 4);
+        }
+        other => panic!("expected `this` in a function, got {:?}", other),
+    }
+}
+
+#[test]
+fn fields_and_this_in_methods_and_constructors() {
+    let body = "let x = s; do next.f(); do Main.g(this);";
+    let vm_code = compile_subroutine("method", body).unwrap();
+    assert!(
+        vm_code.contains("push static 0\npop this 0\n"),
+        "{}",
+        vm_code
+    );
+    assert!(
+        vm_code.contains("push pointer 0\ncall Main.g 1\n"),
+        "{}",
+        vm_code
+    );
+    assert!(compile_subroutine("constructor", body).is_ok());
+}
@@ -0,0 +1,71 @@
+//! Variables are looked up in one scoped symbol table: parameters and locals shadow statics and
+//! fields, and every kind of variable can be the object of a method call.
+
+extern crate jack_compiler;
+
+mod common;
+
+use common::compile_class;
+use jack_compiler::jack_ast::{JackVariableType, VariableKind};
+use jack_compiler::symbol_table::SymbolTable;
+
+#[test]
+fn subroutine_scope_shadows_class_scope() {
+    let mut table = SymbolTable::new();
+    assert!(table.define("x", JackVariableType::Jint, VariableKind::Jfield));
+    assert!(table.define("y", JackVariableType::Jint, VariableKind::Jstatic));
+    assert!(table.define("x", JackVariableType::Jboolean, VariableKind::Jarg));
+    assert!(!table.define("y", JackVariableType::Jint, VariableKind::Jfield));
+    assert_eq!(table.lookup("x").unwrap().vm_location(), "argument 0");
+    assert_eq!(table.lookup("y").unwrap().vm_location(), "static 0");
+
+    table.start_subroutine();
+    assert_eq!(table.lookup("x").unwrap().vm_location(), "this 0");
+    assert_eq!(table.count(VariableKind::Jarg), 0);
+    assert_eq!(table.count(VariableKind::Jfield), 1);
+}
+
+#[test]
+fn every_kind_of_variable_is_a_receiver() {
+    let vm_code = compile_class(
+        "class Main {
+    static Point origin;
+    field Point corner;
+    method void draw(Point p) {
+        var Point q;
+        do origin.draw();
+        do corner.draw();
+        do p.draw();
+        do q.draw();
+        return;
+    }
+}
+",
+    )
+    .unwrap();
+    for location in &["static 0", "this 0", "argument 1", "local 0"] {
+        let call = format!("push {}\ncall Point.draw 1\n", location);
+        assert!(vm_code.contains(&call), "{}: {}", call, vm_code);
+    }
+}
+
+#[test]
+fn locals_shadow_statics_as_receivers() {
+    let vm_code = compile_class(
+        "class Main {
+    static Point p;
+    function void main() {
+        var Circle p;
+        do p.draw();
+        return;
+    }
+}
+",
+    )
+    .unwrap();
+    assert!(
+        vm_code.contains("push local 0\ncall Circle.draw 1\n"),
+        "{}",
+        vm_code
+    );
+}
@@ -0,0 +1,142 @@
+//! The `.tst` test scripts of the VM emulator: loading programs, setting and writing values and
+//! comparing the output to a compare file.
+
+extern crate jack_compiler;
+
+mod common;
+
+use common::TempDir;
+use jack_compiler::test_script::{run, ComparisonFailure, TestScriptError};
+
+/// The `SimpleAdd.vm` program of project 7
+const SIMPLE_ADD: &str = "push constant 7\npush constant 8\nadd\n";
+
+/// The `SimpleAddVME.tst` script of project 7
+const SIMPLE_ADD_SCRIPT: &str = "
+load SimpleAdd.vm,
+output-file SimpleAdd.out,
+compare-to SimpleAdd.cmp,
+output-list RAM[0]%D2.6.2 RAM[256]%D2.6.2;
+
+set sp 256,
+
+repeat 3 {      // SimpleAdd.vm has 3 instructions
+  vmstep;
+}
+
+output;        // the stack pointer and the stack base
+";
+
+const SIMPLE_ADD_OUTPUT: &str = "|  RAM[0]  | RAM[256] |\n|     257  |      15  |\n";
+
+#[test]
+fn simple_add_of_project_7() {
+    let directory = TempDir::new(
+        "test_script_simple_add",
+        &[
+            ("SimpleAdd.vm", SIMPLE_ADD),
+            ("SimpleAdd.cmp", SIMPLE_ADD_OUTPUT),
+        ],
+    );
+    let test_run = run(SIMPLE_ADD_SCRIPT, directory.path()).unwrap();
+    assert_eq!(test_run.output, SIMPLE_ADD_OUTPUT);
+    assert_eq!(test_run.output_file, Some(directory.join("SimpleAdd.out")));
+    assert!(test_run.compared);
+    assert_eq!(test_run.comparison_failure, None);
+}
+
+#[test]
+fn script_stops_at_the_first_comparison_failure() {
+    let compare_file = "|  RAM[0]  | RAM[256] |\n|     257  |      16  |\n|  extra   |\n";
+    let directory = TempDir::new(
+        "test_script_comparison_failure",
+        &[
+            ("SimpleAdd.vm", SIMPLE_ADD),
+            ("SimpleAdd.cmp", compare_file),
+        ],
+    );
+    let test_run = run(&format!("{}output;\n", SIMPLE_ADD_SCRIPT), directory.path()).unwrap();
+    assert_eq!(test_run.output, SIMPLE_ADD_OUTPUT);
+    assert_eq!(
+        test_run.comparison_failure,
+        Some(ComparisonFailure {
+            line: 2,
+            expected: "|     257  |      16  |".to_string(),
+            actual: "|     257  |      15  |".to_string(),
+        })
+    );
+}
+
+#[test]
+fn segments_and_formats() {
+    let directory = TempDir::new(
+        "test_script_segments",
+        &[(
+            "Segments.vm",
+            "push argument 1\npush constant 1\nadd\npop local 0\n",
+        )],
+    );
+    let script = "
+        load Segments.vm,
+        output-list local[0]%X1.4.1 argument[1]%B1.8.1 temp[2]%S1.3.1 sp;
+        set sp 300, set local 300, set argument 400,
+        set argument[1] %X00FF, set temp[2] -5,
+        repeat 4 { vmstep; }
+        output;
+    ";
+    let test_run = run(script, directory.path()).unwrap();
+    assert_eq!(
+        test_run.output,
+        "|local[|argument[1|temp[|   sp   |\n| 0100 | 11111111 | -5  |    300 |\n"
+    );
+    assert!(!test_run.compared);
+}
+
+#[test]
+fn load_without_a_file_starts_at_sys_init() {
+    let directory = TempDir::new(
+        "test_script_directory",
+        &[
+            (
+                "Sys.vm",
+                "function Sys.init 0\npush constant 4\ncall Main.double 1\npop temp 0\n\
+                 label HALT\ngoto HALT\n",
+            ),
+            (
+                "Main.vm",
+                "function Main.double 0\npush argument 0\npush argument 0\nadd\nreturn\n",
+            ),
+        ],
+    );
+    let script = "load, set sp 256, output-list temp[0]%D1.3.1; repeat 20 { vmstep; } output;";
+    let test_run = run(script, directory.path()).unwrap();
+    assert_eq!(test_run.output, "|temp[|\n|   8 |\n");
+}
+
+#[test]
+fn invalid_scripts() {
+    let error =
+        |script: &str| -> TestScriptError { run(script, &std::env::temp_dir()).unwrap_err() };
+    assert_eq!(
+        error("set sp 256,\n\nticktock;"),
+        TestScriptError {
+            line: 3,
+            message: "unsupported command `ticktock`".to_string()
+        }
+    );
+    assert_eq!(error("vmstep;").message, "no program is loaded");
+    assert_eq!(error("set RAM[0] 40000;").message, "invalid value `40000`");
+    assert_eq!(error("set pc 0;").message, "unknown variable `pc`");
+    assert_eq!(
+        error("output-list RAM[0]%Q1.6.1;").message,
+        "invalid output format `RAM[0]%Q1.6.1`"
+    );
+    assert_eq!(
+        error("repeat 3 { vmstep;").message,
+        "expected `}` at the end of `repeat`"
+    );
+    assert_eq!(
+        error("vmstep").message,
+        "expected `,`, `;` or `!` after the command"
+    );
+}
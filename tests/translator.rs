@@ -0,0 +1,140 @@
+//! The Hack assembly of the VM translator must compute the same results as the VM interpreter.
+//! The assembly is run on a minimal Hack CPU.
+
+extern crate jack_compiler;
+
+use jack_compiler::hack_assembler;
+use jack_compiler::vm_command;
+use jack_compiler::vm_translator;
+
+const RAM_SIZE: usize = 32768;
+const MAX_CYCLES: usize = 100_000;
+
+/// Runs a Hack program until the program counter leaves it and returns the RAM
+fn run_hack(program: &[u16], mut ram: Vec<i16>) -> Vec<i16> {
+    let (mut a, mut d, mut pc) = (0i16, 0i16, 0usize);
+    for _ in 0..MAX_CYCLES {
+        let instruction = match program.get(pc) {
+            Some(&instruction) => instruction,
+            None => return ram,
+        };
+        if instruction & 0x8000 == 0 {
+            a = instruction as i16;
+            pc += 1;
+            continue;
+        }
+        let address = a as u16 as usize;
+        let mut x = d;
+        let mut y = if instruction & 0x1000 != 0 {
+            ram[address]
+        } else {
+            a
+        };
+        let control = (instruction >> 6) & 0x3f;
+        if control & 0x20 != 0 {
+            x = 0;
+        }
+        if control & 0x10 != 0 {
+            x = !x;
+        }
+        if control & 0x08 != 0 {
+            y = 0;
+        }
+        if control & 0x04 != 0 {
+            y = !y;
+        }
+        let mut out = if control & 0x02 != 0 {
+            x.wrapping_add(y)
+        } else {
+            x & y
+        };
+        if control & 0x01 != 0 {
+            out = !out;
+        }
+        let dest = (instruction >> 3) & 0x7;
+        let jump = instruction & 0x7;
+        let jumps =
+            (jump & 4 != 0 && out < 0) || (jump & 2 != 0 && out == 0) || (jump & 1 != 0 && out > 0);
+        if dest & 1 != 0 {
+            ram[address] = out;
+        }
+        if dest & 2 != 0 {
+            d = out;
+        }
+        if dest & 4 != 0 {
+            a = out;
+        }
+        pc = if jumps { address } else { pc + 1 };
+    }
+    panic!("the program did not end after {} cycles", MAX_CYCLES);
+}
+
+/// Pushes `value` in VM code, also for values that are not constants
+fn push(value: i16) -> String {
+    if value >= 0 {
+        format!("push constant {}\n", value)
+    } else {
+        format!("push constant {}\nnot\n", !value)
+    }
+}
+
+/// Runs `x command y` translated to Hack assembly and returns the top of the stack
+fn compare_in_hack(x: i16, y: i16, command: &str) -> i16 {
+    let vm_code = format!("{}{}{}\n", push(x), push(y), command);
+    let commands = vm_command::parse(&vm_code).unwrap();
+    let asm = vm_translator::translate(&[("Test".to_string(), commands)], false);
+    let program = hack_assembler::assemble(&asm).unwrap();
+    let mut ram = vec![0; RAM_SIZE];
+    ram[0] = 256;
+    let ram = run_hack(&program, ram);
+    assert_eq!(ram[0], 257, "{}", vm_code);
+    ram[256]
+}
+
+fn truth(b: bool) -> i16 {
+    if b {
+        -1
+    } else {
+        0
+    }
+}
+
+#[test]
+fn comparisons_of_operands_with_different_signs() {
+    assert_eq!(compare_in_hack(-30000, 30000, "lt"), -1);
+    assert_eq!(compare_in_hack(30000, -30000, "gt"), -1);
+    assert_eq!(compare_in_hack(-32768, 1, "lt"), -1);
+    assert_eq!(compare_in_hack(30000, -30000, "lt"), 0);
+    assert_eq!(compare_in_hack(-30000, 30000, "gt"), 0);
+    assert_eq!(compare_in_hack(-32768, 0, "eq"), 0);
+}
+
+#[test]
+fn comparisons_match_the_interpreter() {
+    let values = [-32768, -30000, -2, -1, 0, 1, 2, 30000, 32767];
+    for &x in &values {
+        for &y in &values {
+            assert_eq!(compare_in_hack(x, y, "lt"), truth(x < y), "{} lt {}", x, y);
+            assert_eq!(compare_in_hack(x, y, "gt"), truth(x > y), "{} gt {}", x, y);
+            assert_eq!(compare_in_hack(x, y, "eq"), truth(x == y), "{} eq {}", x, y);
+        }
+    }
+}
+
+#[test]
+fn calls_of_undefined_functions_are_unresolved() {
+    let main = vm_command::parse(
+        "function Main.main 0\ncall Main.helper 0\ncall Output.printInt 1\ncall Math.multiply 2\n",
+    )
+    .unwrap();
+    let helper = vm_command::parse("function Main.helper 0\ncall Output.printInt 1\n").unwrap();
+    let files = vec![("Main".to_string(), main), ("Helper".to_string(), helper)];
+    assert_eq!(
+        vm_translator::unresolved_calls(&files, false),
+        vec!["Math.multiply", "Output.printInt"]
+    );
+    assert_eq!(
+        vm_translator::unresolved_calls(&files, true),
+        vec!["Math.multiply", "Output.printInt", "Sys.init"]
+    );
+}
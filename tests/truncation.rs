@@ -0,0 +1,100 @@
+//! A class that is cut off at any token must produce an "unexpected end of file" error
+//! instead of a panic.
+
+extern crate jack_compiler;
+
+use jack_compiler::jack_tokenizer::tokenize;
+use jack_compiler::compile_error::DEFAULT_MAX_ERRORS;
+use jack_compiler::{compile, CompileError, JackParser};
+
+/// Uses every production of the Jack grammar at least once
+const COMPLETE_CLASS: &str = r#"
+class Point {
+    static int count;
+    field int x, y;
+    field Array history;
+
+    constructor Point new(int ax, int ay) {
+        let x = ax;
+        let y = ay;
+        let count = count + 1;
+        return this;
+    }
+
+    method int manhattan(Point other, boolean absolute) {
+        var int dx, dy;
+        var String label;
+        let dx = x - other.getX();
+        let dy = -(y - other.getY());
+        if (absolute & (dx < 0)) {
+            let dx = ~dx + 1;
+        } else {
+            let label = "relative";
+        }
+        while ((dy < 0) | false) {
+            let dy = dy + 1;
+        }
+        let history[count] = dx * dy / 2;
+        do Output.printInt(history[count]);
+        do reset();
+        if (dx = null) {
+            return 0;
+        }
+        return dx + dy;
+    }
+
+    method int getX() { return x; }
+    method int getY() { return y; }
+
+    method void reset() {
+        let x = 0;
+        return;
+    }
+
+    function char letter() {
+        return 65;
+    }
+}
+"#;
+
+#[test]
+fn complete_class_compiles() {
+    let tokens = tokenize(COMPLETE_CLASS.to_string(), 0);
+    assert!(compile(&tokens, 0, DEFAULT_MAX_ERRORS).is_ok());
+}
+
+#[test]
+fn truncation_at_every_token_reports_unexpected_eof() {
+    let tokens = tokenize(COMPLETE_CLASS.to_string(), 0);
+    for cut in 0..tokens.len() {
+        let result = JackParser::new(&tokens[..cut], 0).parse_class();
+        match result.as_ref().map_err(|errors| errors.as_slice()) {
+            Err([CompileError::UnexpectedEof { expected, span }]) => {
+                assert!(!expected.is_empty(), "no alternatives after {} tokens", cut);
+                if let Some(last) = tokens[..cut].last() {
+                    assert_eq!(span.offset, last.span.end());
+                    assert_eq!(span.line, last.span.line);
+                }
+            }
+            other => panic!(
+                "expected an unexpected end of file after {} tokens, got {:?}",
+                cut, other
+            ),
+        }
+    }
+}
+
+#[test]
+fn empty_file_expects_class() {
+    let tokens = tokenize(String::new(), 3);
+    let result = JackParser::new(&tokens, 3).parse_class();
+    match result.as_ref().map_err(|errors| errors.as_slice()) {
+        Err([CompileError::UnexpectedEof { expected, span }]) => {
+            assert_eq!(expected, &["class"]);
+            // the start of the empty file, not a span without a file
+            assert_eq!((span.file_id, span.line, span.column), (3, 1, 1));
+            assert_eq!(span.len, 0);
+        }
+        other => panic!("expected an unexpected end of file, got {:?}", other),
+    }
+}
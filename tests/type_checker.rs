@@ -0,0 +1,101 @@
+//! The optional type checker: values of unknown type, like array elements, are accepted
+//! everywhere, including as operands of `~`, `&` and `|`.
+
+extern crate jack_compiler;
+
+mod common;
+
+use common::class;
+use jack_compiler::jack_signatures::ProgramSignatures;
+use jack_compiler::jack_tokenizer::tokenize;
+use jack_compiler::type_checker::check_class;
+use jack_compiler::{CompileError, JackParser};
+
+/// The type errors of a class with the function `f(int i, boolean b, Array a, String s)` whose
+/// body is `body`
+fn type_errors(body: &str) -> Vec<CompileError> {
+    let function = format!(
+        "    function void f(int i, boolean b, Array a, String s) {{\n        \
+         var Array visited;\n{}\n        return;\n    }}",
+        body
+    );
+    let tokens = tokenize(class("Main", &function), 0);
+    let class = JackParser::new(&tokens, 0).parse_class().unwrap();
+    let mut program = ProgramSignatures::new().with_os_api();
+    program.add_class(&class);
+    check_class(&class, &program)
+}
+
+fn assert_no_type_errors(body: &str) {
+    let errors = type_errors(body);
+    assert!(errors.is_empty(), "{}: {:?}", body, errors);
+}
+
+#[test]
+fn array_elements_are_accepted_everywhere() {
+    assert_no_type_errors("if (~visited[i]) { let b = a[0]; }");
+    assert_no_type_errors("if (a[0] & a[1]) { let i = a[0] | a[1]; }");
+    assert_no_type_errors("while (a[0] | (a[1] & b)) { let i = ~a[i]; }");
+    assert_no_type_errors("let b = ~(a[0] & a[1]); let i = -a[0];");
+    assert_no_type_errors("let s = a[0]; do Output.printString(a[i]);");
+}
+
+#[test]
+fn logical_and_bitwise_operators() {
+    assert_no_type_errors("let b = ~b & (b | false); let i = ~i & (i | 1);");
+    assert_no_type_errors("let b = a[0] & b; let i = a[0] & i;");
+    match type_errors("let b = ~i;").as_slice() {
+        [CompileError::TypeMismatch {
+            expected, found, ..
+        }] => {
+            assert_eq!((expected.as_str(), found.as_str()), ("boolean", "int"));
+        }
+        other => panic!("expected a type mismatch, got {:?}", other),
+    }
+    match type_errors("let i = b & i;").as_slice() {
+        [CompileError::TypeMismatch { .. }] => {}
+        other => panic!("expected a type mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn conditions_must_be_boolean() {
+    match type_errors("if (i + 1) { let i = 0; }").as_slice() {
+        [CompileError::TypeMismatch { expected, .. }] => assert_eq!(expected, "boolean"),
+        other => panic!("expected a type mismatch, got {:?}", other),
+    }
+    match type_errors("while (s) { let i = 0; }").as_slice() {
+        [CompileError::TypeMismatch {
+            expected, found, ..
+        }] => {
+            assert_eq!((expected.as_str(), found.as_str()), ("boolean", "String"));
+        }
+        other => panic!("expected a type mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn operands_of_arithmetic_must_be_numeric() {
+    assert_no_type_errors("let i = i * a[0] + a - s.length();");
+    match type_errors("let i = i + b;").as_slice() {
+        [CompileError::InvalidOperand {
+            operator, found, ..
+        }] => {
+            assert_eq!((operator.as_str(), found.as_str()), ("+", "boolean"));
+        }
+        other => panic!("expected an invalid operand, got {:?}", other),
+    }
+}
+
+#[test]
+fn arguments_must_match_the_parameters() {
+    assert_no_type_errors("do Output.printInt(a[0]); do Output.printString(null);");
+    match type_errors("do Output.printInt(s);").as_slice() {
+        [CompileError::TypeMismatch {
+            expected, found, ..
+        }] => {
+            assert_eq!((expected.as_str(), found.as_str()), ("int", "String"));
+        }
+        other => panic!("expected a type mismatch, got {:?}", other),
+    }
+}
@@ -0,0 +1,91 @@
+//! `--emit xml` writes the token and parse tree files of project 10. The programs of the project
+//! are in `tests/project10`, together with the `*T.xml` and `*.xml` files that the output must
+//! match when compared like the course's TextComparer does.
+
+extern crate jack_compiler;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{jack_compiler, outcome, TempDir};
+
+/// The lines of a file as the TextComparer compares them, without any whitespace
+fn text_comparer_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.chars().filter(|c| !c.is_whitespace()).collect())
+        .collect()
+}
+
+/// Compiles the program `name` of `tests/project10` with `--emit xml` and compares every
+/// generated file with the expected one
+fn assert_matches_project10(name: &str) {
+    let expected_directory = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/project10")
+        .join(name);
+    let mut sources = vec![];
+    let mut expected_files = vec![];
+    for entry in fs::read_dir(&expected_directory).unwrap() {
+        let path = entry.unwrap().path();
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let content = fs::read_to_string(&path).unwrap();
+        if file_name.ends_with(".jack") {
+            sources.push((file_name, content));
+        } else {
+            expected_files.push((file_name, content));
+        }
+    }
+    let files: Vec<(&str, &str)> = sources
+        .iter()
+        .map(|(file_name, content)| (file_name.as_str(), content.as_str()))
+        .collect();
+    let directory = TempDir::new(&format!("xml_{}", name), &files);
+
+    let (success, _, stderr) = outcome(&jack_compiler([
+        directory.path().as_os_str(),
+        "--emit".as_ref(),
+        "xml".as_ref(),
+    ]));
+    assert!(success, "{}: {}", name, stderr);
+    assert_eq!(expected_files.len(), 2 * sources.len());
+    for (file_name, expected) in &expected_files {
+        let written = fs::read_to_string(directory.join(file_name)).unwrap();
+        let expected_lines = text_comparer_lines(expected);
+        let written_lines = text_comparer_lines(&written);
+        for (number, (written_line, expected_line)) in
+            written_lines.iter().zip(&expected_lines).enumerate()
+        {
+            assert_eq!(
+                written_line,
+                expected_line,
+                "{}/{}, line {}",
+                name,
+                file_name,
+                number + 1
+            );
+        }
+        assert_eq!(
+            written_lines.len(),
+            expected_lines.len(),
+            "{}/{}",
+            name,
+            file_name
+        );
+    }
+}
+
+#[test]
+fn array_test() {
+    assert_matches_project10("ArrayTest");
+}
+
+#[test]
+fn square() {
+    assert_matches_project10("Square");
+}
+
+#[test]
+fn expression_less_square() {
+    assert_matches_project10("ExpressionLessSquare");
+}